@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE watch_entries (
+    name VARCHAR(255) NOT NULL,
+    kind VARCHAR(255) NOT NULL,
+    query TEXT NOT NULL,
+    color VARCHAR(255) NULL,
+    PRIMARY KEY (name)
+);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE fixtures (
+    id VARCHAR(255) NOT NULL,
+    stage VARCHAR(255) NOT NULL,
+    home_code VARCHAR(255) NOT NULL,
+    home_name VARCHAR(255) NOT NULL,
+    home_hashtags TEXT NOT NULL,
+    away_code VARCHAR(255) NOT NULL,
+    away_name VARCHAR(255) NOT NULL,
+    away_hashtags TEXT NOT NULL,
+    kickoff VARCHAR(255) NOT NULL,
+    venue VARCHAR(255) NULL,
+    PRIMARY KEY (id)
+);
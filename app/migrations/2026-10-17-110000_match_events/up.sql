@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE match_events (
+    id VARCHAR(255) NOT NULL,
+    kind VARCHAR(255) NOT NULL,
+    detected_at VARCHAR(255) NOT NULL,
+    query TEXT NOT NULL,
+    tweets_per_minute DOUBLE NOT NULL,
+    baseline DOUBLE NOT NULL,
+    score DOUBLE NOT NULL,
+    keyword_hits INTEGER NOT NULL,
+    sample_tweet_id VARCHAR(255) NULL,
+    PRIMARY KEY (id)
+);
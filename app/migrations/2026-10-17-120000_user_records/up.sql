@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE user_records (
+    id VARCHAR(255) NOT NULL,
+    username VARCHAR(255) NOT NULL,
+    name TEXT NOT NULL,
+    created_at VARCHAR(255) NULL,
+    description TEXT NULL,
+    location TEXT NULL,
+    profile_image_url TEXT NULL,
+    url TEXT NULL,
+    protected BOOLEAN NULL,
+    verified BOOLEAN NULL,
+    public_metrics TEXT NULL,
+    fetched_at VARCHAR(255) NOT NULL,
+    PRIMARY KEY (id)
+);
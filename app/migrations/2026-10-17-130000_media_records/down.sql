@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+drop table media_records;
\ No newline at end of file
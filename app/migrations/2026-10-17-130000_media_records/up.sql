@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE media_records (
+    media_key VARCHAR(255) NOT NULL,
+    tweet_id VARCHAR(255) NOT NULL,
+    position INTEGER NOT NULL,
+    media_type VARCHAR(255) NOT NULL,
+    url TEXT NULL,
+    preview_image_url TEXT NULL,
+    duration_ms BIGINT NULL,
+    width INTEGER NULL,
+    height INTEGER NULL,
+    alt_text TEXT NULL,
+    PRIMARY KEY (media_key)
+);
+CREATE INDEX media_records_tweet_id ON media_records (tweet_id);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+drop table tweet_metric_snapshots;
\ No newline at end of file
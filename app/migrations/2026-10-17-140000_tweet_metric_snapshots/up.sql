@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE tweet_metric_snapshots (
+    tweet_id VARCHAR(255) NOT NULL,
+    observed_at VARCHAR(255) NOT NULL,
+    like_count BIGINT NOT NULL,
+    retweet_count BIGINT NOT NULL,
+    reply_count BIGINT NOT NULL,
+    quote_count BIGINT NOT NULL,
+    PRIMARY KEY (tweet_id, observed_at)
+);
+CREATE INDEX tweet_metric_snapshots_observed_at ON tweet_metric_snapshots (observed_at);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tweet_records_conversation_id;
+ALTER TABLE tweet_records
+  DROP conversation_id;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE tweet_records
+  ADD conversation_id VARCHAR(255) NULL;
+CREATE INDEX tweet_records_conversation_id ON tweet_records (conversation_id);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE actions (
+    id VARCHAR(255) NOT NULL,
+    kind VARCHAR(255) NOT NULL,
+    tweet_id VARCHAR(255) NOT NULL,
+    performed_at VARCHAR(255) NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    error TEXT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX actions_performed_at ON actions (performed_at);
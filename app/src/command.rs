@@ -0,0 +1,19 @@
+pub mod action;
+pub mod args;
+pub mod auth;
+pub mod cheer;
+pub mod events;
+pub mod game;
+pub mod history;
+pub mod keisuke;
+pub mod likes;
+pub mod plugins;
+pub mod real;
+pub mod reply;
+pub mod schedule;
+pub mod search;
+pub mod show;
+pub mod stream;
+pub mod thread;
+pub mod top;
+pub mod watch;
@@ -0,0 +1,58 @@
+use crate::command::args;
+use crate::domain::model::{ActionKind, PostError, TweetID};
+use crate::error::*;
+use crate::initializer::AppContext;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+/// `like` / `unlike` / `retweet` / `unretweet` / `delete`
+pub fn commands() -> Vec<Command> {
+    ActionKind::NAMES
+        .iter()
+        .map(|name| {
+            let kind = ActionKind::parse(name).unwrap();
+            let command = Command::new(*name).about(about(kind)).arg(
+                Arg::new("tweet")
+                    .required(true)
+                    .help("ツイートの ID か URL (twitter.com / x.com)"),
+            );
+            match kind {
+                ActionKind::Delete => command.arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .action(ArgAction::SetTrue)
+                        .help("確認せずに削除する"),
+                ),
+                _ => command,
+            }
+        })
+        .collect()
+}
+
+fn about(kind: ActionKind) -> &'static str {
+    match kind {
+        ActionKind::Like => "👍ツイートをいいねする",
+        ActionKind::Unlike => "👎いいねを取り消す",
+        ActionKind::Retweet => "🔁リツイートする",
+        ActionKind::Unretweet => "↩️リツイートを取り消す",
+        ActionKind::Delete => "🗑️自分のツイートを削除する",
+    }
+}
+
+pub async fn run(app: &AppContext, name: &str, matches: &ArgMatches) -> Result<()> {
+    let kind = ActionKind::parse(name)?;
+    let id = TweetID::parse(matches.get_one::<String>("tweet").unwrap())?;
+    if kind == ActionKind::Delete
+        && !matches.get_flag("yes")
+        && !args::confirm(&format!("{} を削除しますか? [y/N] ", id.0))
+    {
+        return Err(ServiceError::new(
+            PostError::Cancelled,
+            anyhow::anyhow!("削除しませんでした"),
+        ));
+    }
+
+    let action = app.services.action.perform(kind, &id).await?;
+    println!("{}: {}", kind.label(), action.tweet_id);
+    Ok(())
+}
@@ -0,0 +1,403 @@
+use crate::domain::model::{
+    hashtags_from_template, LocalQuery, Order, PostError, SearchQuery, Tweet,
+};
+use crate::error::*;
+use crate::fixtures::Fixtures;
+use crate::initializer::AppContext;
+use crate::view;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use clap::{Arg, ArgAction, ArgMatches};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// `real` / `search` の検索条件
+pub fn query_args() -> Vec<Arg> {
+    vec![
+        Arg::new("keywords")
+            .num_args(0..)
+            .help("すべて含むキーワード (省略時は config.toml の queries)"),
+        Arg::new("any")
+            .long("any")
+            .action(ArgAction::Append)
+            .help("どれかを含むキーワード (OR)"),
+        Arg::new("hashtag")
+            .long("hashtag")
+            .short('t')
+            .action(ArgAction::Append)
+            .help("#ハッシュタグ"),
+        Arg::new("from")
+            .long("from")
+            .action(ArgAction::Append)
+            .help("投稿したユーザー (from:)"),
+        Arg::new("exclude")
+            .long("exclude")
+            .short('x')
+            .action(ArgAction::Append)
+            .help("含まないキーワード"),
+        Arg::new("lang").long("lang").help("言語 (lang:)"),
+        Arg::new("has-media")
+            .long("has-media")
+            .action(ArgAction::SetTrue)
+            .help("画像・動画つきのみ (has:media)"),
+        Arg::new("is-reply")
+            .long("is-reply")
+            .action(ArgAction::SetTrue)
+            .help("リプライのみ (is:reply)"),
+        Arg::new("retweets")
+            .long("retweets")
+            .action(ArgAction::SetTrue)
+            .help("リツイートも含める"),
+    ]
+}
+
+/// 保存済みのツイートを絞り込む条件 (`search --local` / `history`)
+pub fn local_filter_args() -> Vec<Arg> {
+    vec![
+        Arg::new("since")
+            .long("since")
+            .value_parser(parse_since)
+            .help("この日時以降 (2022-12-05 または 2022-12-06T00:00:00+09:00)"),
+        Arg::new("until")
+            .long("until")
+            .value_parser(parse_until)
+            .help("この日時より前 (日付だけならその日の終わりまで)"),
+        Arg::new("sensitive")
+            .long("sensitive")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("safe")
+            .help("センシティブなツイートのみ"),
+        Arg::new("safe")
+            .long("safe")
+            .action(ArgAction::SetTrue)
+            .help("センシティブなツイートを除く"),
+        Arg::new("oldest-first")
+            .long("oldest-first")
+            .action(ArgAction::SetTrue)
+            .help("古い順に表示する"),
+        Arg::new("offset")
+            .long("offset")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("0")
+            .help("読み飛ばす件数"),
+    ]
+}
+
+/// 日付だけならローカル時間のその日の 0 時
+fn parse_date(value: &str, days: u64) -> std::result::Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("`{}` is not a date (2022-12-05) or RFC 3339 time", value))?;
+    let midnight = (date + chrono::Days::new(days))
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| format!("`{}` does not exist in local time", value))
+}
+
+fn parse_since(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    parse_date(value, 0)
+}
+
+fn parse_until(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    parse_date(value, 1)
+}
+
+/// `keywords` / `hashtag` / `from` / `lang` と `local_filter_args` から組み立てる
+pub fn local_query(matches: &ArgMatches, limit: usize) -> LocalQuery {
+    let values = |id: &str| -> Vec<String> {
+        matches
+            .get_many::<String>(id)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    };
+    let mut texts = values("keywords");
+    texts.extend(values("hashtag").into_iter().map(|it| format!("#{}", it)));
+
+    LocalQuery {
+        texts,
+        authors: values("from"),
+        lang: matches.get_one::<String>("lang").cloned(),
+        conversation_id: None,
+        since: matches.get_one::<DateTime<Utc>>("since").copied(),
+        until: matches.get_one::<DateTime<Utc>>("until").copied(),
+        sensitive: if matches.get_flag("sensitive") {
+            Some(true)
+        } else if matches.get_flag("safe") {
+            Some(false)
+        } else {
+            None
+        },
+        order: if matches.get_flag("oldest-first") {
+            Order::OldestFirst
+        } else {
+            Order::NewestFirst
+        },
+        limit,
+        offset: *matches.get_one::<usize>("offset").unwrap(),
+    }
+}
+
+/// `schedule` / `match` で読む fixture ファイル
+pub fn fixtures_arg() -> Arg {
+    Arg::new("fixtures")
+        .long("fixtures")
+        .action(ArgAction::Append)
+        .value_parser(clap::value_parser!(PathBuf))
+        .help("追加で読む日程ファイル (.toml / .json)")
+}
+
+/// 同梱・設定ディレクトリ・`--fixtures` の日程を DB に反映する
+pub async fn sync_fixtures(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let extra = matches
+        .get_many::<PathBuf>("fixtures")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    let fixtures = Fixtures::load(&extra)?;
+    app.services.fixture.sync(fixtures.into_matches()).await
+}
+
+/// `--disguise cargo|pytest|kubectl|tail`
+pub fn disguise_arg() -> Arg {
+    Arg::new("disguise")
+        .long("disguise")
+        .value_parser(view::Disguise::NAMES)
+        .help("ビルドログなどに見せかけて表示する")
+}
+
+pub fn disguise(matches: &ArgMatches) -> Result<Option<view::Disguise>> {
+    matches
+        .get_one::<String>("disguise")
+        .map(|name| view::Disguise::parse(name))
+        .transpose()
+}
+
+/// `--output` / `--fields`
+pub fn output_args() -> Vec<Arg> {
+    vec![
+        Arg::new("output")
+            .long("output")
+            .short('o')
+            .value_parser(view::OutputFormat::NAMES)
+            .default_value("pretty")
+            .help("出力形式"),
+        Arg::new("fields")
+            .long("fields")
+            .help("出力する項目 (例: id,author_id,created_at,text)。pretty では使えない"),
+    ]
+}
+
+/// プレビューの大きさ (セル)
+const PREVIEW_COLS: u32 = 48;
+const PREVIEW_ROWS: u32 = 16;
+
+/// `--preview [halfblock|sixel]`
+pub fn preview_arg() -> Arg {
+    Arg::new("preview")
+        .long("preview")
+        .value_parser(view::PreviewProtocol::NAMES)
+        .num_args(0..=1)
+        .default_missing_value("halfblock")
+        .help("添付の画像 (動画と GIF はサムネイル) を表示する (pretty で色を使うときだけ)")
+}
+
+/// pretty で出力しないときと、`--color never` や `NO_COLOR`、`theme = "plain"` で色を使わないときは `None`
+pub fn preview(app: &AppContext, matches: &ArgMatches) -> Result<Option<view::PreviewProtocol>> {
+    let pretty = matches
+        .try_get_one::<String>("output")
+        .ok()
+        .flatten()
+        .is_none_or(|it| it == "pretty");
+    let disguised = matches
+        .try_get_one::<String>("disguise")
+        .ok()
+        .flatten()
+        .is_some();
+    let protocol = matches
+        .get_one::<String>("preview")
+        .map(|name| view::PreviewProtocol::parse(name))
+        .transpose()?;
+    if !pretty || disguised || view::theme(&app.config.theme, app.config.color)?.is_none() {
+        return Ok(None);
+    }
+    Ok(protocol)
+}
+
+/// ツイートの添付をダウンロードして表示する。取れなかった画像は理由だけ出して続ける
+pub async fn print_previews(
+    app: &AppContext,
+    tweet: &Tweet,
+    protocol: view::PreviewProtocol,
+) -> Result<()> {
+    let (cols, _) = crossterm::terminal::size().unwrap_or((80, 24));
+    let max_cols = (cols as u32).saturating_sub(2).clamp(1, PREVIEW_COLS);
+    for media in &tweet.media {
+        let lines = match app.services.media.preview_image(media).await {
+            Ok(Some(bytes)) => view::preview_lines(&bytes, protocol, max_cols, PREVIEW_ROWS),
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        match lines {
+            Ok(lines) => {
+                for line in lines {
+                    println!("  {}", line);
+                }
+            }
+            Err(err) => eprintln!("  ({})", err),
+        }
+    }
+    Ok(())
+}
+
+pub fn renderer_options(
+    matches: &ArgMatches,
+    theme: Option<view::Theme>,
+) -> Result<view::RendererOptions> {
+    let format = view::OutputFormat::parse(matches.get_one::<String>("output").unwrap())?;
+    let fields = match matches.get_one::<String>("fields") {
+        Some(fields) => view::parse_fields(fields)?,
+        None => None,
+    };
+    // pretty は項目を選べないので、黙って無視せずにエラーにする
+    if format == view::OutputFormat::Pretty && fields.is_some() {
+        return Err(ServiceError::new(
+            view::ViewError::FieldsNotSupported,
+            anyhow::anyhow!(
+                "--fields は --output json / ndjson / csv / table と一緒に指定してください"
+            ),
+        ));
+    }
+    Ok(view::RendererOptions {
+        format,
+        fields,
+        theme,
+        indent: String::new(),
+    })
+}
+
+pub fn pretty(theme: Option<view::Theme>, indent: &str) -> Box<dyn view::Renderer + Send> {
+    view::renderer(
+        view::RendererOptions {
+            format: view::OutputFormat::Pretty,
+            fields: None,
+            theme,
+            indent: indent.to_string(),
+        },
+        Box::new(std::io::stdout()),
+    )
+}
+
+pub fn search_query(matches: &ArgMatches, defaults: &[String]) -> SearchQuery {
+    let values = |id: &str| -> Vec<String> {
+        matches
+            .get_many::<String>(id)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    };
+    let (keywords, any, hashtags, from) = (
+        values("keywords"),
+        values("any"),
+        values("hashtag"),
+        values("from"),
+    );
+
+    let mut query = SearchQuery::new();
+    if keywords.is_empty() && any.is_empty() && hashtags.is_empty() && from.is_empty() {
+        query = query.any_of(
+            defaults
+                .iter()
+                .map(|it| SearchQuery::new().raw(it))
+                .collect(),
+        );
+    }
+    for keyword in keywords {
+        query = query.keyword(keyword);
+    }
+    query = query.any_of(
+        any.into_iter()
+            .map(|it| SearchQuery::new().keyword(it))
+            .collect(),
+    );
+    for hashtag in hashtags {
+        query = query.hashtag(hashtag);
+    }
+    query = query.any_of(
+        from.into_iter()
+            .map(|it| SearchQuery::new().author(it))
+            .collect(),
+    );
+    for exclude in values("exclude") {
+        query = query.exclude(SearchQuery::new().keyword(exclude));
+    }
+    if let Some(lang) = matches.get_one::<String>("lang") {
+        query = query.lang(lang);
+    }
+    if matches.get_flag("has-media") {
+        query = query.has_media();
+    }
+    if matches.get_flag("is-reply") {
+        query = query.only_replies();
+    }
+    query.include_retweets(matches.get_flag("retweets"))
+}
+
+/// `cheer` / `reply` の `--template` / `--no-tags` / `--yes`
+pub fn post_args() -> Vec<Arg> {
+    vec![
+        Arg::new("template")
+            .long("template")
+            .short('t')
+            .default_value(DEFAULT_TEMPLATE)
+            .help("末尾に付けるハッシュタグ (config.toml の cheer_templates の名前)"),
+        Arg::new("no-tags")
+            .long("no-tags")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("template")
+            .help("ハッシュタグを付けない"),
+        Arg::new("yes")
+            .long("yes")
+            .short('y')
+            .action(ArgAction::SetTrue)
+            .help("確認せずに投稿する"),
+    ]
+}
+
+/// 設定に無いときは何も付けないテンプレート
+const DEFAULT_TEMPLATE: &str = "default";
+
+/// `--template` で選んだテンプレートのハッシュタグ
+pub fn post_hashtags(app: &AppContext, matches: &ArgMatches) -> Result<Vec<String>> {
+    if matches.get_flag("no-tags") {
+        return Ok(Vec::new());
+    }
+    let name = matches.get_one::<String>("template").unwrap();
+    match app.config.cheer_templates.get(name) {
+        Some(template) => Ok(hashtags_from_template(template)),
+        None if name == DEFAULT_TEMPLATE => Ok(Vec::new()),
+        None => Err(ServiceError::new(
+            PostError::UnknownTemplate,
+            anyhow::anyhow!("cheer_templates に `{}` がありません", name),
+        )),
+    }
+}
+
+/// 入力が無い (パイプが閉じている) ときは `false`
+pub fn confirm(prompt: &str) -> bool {
+    eprint!("{}", prompt);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    match std::io::stdin().lock().read_line(&mut answer) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => matches!(answer.trim(), "y" | "Y" | "yes"),
+    }
+}
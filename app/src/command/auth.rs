@@ -0,0 +1,92 @@
+use crate::domain::model::AuthCallback;
+use crate::error::*;
+use crate::infra::LoopbackListener;
+use crate::initializer::AppContext;
+use chrono::{Local, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("auth")
+        .about("🔑いいねや削除に使うユーザーのログイン")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("login")
+                .about("OAuth 2.0 (PKCE) でログインする")
+                .arg(
+                    Arg::new("no-browser")
+                        .long("no-browser")
+                        .action(ArgAction::SetTrue)
+                        .help("ブラウザを開かず URL だけ表示する"),
+                ),
+        )
+        .subcommand(Command::new("status").about("ログインしているユーザーとトークンの期限"))
+        .subcommand(Command::new("logout").about("保存したトークンを消す"))
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let auth = &app.services.auth;
+    match matches.subcommand() {
+        Some(("login", sub_matches)) => {
+            let listener = LoopbackListener::bind(app.config.redirect_port).await?;
+            let request = auth.start_login(&listener.redirect_uri()?)?;
+            eprintln!(
+                "ブラウザで次の URL を開いて許可してください:\n\n  {}\n",
+                request.url
+            );
+            if !sub_matches.get_flag("no-browser") {
+                open_browser(&request.url);
+            }
+            let target = listener.accept(&request.state).await?;
+            let token = auth
+                .finish_login(&request, AuthCallback::parse(&target)?)
+                .await?;
+            println!("@{} でログインしました", token.username.unwrap_or_default());
+            Ok(())
+        }
+        Some(("status", _)) => {
+            if let Some(oauth1) = &app.config.oauth1 {
+                println!(
+                    "OAuth 1.0a のアクセストークンで署名します (user {})",
+                    oauth1.user_id().unwrap_or("?")
+                );
+                return Ok(());
+            }
+            match auth.current().await? {
+                Some(token) => {
+                    let expires_at = token.expires_at.with_timezone(&Local).format("%F %T");
+                    println!("user    @{}", token.username.as_deref().unwrap_or_default());
+                    if token.is_expired(Utc::now()) {
+                        println!("expires {} (次に使うときにリフレッシュします)", expires_at);
+                    } else {
+                        println!("expires {}", expires_at);
+                    }
+                    println!("scope   {}", token.scope);
+                }
+                None => println!("ログインしていません (`samuraicli auth login`)"),
+            }
+            Ok(())
+        }
+        Some(("logout", _)) => {
+            auth.logout().await?;
+            println!("ログアウトしました");
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// 開けなくても URL は表示してあるので失敗は無視する
+fn open_browser(url: &str) {
+    let program = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    let _ = std::process::Command::new(program)
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
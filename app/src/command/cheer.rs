@@ -0,0 +1,40 @@
+use crate::command::args;
+use crate::domain::model::{Draft, PostError, MAX_WEIGHTED_LENGTH};
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("cheer")
+        .about("📣応援ツイートを投稿する")
+        .arg(Arg::new("text").required(true).help("本文"))
+        .args(args::post_args())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let draft = Draft::new(
+        matches.get_one::<String>("text").unwrap(),
+        &args::post_hashtags(app, matches)?,
+        None,
+    )?;
+    post(app, draft, matches.get_flag("yes")).await
+}
+
+/// 本文と文字数を見せて確認してから投稿する
+pub async fn post(app: &AppContext, draft: Draft, yes: bool) -> Result<()> {
+    eprintln!("{}", draft.text);
+    eprintln!("({} / {})", draft.weighted_length(), MAX_WEIGHTED_LENGTH);
+    if !yes && !args::confirm("投稿しますか? [y/N] ") {
+        return Err(ServiceError::new(
+            PostError::Cancelled,
+            anyhow::anyhow!("投稿しませんでした"),
+        ));
+    }
+
+    let tweet = app.services.tweet.post(&draft).await?;
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let mut renderer = args::pretty(theme, "");
+    renderer.render(&tweet)?;
+    renderer.finish()
+}
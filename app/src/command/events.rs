@@ -0,0 +1,38 @@
+use crate::error::*;
+use crate::initializer::AppContext;
+use chrono::Local;
+use clap::{Arg, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("events")
+        .about("⚽real で見つけたゴールなどのイベントを表示する")
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(i64))
+                .default_value("20")
+                .help("表示する件数"),
+        )
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let limit = *matches.get_one::<i64>("limit").unwrap();
+    for event in app.services.event.list(limit).await? {
+        println!(
+            "{}  {:<10} {}  [{}]{}",
+            event
+                .detected_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            event.kind.as_str(),
+            event.summary(),
+            event.query,
+            event
+                .sample_tweet_id
+                .map(|id| format!(" {}", id))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
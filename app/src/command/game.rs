@@ -0,0 +1,67 @@
+//! `match` サブコマンド
+use crate::command::{args, real, schedule};
+use crate::error::*;
+use crate::initializer::AppContext;
+use chrono::{Duration, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("match")
+        .about("🏟️キックオフに合わせて試合のハッシュタグで real を始める")
+        .arg(
+            Arg::new("match")
+                .required(true)
+                .help("試合の id またはチームコード (JPN なら次の日本の試合)"),
+        )
+        .arg(
+            Arg::new("lead")
+                .long("lead")
+                .value_parser(clap::value_parser!(i64))
+                .default_value("10")
+                .help("キックオフの何分前に始めるか"),
+        )
+        .arg(
+            Arg::new("plain")
+                .long("plain")
+                .action(ArgAction::SetTrue)
+                .help("全画面表示にせず 1 行ずつ表示する"),
+        )
+        .arg(args::disguise_arg())
+        .arg(args::preview_arg().requires("plain"))
+        .arg(args::fixtures_arg())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    args::sync_fixtures(app, matches).await?;
+
+    let key = matches.get_one::<String>("match").unwrap();
+    let game = app.services.fixture.find(key, Utc::now()).await?;
+    let query = game.search_query();
+    println!("{}", schedule::format_match(&game, Utc::now()));
+    println!("query: {}", query.build()?);
+
+    let lead = Duration::minutes(*matches.get_one::<i64>("lead").unwrap());
+    let wait = game.kickoff - lead - Utc::now();
+    if let Ok(wait) = wait.to_std() {
+        println!(
+            "キックオフの {} 分前 ({}) に始めます",
+            lead.num_minutes(),
+            (game.kickoff - lead)
+                .with_timezone(&chrono::Local)
+                .format("%m/%d %H:%M")
+        );
+        tokio::time::sleep(wait).await;
+    }
+
+    let disguise = args::disguise(matches)?;
+    let preview = args::preview(app, matches)?;
+    real::start(
+        app,
+        query,
+        matches.get_flag("plain"),
+        false,
+        disguise,
+        preview,
+    )
+    .await
+}
@@ -0,0 +1,57 @@
+use crate::command::args;
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("history")
+        .about("📜保存したツイートを表示する")
+        .arg(
+            Arg::new("keywords")
+                .num_args(0..)
+                .help("すべて本文に含むキーワード"),
+        )
+        .arg(
+            Arg::new("hashtag")
+                .long("hashtag")
+                .short('t')
+                .action(ArgAction::Append)
+                .help("#ハッシュタグ"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .action(ArgAction::Append)
+                .help("投稿したユーザーの ID"),
+        )
+        .arg(Arg::new("lang").long("lang").help("言語"))
+        .args(args::local_filter_args())
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(usize))
+                .default_value("20")
+                .help("表示する件数"),
+        )
+        .args(args::output_args())
+        .arg(args::preview_arg())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let query = args::local_query(matches, limit);
+    let options = args::renderer_options(matches, theme)?;
+    let mut renderer = view::renderer(options, Box::new(std::io::stdout()));
+    let preview = args::preview(app, matches)?;
+
+    for tweet in app.services.tweet.search_local(&query).await? {
+        renderer.render(&tweet)?;
+        if let Some(protocol) = preview {
+            args::print_previews(app, &tweet, protocol).await?;
+        }
+    }
+    renderer.finish()
+}
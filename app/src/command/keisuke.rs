@@ -0,0 +1,21 @@
+use crate::command::args;
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("keisuke").about("📣本田圭佑の動向を取得する")
+}
+
+pub async fn run(app: &AppContext, _matches: &ArgMatches) -> Result<()> {
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    // ウォッチリストで上書きされていればそちらを使う
+    let entry = app.services.watch.find("keisuke").await?;
+    let tweets = app.services.tweet.get_tweets(&entry.search_query()).await?;
+    let mut renderer = args::pretty(theme, "    ");
+    for tweet in tweets {
+        renderer.render(&tweet)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::command::args;
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("likes")
+        .about("💙いいねしたツイートを表示する")
+        .arg(
+            Arg::new("local")
+                .long("local")
+                .action(ArgAction::SetTrue)
+                .help("API を呼ばず、`like` / `unlike` の記録から表示する"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(usize))
+                .default_value("20")
+                .help("表示する件数"),
+        )
+        .args(args::output_args())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let options = args::renderer_options(matches, theme)?;
+    let mut renderer = view::renderer(options, Box::new(std::io::stdout()));
+
+    let tweets = if matches.get_flag("local") {
+        let (tweets, missing) = app.services.action.liked_local(limit).await?;
+        for id in missing {
+            eprintln!("{} (未保存)", id);
+        }
+        tweets
+    } else {
+        app.services.action.liked_remote(limit).await?
+    };
+    for tweet in tweets {
+        renderer.render(&tweet)?;
+    }
+    renderer.finish()
+}
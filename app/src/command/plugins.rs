@@ -0,0 +1,243 @@
+use crate::config;
+use crate::error::*;
+use crate::initializer::{AppContext, Config};
+use clap::{ArgMatches, Command};
+use serde::*;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// `samuraicup foo` は `samuraicup-foo` を探して実行する
+pub const PLUGIN_PREFIX: &str = "samuraicup-";
+
+#[derive(Debug)]
+pub enum PluginError {
+    NotFound,
+    ExecFailed,
+}
+
+impl IServiceError for PluginError {
+    fn error_type(&self) -> String {
+        use PluginError::*;
+
+        match self {
+            NotFound => "plugin_not_found",
+            ExecFailed => "plugin_exec_failed",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use PluginError::*;
+
+        match self {
+            NotFound => http::StatusCode::NOT_FOUND,
+            ExecFailed => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// プラグインに `SAMURAICUP_CONTEXT` (JSON) として渡す設定
+#[derive(Serialize)]
+pub struct PluginContext<'a> {
+    pub profile: &'a str,
+    pub database_url: &'a str,
+    pub bearer_token: &'a str,
+    pub queries: &'a [String],
+    pub poll_interval_secs: u64,
+    pub theme: &'a str,
+    pub config_dir: Option<PathBuf>,
+}
+
+impl<'a> PluginContext<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        PluginContext {
+            profile: &config.profile,
+            database_url: &config.db_url,
+            bearer_token: &config.bearer_token,
+            queries: &config.queries,
+            poll_interval_secs: config.poll_interval.as_secs(),
+            theme: &config.theme,
+            config_dir: plugins_dir().and_then(|dir| dir.parent().map(Path::to_path_buf)),
+        }
+    }
+}
+
+pub fn command() -> Command {
+    Command::new("plugins")
+        .about("🔌インストールされているプラグインを表示する")
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("プラグインの一覧"))
+}
+
+pub async fn run(_app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let plugins = find_plugins(&search_dirs());
+            if plugins.is_empty() {
+                println!(
+                    "no plugins found (put `{}<name>` on PATH or in {})",
+                    PLUGIN_PREFIX,
+                    plugins_dir()
+                        .map(|dir| dir.display().to_string())
+                        .unwrap_or_default()
+                );
+            }
+            for plugin in plugins {
+                println!("{:<16} {}", plugin.name, plugin.path.display());
+            }
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// 使っている `config.toml` (`SAMURAICUP_CONFIG` も含む) と同じディレクトリの `plugins`
+pub fn plugins_dir() -> Option<PathBuf> {
+    config::config_path().and_then(|path| path.parent().map(|dir| dir.join("plugins")))
+}
+
+/// プラグインディレクトリを PATH より優先する
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = plugins_dir().into_iter().collect::<Vec<_>>();
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+    dirs
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name.strip_prefix(PLUGIN_PREFIX)?;
+    let name = name
+        .strip_suffix(std::env::consts::EXE_SUFFIX)
+        .unwrap_or(name);
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// 同じ名前のプラグインは先に見つかった方を使う
+pub fn find_plugins(dirs: &[PathBuf]) -> Vec<Plugin> {
+    let mut plugins: Vec<Plugin> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut found = entries
+            .filter_map(|entry| entry.ok().map(|it| it.path()))
+            .filter(|path| is_executable(path))
+            .filter_map(|path| plugin_name(&path).map(|name| Plugin { name, path }))
+            .filter(|plugin| plugins.iter().all(|it| it.name != plugin.name))
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins.extend(found);
+    }
+    plugins
+}
+
+pub fn find_plugin(name: &str) -> Option<Plugin> {
+    find_plugins(&search_dirs())
+        .into_iter()
+        .find(|plugin| plugin.name == name)
+}
+
+/// プラグインを実行して、その終了コードを返す
+///
+/// 設定を読めなかったとき (`config` が `None`) も実行し、`SAMURAICUP_*` だけ渡さない。
+pub async fn dispatch(config: Option<&Config>, name: &str, args: &[&OsString]) -> Result<i32> {
+    let plugin = find_plugin(name).ok_or_else(|| {
+        ServiceError::new(
+            PluginError::NotFound,
+            anyhow::anyhow!(
+                "unknown subcommand `{}` ({}{} not found)",
+                name,
+                PLUGIN_PREFIX,
+                name
+            ),
+        )
+    })?;
+
+    let mut command = tokio::process::Command::new(&plugin.path);
+    command.args(args);
+    if let Some(config) = config {
+        let context = serde_json::to_string(&PluginContext::new(config))
+            .map_err(GeneralError::serialization_error)?;
+        command
+            .env("SAMURAICUP_PROFILE", &config.profile)
+            .env("SAMURAICUP_DATABASE_URL", &config.db_url)
+            .env("SAMURAICUP_BEARER_TOKEN", &config.bearer_token)
+            .env("SAMURAICUP_CONTEXT", context);
+    }
+    let status = command
+        .status()
+        .await
+        .map_err(|err| ServiceError::new(PluginError::ExecFailed, err))?;
+
+    // シグナルで終わった場合は 1 にしておく
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn install(dir: &Path, file_name: &str, executable: bool) {
+        let path = dir.join(file_name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        let mode = if executable { 0o755 } else { 0o644 };
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn it_should_find_executable_plugins_in_order() {
+        let root = std::env::temp_dir().join(format!("samuraicup-test-{}", uuid::Uuid::new_v4()));
+        let (first, second) = (root.join("plugins"), root.join("bin"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        install(&first, "samuraicup-odds", true);
+        install(&second, "samuraicup-odds", true);
+        install(&second, "samuraicup-lineup", true);
+        install(&second, "samuraicup-readme", false);
+        install(&second, "other-tool", true);
+
+        let plugins = find_plugins(&[first.clone(), second.clone(), root.join("missing")]);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            plugins,
+            vec![
+                Plugin {
+                    name: "odds".to_string(),
+                    path: first.join("samuraicup-odds"),
+                },
+                Plugin {
+                    name: "lineup".to_string(),
+                    path: second.join("samuraicup-lineup"),
+                },
+            ]
+        );
+    }
+}
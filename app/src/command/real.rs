@@ -0,0 +1,233 @@
+mod session;
+pub use session::*;
+
+use crate::command::args;
+use crate::config;
+use crate::domain::model::{MatchEvent, MatchEventError, SearchQuery, StreamRuleError};
+use crate::domain::service::{
+    describe_error, spawn_detector, spawn_poller, spawn_refresher, spawn_session, spawn_streamer,
+    EventDetector, PollEvent, PollerHandle,
+};
+use crate::error::*;
+use crate::initializer::{AppContext, Config};
+use crate::view;
+use chrono::Local;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use owo_colors::OwoColorize;
+use std::io::{IsTerminal, Write};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+
+pub fn command() -> Command {
+    Command::new("real")
+        .about("⚽ワールドカップをリアルタイムで確認する")
+        .args(args::query_args())
+        .arg(
+            Arg::new("plain")
+                .long("plain")
+                .action(ArgAction::SetTrue)
+                .help("全画面表示にせず 1 行ずつ表示する"),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .action(ArgAction::SetTrue)
+                .help("ポーリングせず search/stream で受け取る (対象は `stream rules` で決める)"),
+        )
+        .arg(args::preview_arg().requires("plain"))
+        .arg(args::disguise_arg().help(format!(
+            "ビルドログなどに見せかけて表示する (`{}` で偽ビルドに切り替え)",
+            view::PANIC_KEY
+        )))
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let query = args::search_query(matches, &app.config.queries);
+    let disguise = args::disguise(matches)?;
+    let preview = args::preview(app, matches)?;
+    start(
+        app,
+        query,
+        matches.get_flag("plain"),
+        matches.get_flag("stream"),
+        disguise,
+        preview,
+    )
+    .await
+}
+
+/// `match` からも使う
+pub async fn start(
+    app: &AppContext,
+    query: SearchQuery,
+    plain: bool,
+    stream: bool,
+    disguise: Option<view::Disguise>,
+    preview: Option<view::PreviewProtocol>,
+) -> Result<()> {
+    let (poller, query_text) = if stream {
+        let rules = app.services.stream.list_rules().await?;
+        if rules.is_empty() {
+            return Err(ServiceError::new(
+                StreamRuleError::NoRules,
+                anyhow::anyhow!("`samuraicli stream rules add` でルールを追加してください"),
+            ));
+        }
+        let label = rules
+            .into_iter()
+            .map(|rule| rule.tag.unwrap_or(rule.value))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let streamer = spawn_streamer(
+            app.services.stream.clone(),
+            app.config.poll_interval,
+            app.infras.rate_limits.clone(),
+        );
+        (streamer, label)
+    } else {
+        let query_text = query.build()?;
+        let poller = spawn_poller(
+            app.services.tweet.clone(),
+            query,
+            app.config.poll_interval,
+            app.infras.rate_limits.clone(),
+        );
+        (poller, query_text)
+    };
+    let poller = spawn_detector(
+        poller,
+        EventDetector::new(app.config.detector.clone(), query_text.clone()),
+        app.services.event.clone(),
+        notifier(&app.config, disguise.is_some()),
+    );
+    // 見たツイートのいいねなどの伸びを `top` で比べられるように取り直し続ける
+    let refresher = spawn_refresher(app.services.metric.clone());
+    let stats = Arc::new(Mutex::new(SessionStats::new(Local::now())));
+    let mut poller = spawn_session(
+        poller,
+        {
+            let stats = stats.clone();
+            let log_path = config::log_path();
+            move |event| {
+                stats.lock().unwrap().observe(event);
+                if let Some(path) = &log_path {
+                    append_log(path, event);
+                }
+            }
+        },
+        // シグナルが来たらイベントが閉じて、表示も普通に終わる
+        shutdown_signal(),
+    );
+
+    let result = if let Some(disguise) = disguise {
+        view::run_disguised(&mut poller, disguise).await
+    } else if plain || !std::io::stdout().is_terminal() {
+        run_plain(app, &mut poller, preview).await
+    } else {
+        match view::theme(&app.config.theme, app.config.color) {
+            Ok(theme) => {
+                view::tui::run(&mut poller, query_text, theme, app.services.media.clone()).await
+            }
+            Err(err) => Err(err),
+        }
+    };
+    // 保存中のツイートを書き終えてから終わる
+    poller.shutdown().await;
+    refresher.abort();
+
+    for line in stats.lock().unwrap().summary(Local::now()) {
+        eprintln!("{}", line);
+    }
+    result
+}
+
+async fn run_plain(
+    app: &AppContext,
+    poller: &mut PollerHandle,
+    preview: Option<view::PreviewProtocol>,
+) -> Result<()> {
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let mut renderer = args::pretty(theme.clone(), "");
+
+    while let Some(event) = poller.events.recv().await {
+        match event {
+            PollEvent::Tweets { tweets, .. } => {
+                for tweet in tweets {
+                    renderer.render(&tweet)?;
+                    if let Some(protocol) = preview {
+                        args::print_previews(app, &tweet, protocol).await?;
+                    }
+                }
+            }
+            PollEvent::Error {
+                message,
+                at,
+                retry_in,
+            } => {
+                eprintln!(
+                    "[{}] {}",
+                    at.format("%H:%M:%S"),
+                    describe_error(&message, retry_in)
+                );
+            }
+            PollEvent::Match(event) => {
+                let line = format!(
+                    "⚽ [{}] {}",
+                    event.detected_at.with_timezone(&Local).format("%H:%M:%S"),
+                    event.summary()
+                );
+                match theme {
+                    Some(_) => println!("{}", line.black().on_yellow().bold()),
+                    None => println!("{}", line),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// イベントを見つけたら端末のベルを鳴らし、`event_hook` を実行する
+///
+/// `--disguise` の間は音で気づかれないようベルは鳴らさない。
+fn notifier(
+    config: &Config,
+    disguised: bool,
+) -> impl Fn(&MatchEvent) -> Result<()> + Send + Sync + 'static {
+    let bell = config.event_bell && !disguised;
+    let hook = config.event_hook.clone();
+    move |event| {
+        if bell {
+            let mut stdout = std::io::stdout();
+            write!(stdout, "\x07").map_err(view::ViewError::write_error)?;
+            stdout.flush().map_err(view::ViewError::write_error)?;
+        }
+        if let Some(hook) = &hook {
+            run_hook(hook, event)?;
+        }
+        Ok(())
+    }
+}
+
+/// 画面を崩さないよう出力は捨てる。終わるのは待たない
+fn run_hook(hook: &str, event: &MatchEvent) -> Result<()> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("SAMURAICUP_EVENT_ID", &event.id)
+        .env("SAMURAICUP_EVENT_KIND", event.kind.as_str())
+        .env("SAMURAICUP_EVENT_SUMMARY", event.summary())
+        .env("SAMURAICUP_EVENT_QUERY", &event.query)
+        .env(
+            "SAMURAICUP_EVENT_TWEET_ID",
+            event.sample_tweet_id.as_deref().unwrap_or_default(),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| ServiceError::new(MatchEventError::HookFailed, err))?;
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(())
+}
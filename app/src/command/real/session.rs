@@ -0,0 +1,263 @@
+use crate::domain::model::{MatchEvent, RateLimit};
+use crate::domain::service::{describe_error, PollEvent};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+/// まとめに出す著者・ハッシュタグ・エラーの数
+const TOP: usize = 5;
+
+/// `real` を終えたときに出すまとめ
+pub struct SessionStats {
+    started_at: DateTime<Local>,
+    polls: usize,
+    tweets: HashSet<String>,
+    authors: HashMap<String, usize>,
+    hashtags: HashMap<String, usize>,
+    events: Vec<MatchEvent>,
+    errors: Vec<(DateTime<Local>, String)>,
+    rate_limit: Option<RateLimit>,
+}
+
+impl SessionStats {
+    pub fn new(started_at: DateTime<Local>) -> Self {
+        Self {
+            started_at,
+            polls: 0,
+            tweets: HashSet::new(),
+            authors: HashMap::new(),
+            hashtags: HashMap::new(),
+            events: Vec::new(),
+            errors: Vec::new(),
+            rate_limit: None,
+        }
+    }
+
+    pub fn observe(&mut self, event: &PollEvent) {
+        match event {
+            PollEvent::Tweets {
+                tweets, rate_limit, ..
+            } => {
+                self.polls += 1;
+                self.rate_limit = rate_limit.or(self.rate_limit);
+                // クエリを変えると同じツイートがまた来ることがある
+                for tweet in tweets {
+                    if !self.tweets.insert(tweet.id.clone()) {
+                        continue;
+                    }
+                    *self.authors.entry(tweet.author_label()).or_default() += 1;
+                    for hashtag in tweet.hashtags() {
+                        *self.hashtags.entry(hashtag).or_default() += 1;
+                    }
+                }
+            }
+            PollEvent::Error { message, at, .. } => self.errors.push((*at, message.clone())),
+            PollEvent::Match(event) => self.events.push(event.clone()),
+        }
+    }
+
+    pub fn summary(&self, ended_at: DateTime<Local>) -> Vec<String> {
+        let seconds = (ended_at - self.started_at).num_seconds().max(0);
+        let mut lines = vec![
+            "--- session summary ---".to_string(),
+            format!(
+                "duration  {}h {:02}m {:02}s ({} - {})",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60,
+                self.started_at.format("%m/%d %H:%M:%S"),
+                ended_at.format("%m/%d %H:%M:%S")
+            ),
+            format!("polls     {}", self.polls),
+            format!("tweets    {}", self.tweets.len()),
+        ];
+        lines.push(format!(
+            "authors   {}",
+            ranking(&self.authors, |author| author.to_string())
+        ));
+        lines.push(format!(
+            "hashtags  {}",
+            ranking(&self.hashtags, |hashtag| format!("#{}", hashtag))
+        ));
+        if !self.events.is_empty() {
+            lines.push(format!(
+                "events    {}",
+                self.events
+                    .iter()
+                    .map(|event| format!(
+                        "{} {}",
+                        event.detected_at.with_timezone(&Local).format("%H:%M"),
+                        event.kind.label()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if let Some(limit) = &self.rate_limit {
+            lines.push(format!(
+                "quota     {}/{} (reset {})",
+                limit.remaining,
+                limit.limit,
+                limit.reset.with_timezone(&Local).format("%H:%M:%S")
+            ));
+        }
+
+        let mut errors: HashMap<&str, usize> = HashMap::new();
+        for (_, message) in &self.errors {
+            *errors.entry(message.as_str()).or_default() += 1;
+        }
+        lines.push(format!("errors    {}", self.errors.len()));
+        lines.extend(
+            top(&errors)
+                .into_iter()
+                .map(|(message, count)| format!("  {}x {}", count, message.replace('\n', " "))),
+        );
+        lines
+    }
+}
+
+/// 多い順 (同数なら名前順) に `TOP` 件
+fn top<K: Ord>(counts: &HashMap<K, usize>) -> Vec<(&K, usize)> {
+    let mut counts = counts
+        .iter()
+        .map(|(key, count)| (key, *count))
+        .collect::<Vec<_>>();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    counts.truncate(TOP);
+    counts
+}
+
+fn ranking(counts: &HashMap<String, usize>, label: impl Fn(&str) -> String) -> String {
+    let top = top(counts);
+    if top.is_empty() {
+        return "-".to_string();
+    }
+    top.into_iter()
+        .map(|(key, count)| format!("{} ({})", label(key), count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 全画面表示ではエラーがすぐ消えるので、ファイルにも残す
+pub fn append_log(path: &Path, event: &PollEvent) {
+    let PollEvent::Error {
+        message,
+        at,
+        retry_in,
+    } = event
+    else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // ログが書けなくても表示は続ける
+    if let Ok(mut file) = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+    {
+        let _ = writeln!(
+            file,
+            "{} real: {}",
+            at.to_rfc3339(),
+            describe_error(message, *retry_in).replace('\n', " ")
+        );
+    }
+}
+
+/// Ctrl-C か SIGTERM
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::Tweet;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    fn tweet(id: &str, author_id: &str, hashtags: &[&str]) -> Tweet {
+        let tags = hashtags
+            .iter()
+            .map(|tag| serde_json::json!({ "tag": tag }))
+            .collect::<Vec<_>>();
+        Tweet::new(
+            id.to_string(),
+            "text".to_string(),
+            author_id.to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            Some(serde_json::json!({ "hashtags": tags })),
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn it_should_summarize_session() {
+        let started_at = Local.with_ymd_and_hms(2022, 12, 6, 0, 0, 0).unwrap();
+        let mut stats = SessionStats::new(started_at);
+        stats.observe(&PollEvent::Tweets {
+            tweets: vec![
+                tweet("1", "a", &["SAMURAIBLUE", "JPNCRO"]),
+                tweet("2", "b", &["SAMURAIBLUE"]),
+                tweet("3", "a", &[]),
+            ],
+            polled_at: started_at,
+            rate_limit: None,
+        });
+        stats.observe(&PollEvent::Tweets {
+            tweets: vec![tweet("3", "a", &[])],
+            polled_at: started_at,
+            rate_limit: None,
+        });
+        for _ in 0..2 {
+            stats.observe(&PollEvent::Error {
+                message: "timeout".to_string(),
+                at: started_at,
+                retry_in: Duration::from_secs(1),
+            });
+        }
+
+        let lines = stats.summary(started_at + chrono::Duration::seconds(3723));
+        assert_eq!(
+            lines[1..],
+            [
+                "duration  1h 02m 03s (12/06 00:00:00 - 12/06 01:02:03)",
+                "polls     2",
+                "tweets    3",
+                "authors   a (2), b (1)",
+                "hashtags  #SAMURAIBLUE (2), #JPNCRO (1)",
+                "errors    2",
+                "  2x timeout",
+            ]
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::command::{args, cheer};
+use crate::domain::model::{Draft, TweetID};
+use crate::error::*;
+use crate::initializer::AppContext;
+use clap::{Arg, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("reply")
+        .about("↩️ツイートに返信する")
+        .arg(
+            Arg::new("tweet")
+                .required(true)
+                .help("返信先のツイートの ID か URL"),
+        )
+        .arg(Arg::new("text").required(true).help("本文"))
+        .args(args::post_args())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let id = TweetID::parse(matches.get_one::<String>("tweet").unwrap())?;
+    let draft = Draft::new(
+        matches.get_one::<String>("text").unwrap(),
+        &args::post_hashtags(app, matches)?,
+        Some(id),
+    )?;
+    cheer::post(app, draft, matches.get_flag("yes")).await
+}
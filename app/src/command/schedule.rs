@@ -0,0 +1,74 @@
+use crate::command::args;
+use crate::domain::model::{Match, MatchStatus};
+use crate::error::*;
+use crate::initializer::AppContext;
+use chrono::{DateTime, Local, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("schedule")
+        .about("📅試合の日程を表示する")
+        .arg(
+            Arg::new("past")
+                .long("past")
+                .action(ArgAction::SetTrue)
+                .help("終わった試合を新しい順に表示する"),
+        )
+        .arg(
+            Arg::new("team")
+                .long("team")
+                .help("チームコードで絞り込む (例: JPN)"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("表示する試合数"),
+        )
+        .arg(args::fixtures_arg())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    args::sync_fixtures(app, matches).await?;
+
+    let now = Utc::now();
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let team = matches.get_one::<String>("team");
+    let games = app
+        .services
+        .fixture
+        .schedule(now, matches.get_flag("past"))
+        .await?
+        .into_iter()
+        .filter(|game| team.is_none_or(|team| game.involves(team)))
+        .take(limit)
+        .collect::<Vec<_>>();
+
+    if games.is_empty() {
+        println!("no matches");
+    }
+    for game in games {
+        println!("{}", format_match(&game, now));
+    }
+    Ok(())
+}
+
+/// `12/06 00:00  ラウンド16  日本 vs クロアチア  Al Janoub Stadium  [r16-jpn-cro]`
+pub fn format_match(game: &Match, now: DateTime<Utc>) -> String {
+    let status = match game.status(now) {
+        MatchStatus::Live => " LIVE",
+        _ => "",
+    };
+    format!(
+        "{}{}  {}  {} vs {}  {}  [{}]",
+        game.kickoff.with_timezone(&Local).format("%m/%d %H:%M"),
+        status,
+        game.stage.label(),
+        game.home.name,
+        game.away.name,
+        game.venue.as_deref().unwrap_or("-"),
+        game.id
+    )
+}
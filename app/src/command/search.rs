@@ -0,0 +1,73 @@
+use crate::command::args;
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use futures::StreamExt;
+
+pub fn command() -> Command {
+    Command::new("search")
+        .about("🥅ワールドカップのツイートを取得する")
+        .args(args::query_args())
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("取得する件数 (100 件を超える場合はページをたどる)"),
+        )
+        .arg(
+            Arg::new("local")
+                .long("local")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["any", "exclude", "has-media", "is-reply", "retweets"])
+                .help("API を使わず保存済みのツイートから探す (--from は author_id)"),
+        )
+        .args(
+            args::local_filter_args()
+                .into_iter()
+                .map(|arg| arg.requires("local")),
+        )
+        .args(args::output_args())
+        .arg(args::disguise_arg().conflicts_with_all(["output", "fields"]))
+        .arg(args::preview_arg())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let mut renderer = match args::disguise(matches)? {
+        Some(disguise) => view::disguise_renderer(disguise, Box::new(std::io::stdout())),
+        None => {
+            let options = args::renderer_options(matches, theme)?;
+            view::renderer(options, Box::new(std::io::stdout()))
+        }
+    };
+
+    let preview = args::preview(app, matches)?;
+
+    if matches.get_flag("local") {
+        let query = args::local_query(matches, limit);
+        for tweet in app.services.tweet.search_local(&query).await? {
+            renderer.render(&tweet)?;
+            if let Some(protocol) = preview {
+                args::print_previews(app, &tweet, protocol).await?;
+            }
+        }
+        return renderer.finish();
+    }
+
+    let query = args::search_query(matches, &app.config.queries);
+    query.build()?;
+    let tweets = app.services.tweet.search_stream(query, limit);
+    futures::pin_mut!(tweets);
+    while let Some(tweet) = tweets.next().await {
+        let tweet = tweet?;
+        renderer.render(&tweet)?;
+        if let Some(protocol) = preview {
+            args::print_previews(app, &tweet, protocol).await?;
+        }
+    }
+    renderer.finish()
+}
@@ -0,0 +1,145 @@
+use crate::command::args;
+use crate::domain::model::{Tweet, TweetID};
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgMatches, Command};
+use owo_colors::OwoColorize;
+
+pub fn command() -> Command {
+    Command::new("show")
+        .about("🔍ツイートを 1 件すべての項目で表示する")
+        .arg(
+            Arg::new("tweet")
+                .required(true)
+                .help("ツイートの ID か URL (twitter.com / x.com)"),
+        )
+        .args(args::output_args())
+        .arg(args::preview_arg())
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let id = TweetID::parse(matches.get_one::<String>("tweet").unwrap())?;
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let (tweet, referenced) = app.services.tweet.show(&id).await?;
+
+    let options = args::renderer_options(matches, theme.clone())?;
+    if options.format != view::OutputFormat::Pretty {
+        let mut renderer = view::renderer(options, Box::new(std::io::stdout()));
+        renderer.render(&tweet)?;
+        return renderer.finish();
+    }
+
+    for (key, value) in details(&tweet, &referenced) {
+        match theme {
+            Some(_) => println!("{:<16} {}", key.bold(), value),
+            None => println!("{:<16} {}", key, value),
+        }
+    }
+    if let Some(protocol) = args::preview(app, matches)? {
+        args::print_previews(app, &tweet, protocol).await?;
+    }
+    Ok(())
+}
+
+/// `(項目, 値)`。無い項目は出さない
+fn details(tweet: &Tweet, referenced: &[Tweet]) -> Vec<(&'static str, String)> {
+    let json = |value: &serde_json::Value| value.to_string();
+    let list = |values: Vec<String>| (!values.is_empty()).then(|| values.join(" "));
+    let username = tweet
+        .author
+        .as_ref()
+        .map_or("i/web", |author| author.username.as_str());
+    // 返信先のツイートを取ってあれば、その著者の名前も出す
+    let in_reply_to_user = tweet.in_reply_to_user_id.as_ref().map(|user_id| {
+        match referenced
+            .iter()
+            .filter_map(|it| it.author.as_ref())
+            .find(|author| &author.id == user_id)
+        {
+            Some(author) => format!("@{} [{}]", author.username, user_id),
+            None => user_id.clone(),
+        }
+    });
+
+    let mut details = vec![
+        ("id", Some(tweet.id.clone())),
+        (
+            "url",
+            Some(format!("https://x.com/{}/status/{}", username, tweet.id)),
+        ),
+        (
+            "author",
+            Some(format!("{} [{}]", tweet.author_label(), tweet.author_id)),
+        ),
+        (
+            "created_at",
+            Some(match tweet.created_at_local() {
+                Some(local) => format!(
+                    "{} ({})",
+                    tweet.created_at,
+                    local.format("%Y-%m-%d %H:%M:%S")
+                ),
+                None => tweet.created_at.clone(),
+            }),
+        ),
+        ("conversation_id", tweet.conversation_id.clone()),
+        ("in_reply_to_user", in_reply_to_user),
+        ("lang", tweet.lang.clone().filter(|it| !it.is_empty())),
+        ("source", tweet.source.clone().filter(|it| !it.is_empty())),
+        (
+            "sensitive",
+            tweet.possibly_sensitive.map(|it| it.to_string()),
+        ),
+        ("text", Some(tweet.text.clone())),
+        (
+            "hashtags",
+            list(
+                tweet
+                    .hashtags()
+                    .into_iter()
+                    .map(|it| format!("#{}", it))
+                    .collect(),
+            ),
+        ),
+        (
+            "mentions",
+            list(
+                tweet
+                    .mentions()
+                    .into_iter()
+                    .map(|it| format!("@{}", it))
+                    .collect(),
+            ),
+        ),
+        ("urls", list(tweet.urls())),
+        (
+            "metrics",
+            tweet.public_metrics.as_ref().map(|it| it.summary()),
+        ),
+        ("geo", tweet.geo.as_ref().map(json)),
+        ("withheld", tweet.withheld.as_ref().map(json)),
+    ];
+    for media in &tweet.media {
+        details.push(("media", Some(media.summary())));
+    }
+    for (kind, id) in tweet.references() {
+        let text = match referenced.iter().find(|it| it.id == id) {
+            Some(it) => format!(
+                "{} {} {}",
+                id,
+                it.author_label(),
+                it.text.replace('\n', " ")
+            ),
+            None => format!("{} (未保存)", id),
+        };
+        details.push(("referenced", Some(format!("{}: {}", kind, text))));
+    }
+    if let Some(entities) = &tweet.entities {
+        details.push(("entities", Some(json(entities))));
+    }
+    details
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+}
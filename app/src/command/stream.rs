@@ -0,0 +1,82 @@
+use crate::error::*;
+use crate::initializer::AppContext;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+pub fn command() -> Command {
+    Command::new("stream")
+        .about("📡search/stream のルールを管理する (`real --stream` で使う)")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("rules")
+                .about("ストリームのルール")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("ルールの一覧"))
+                .subcommand(
+                    Command::new("add")
+                        .about("ルールを追加する")
+                        .arg(
+                            Arg::new("value")
+                                .required(true)
+                                .help("検索クエリと同じ書き方 (例: \"三笘 lang:ja\")"),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .long("tag")
+                                .short('t')
+                                .help("届いたツイートに付けて表示する名前"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("delete").about("ルールを削除する").arg(
+                        Arg::new("ids")
+                            .required(true)
+                            .action(ArgAction::Append)
+                            .help("ルールの ID"),
+                    ),
+                ),
+        )
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let stream = &app.services.stream;
+    let Some(("rules", matches)) = matches.subcommand() else {
+        unreachable!()
+    };
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for rule in stream.list_rules().await? {
+                println!(
+                    "{:<20} {:<12} {}",
+                    rule.id.unwrap_or_default(),
+                    rule.tag.unwrap_or_default(),
+                    rule.value
+                );
+            }
+            Ok(())
+        }
+        Some(("add", sub_matches)) => {
+            let rules = stream
+                .add_rule(
+                    sub_matches.get_one::<String>("value").unwrap(),
+                    sub_matches.get_one::<String>("tag").map(|it| it.as_str()),
+                )
+                .await?;
+            for rule in rules {
+                println!("added {} {}", rule.id.unwrap_or_default(), rule.value);
+            }
+            Ok(())
+        }
+        Some(("delete", sub_matches)) => {
+            let ids = sub_matches
+                .get_many::<String>("ids")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            let deleted = stream.delete_rules(ids).await?;
+            println!("deleted {} rule(s)", deleted);
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,57 @@
+use crate::command::args;
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use owo_colors::OwoColorize;
+
+pub fn command() -> Command {
+    Command::new("thread")
+        .about("🧵ツイートへの返信をスレッドで表示する")
+        .arg(
+            Arg::new("id")
+                .required(true)
+                .help("スレッド内のどれかのツイートの ID"),
+        )
+        .arg(
+            Arg::new("local")
+                .long("local")
+                .action(ArgAction::SetTrue)
+                .help("API を使わず保存済みのツイートだけで組み立てる"),
+        )
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let id = matches.get_one::<String>("id").unwrap();
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let entries = app
+        .services
+        .tweet
+        .thread(id, !matches.get_flag("local"))
+        .await?;
+
+    for entry in entries {
+        let indent = "    ".repeat(entry.depth);
+        let marker = if entry.depth == 0 { "" } else { "↳ " };
+        let mut renderer = args::pretty(theme.clone(), &format!("{}{}", indent, marker));
+        renderer.render(&entry.tweet)?;
+        let mut notes = Vec::new();
+        if let Some(quoted) = &entry.quoted {
+            notes.push(format!(
+                "❝ {} {}",
+                quoted.author_label(),
+                quoted.text.replace('\n', " ")
+            ));
+        }
+        if entry.replies > 0 {
+            notes.push(format!("💬 {}", entry.replies));
+        }
+        for note in notes {
+            match theme {
+                Some(_) => println!("{}    {}", indent, note.dimmed()),
+                None => println!("{}    {}", indent, note),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::command::args;
+use crate::domain::model::{parse_window, Ranking};
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use chrono::Utc;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use owo_colors::OwoColorize;
+
+pub fn command() -> Command {
+    Command::new("top")
+        .about("🔥保存したツイートをいいねなどの数で並べる")
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .short('w')
+                .default_value("1h")
+                .help("この時間内に見た数字で比べる (30m, 2h, 1d)"),
+        )
+        .arg(
+            Arg::new("by")
+                .long("by")
+                .value_parser(Ranking::NAMES)
+                .default_value("engagement")
+                .help("engagement は合計、velocity は 1 分あたりの増え方"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("表示する件数"),
+        )
+        .arg(
+            Arg::new("refresh")
+                .long("refresh")
+                .action(ArgAction::SetTrue)
+                .help("並べる前に期間内のツイートの数字を取り直す"),
+        )
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let window = parse_window(matches.get_one::<String>("window").unwrap())?;
+    let ranking = Ranking::parse(matches.get_one::<String>("by").unwrap())?;
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+
+    if matches.get_flag("refresh") {
+        let (count, errors) = app.services.metric.refresh(window, Utc::now()).await?;
+        for err in errors {
+            eprintln!("{}", err);
+        }
+        eprintln!("{} 件の数字を取り直しました", count);
+    }
+
+    let mut renderer = args::pretty(theme.clone(), "    ");
+    let top = app
+        .services
+        .metric
+        .top(window, ranking, limit, Utc::now())
+        .await?;
+    for (i, (ranked, tweet)) in top.into_iter().enumerate() {
+        let line = format!(
+            "{:>2}. {}  +{:.1}/min",
+            i + 1,
+            ranked.metrics.summary(),
+            ranked.velocity
+        );
+        match theme {
+            Some(_) => println!("{}", line.bold()),
+            None => println!("{}", line),
+        }
+        renderer.render(&tweet)?;
+    }
+    renderer.finish()
+}
@@ -0,0 +1,202 @@
+use crate::command::args;
+use crate::domain::model::{Tweet, WatchEntry, WatchKind};
+use crate::domain::service::{describe_error, spawn_poller, PollEvent};
+use crate::error::*;
+use crate::initializer::AppContext;
+use crate::view;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use owo_colors::OwoColorize;
+use tokio::sync::mpsc;
+
+pub fn command() -> Command {
+    Command::new("watch")
+        .about("👀ウォッチリストの人やトピックをまとめて表示する")
+        .args_conflicts_with_subcommands(true)
+        .arg(
+            Arg::new("entry")
+                .long("entry")
+                .short('e')
+                .action(ArgAction::Append)
+                .help("表示するエントリ (省略時はすべて)"),
+        )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .short('f')
+                .action(ArgAction::SetTrue)
+                .help("新しいツイートを待ち続ける"),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("エントリを追加する (同じ名前なら上書き)")
+                .arg(Arg::new("name").required(true).help("エントリ名"))
+                .arg(
+                    Arg::new("query")
+                        .required(true)
+                        .help("検索クエリ (例: \"三笘 OR #三笘薫\")"),
+                )
+                .arg(
+                    Arg::new("kind")
+                        .long("kind")
+                        .value_parser(WatchKind::NAMES)
+                        .default_value("topic")
+                        .help("種類"),
+                )
+                .arg(
+                    Arg::new("tag-color")
+                        .long("tag-color")
+                        .help("タグの色 (#rrggbb)"),
+                ),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("エントリを削除する")
+                .arg(Arg::new("name").required(true).help("エントリ名")),
+        )
+        .subcommand(Command::new("list").about("エントリの一覧"))
+}
+
+pub async fn run(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let watch = &app.services.watch;
+    match matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let value = |id: &str| sub_matches.get_one::<String>(id).unwrap();
+            let entry = WatchEntry::new(
+                value("name"),
+                WatchKind::parse(value("kind"))?,
+                value("query"),
+                sub_matches
+                    .get_one::<String>("tag-color")
+                    .map(|it| it.as_str()),
+            )?;
+            watch.add(entry).await
+        }
+        Some(("remove", sub_matches)) => {
+            watch
+                .remove(sub_matches.get_one::<String>("name").unwrap())
+                .await
+        }
+        Some(("list", _)) => {
+            for entry in watch.list().await? {
+                println!(
+                    "{:<16} {:<10} {:<8} {}{}",
+                    entry.name,
+                    entry.kind.as_str(),
+                    entry.color.as_deref().unwrap_or("-"),
+                    entry.query,
+                    if entry.builtin { " (built-in)" } else { "" }
+                );
+            }
+            Ok(())
+        }
+        _ => feed(app, matches).await,
+    }
+}
+
+/// `[name] ` をエントリの色 (無ければテーマの色) でつける
+fn tag(entry: &WatchEntry, theme: Option<&view::Theme>) -> String {
+    let tag = format!("[{}]", entry.name);
+    let color = match (theme, entry.rgb()) {
+        (None, _) => return format!("{} ", tag),
+        (Some(_), Some((r, g, b))) => owo_colors::Rgb(r, g, b),
+        (Some(theme), None) => theme.author_color(&entry.name),
+    };
+    format!("{} ", tag.color(color).bold())
+}
+
+async fn feed(app: &AppContext, matches: &ArgMatches) -> Result<()> {
+    let names = matches
+        .get_many::<String>("entry")
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let entries = if names.is_empty() {
+        app.services.watch.list().await?
+    } else {
+        let mut entries = Vec::new();
+        for name in names {
+            entries.push(app.services.watch.find(name).await?);
+        }
+        entries
+    };
+
+    let theme = view::theme(&app.config.theme, app.config.color)?;
+    let tags = entries
+        .iter()
+        .map(|entry| tag(entry, theme.as_ref()))
+        .collect::<Vec<_>>();
+    let mut renderers = tags
+        .iter()
+        .map(|tag| args::pretty(theme.clone(), tag))
+        .collect::<Vec<_>>();
+
+    if !matches.get_flag("follow") {
+        let queries = entries
+            .iter()
+            .map(WatchEntry::search_query)
+            .collect::<Vec<_>>();
+        let results = futures::future::join_all(
+            queries
+                .iter()
+                .map(|query| app.services.tweet.get_tweets(query)),
+        )
+        .await;
+        let mut feed: Vec<(usize, Tweet)> = Vec::new();
+        for (i, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(tweets) => feed.extend(tweets.into_iter().map(|tweet| (i, tweet))),
+                Err(err) => eprintln!("{}{}", tags[i], err),
+            }
+        }
+        // 新しいものが下に来るようにする
+        feed.sort_by(|(_, a), (_, b)| a.created_at.cmp(&b.created_at));
+        for (i, tweet) in feed {
+            renderers[i].render(&tweet)?;
+        }
+        return Ok(());
+    }
+
+    // エントリごとにポーリングして 1 本にまとめる
+    let (event_tx, mut event_rx) = mpsc::channel(64);
+    for (i, entry) in entries.iter().enumerate() {
+        let mut poller = spawn_poller(
+            app.services.tweet.clone(),
+            entry.search_query(),
+            app.config.poll_interval,
+            app.infras.rate_limits.clone(),
+        );
+        let event_tx = event_tx.clone();
+        tokio::spawn(async move {
+            while let Some(event) = poller.events.recv().await {
+                if event_tx.send((i, event)).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+    drop(event_tx);
+
+    while let Some((i, event)) = event_rx.recv().await {
+        match event {
+            PollEvent::Tweets { tweets, .. } => {
+                for tweet in tweets {
+                    renderers[i].render(&tweet)?;
+                }
+            }
+            PollEvent::Error {
+                message,
+                at,
+                retry_in,
+            } => {
+                eprintln!(
+                    "[{}] {}{}",
+                    at.format("%H:%M:%S"),
+                    tags[i],
+                    describe_error(&message, retry_in)
+                );
+            }
+            PollEvent::Match(_) => {}
+        }
+    }
+    Ok(())
+}
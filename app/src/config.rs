@@ -0,0 +1,204 @@
+use crate::error::*;
+use directories::ProjectDirs;
+use serde::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PROFILE: &str = "default";
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    InvalidFile,
+    UnknownProfile,
+    MissingValue,
+}
+
+impl IServiceError for ConfigError {
+    fn error_type(&self) -> String {
+        use ConfigError::*;
+
+        match self {
+            InvalidFile => "invalid_config_file",
+            UnknownProfile => "unknown_profile",
+            MissingValue => "missing_config_value",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use ConfigError::*;
+
+        match self {
+            InvalidFile => http::StatusCode::BAD_REQUEST,
+            UnknownProfile => http::StatusCode::NOT_FOUND,
+            MissingValue => http::StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// `config.toml` の中身
+///
+/// ```toml
+/// default_profile = "worldcup"
+///
+/// [profiles.worldcup]
+/// queries = ["ワールドカップ"]
+/// poll_interval = 5
+/// theme = "samurai-blue"
+/// db_path = "/home/me/.local/share/samuraicup/tweets.db"
+/// bearer_token = "..."
+/// client_id = "..."
+///
+/// [profiles.worldcup.cheer_templates]
+/// default = "#SAMURAIBLUE"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    pub queries: Vec<String>,
+    /// 秒
+    pub poll_interval: Option<u64>,
+    /// `samurai-blue` (デフォルト) / `monochrome` / `high-contrast` / `plain`
+    pub theme: Option<String>,
+    pub db_path: Option<String>,
+    pub db_pool_size: Option<u32>,
+    pub bearer_token: Option<String>,
+    /// `real` で平均から標準偏差いくつ分増えたらイベントにするか
+    pub event_threshold: Option<f64>,
+    /// 平均を取る期間 (秒)
+    pub event_window: Option<u64>,
+    /// イベントのときに端末のベルを鳴らすか (デフォルトは鳴らす)
+    pub event_bell: Option<bool>,
+    /// イベントのときに `sh -c` で実行するコマンド
+    pub event_hook: Option<String>,
+    /// `auth login` に使う OAuth 2.0 のクライアント ID
+    pub client_id: Option<String>,
+    /// confidential client のときだけ
+    pub client_secret: Option<String>,
+    /// `auth login` のリダイレクトを受けるポート。アプリの Callback URI と合わせる
+    pub redirect_port: Option<u16>,
+    /// いいねや削除の認証。`oauth2` (デフォルト、`auth login` のトークン) か `oauth1`
+    pub user_auth: Option<String>,
+    /// `user_auth = "oauth1"` のときのコンシューマーキーとアクセストークン
+    pub consumer_key: Option<String>,
+    pub consumer_secret: Option<String>,
+    pub access_token: Option<String>,
+    pub access_token_secret: Option<String>,
+    /// `cheer` / `reply` で末尾に付けるハッシュタグ。`default` はいつも使い、ほかは `--template` で選ぶ
+    pub cheer_templates: BTreeMap<String, String>,
+}
+
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "ekusiadadus", "samuraicup")
+}
+
+/// `real` のエラーを残すファイル
+pub fn log_path() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_local_dir().join("samuraicup.log"))
+}
+
+/// `auth login` で取ったトークンを残すファイル。プロファイルごとに分ける
+pub fn token_path(profile: &str) -> Option<PathBuf> {
+    project_dirs().map(|dirs| {
+        dirs.data_local_dir()
+            .join("tokens")
+            .join(format!("{}.json", profile))
+    })
+}
+
+/// `SAMURAICUP_CONFIG` が設定されていればそちらを優先する
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("SAMURAICUP_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    project_dirs().map(|dirs| dirs.config_dir().join(CONFIG_FILE_NAME))
+}
+
+impl ConfigFile {
+    /// ファイルが無い場合は空の設定として扱う
+    pub fn load(path: &Path) -> Result<ConfigFile> {
+        if !path.exists() {
+            return Ok(ConfigFile::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ServiceError::new(ConfigError::InvalidFile, err))?;
+        ConfigFile::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<ConfigFile> {
+        toml::from_str(content).map_err(|err| ServiceError::new(ConfigError::InvalidFile, err))
+    }
+
+    /// `name` が無ければ `default_profile`、それも無ければ `default` を使う
+    pub fn profile(&self, name: Option<&str>) -> Result<(String, Profile)> {
+        let explicit = name.or(self.default_profile.as_deref());
+        let name = explicit.unwrap_or(DEFAULT_PROFILE).to_string();
+
+        match self.profiles.get(&name) {
+            Some(profile) => Ok((name, profile.clone())),
+            // 明示的に指定されたプロファイルが無いのは設定ミス
+            None if explicit.is_some() => Err(ServiceError::new(
+                ConfigError::UnknownProfile,
+                anyhow::anyhow!("profile `{}` is not defined", name),
+            )),
+            None => Ok((name, Profile::default())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_profile = "worldcup"
+
+[profiles.worldcup]
+queries = ["ワールドカップ"]
+poll_interval = 10
+
+[profiles.keisuke]
+queries = ["本田圭佑"]
+theme = "plain"
+"#;
+
+    #[test]
+    fn it_should_pick_default_profile() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+        let (name, profile) = config.profile(None).unwrap();
+        assert_eq!(name, "worldcup");
+        assert_eq!(profile.queries, vec!["ワールドカップ".to_string()]);
+        assert_eq!(profile.poll_interval, Some(10));
+    }
+
+    #[test]
+    fn it_should_pick_named_profile() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+        let (name, profile) = config.profile(Some("keisuke")).unwrap();
+        assert_eq!(name, "keisuke");
+        assert_eq!(profile.theme, Some("plain".to_string()));
+    }
+
+    #[test]
+    fn it_should_reject_unknown_profile() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+        let err = config.profile(Some("nadeshiko")).unwrap_err();
+        assert!(err.is_error_of(ConfigError::UnknownProfile));
+    }
+
+    #[test]
+    fn it_should_fallback_to_empty_profile() {
+        let config = ConfigFile::default();
+        let (name, profile) = config.profile(None).unwrap();
+        assert_eq!(name, DEFAULT_PROFILE);
+        assert!(profile.queries.is_empty());
+    }
+}
@@ -1,23 +1,142 @@
 use crate::domain::model::*;
-use crate::error::Result;
+use crate::error::{Result, ServiceError};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
 
 #[async_trait]
+#[allow(dead_code)]
 pub trait ITweetRepository {
     async fn find_by_id(&self, id: &TweetID) -> Result<Tweet>;
+    /// 保存済みのものだけ。無い ID は飛ばす
+    async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<Tweet>>;
     async fn save(&self, tweet: Tweet) -> Result<()>;
     async fn save_tweets(&self, tweets: Vec<Tweet>) -> Result<()>;
     async fn search(&self, query: &str) -> Result<Vec<Tweet>>;
-    async fn get_tweets(&self, query: &str) -> Result<Vec<Tweet>>;
+    async fn get_tweets(&self, query: &SearchQuery) -> Result<Vec<Tweet>>;
+    /// `since_id` があればそれより新しいツイートだけ
+    async fn get_tweets_page(
+        &self,
+        query: &SearchQuery,
+        max_results: usize,
+        since_id: Option<&TweetID>,
+        pagination_token: Option<&str>,
+    ) -> Result<TweetPage>;
     async fn get_latest_tweets(&self, count: i32) -> Result<Vec<Tweet>>;
+    /// `/2/tweets/:id` から、参照しているツイート (`includes.tweets`) と一緒に取ってくる。無ければ `TwitterApiError::NotFound`
+    async fn lookup_tweet(&self, id: &TweetID) -> Result<(Tweet, Vec<Tweet>)>;
+    /// `/2/tweets?ids=` から取り直す。消されたツイートは含まず、部分エラーとして返す
+    async fn lookup_tweets(&self, ids: &[String]) -> Result<(Vec<Tweet>, Vec<ServiceError>)>;
+    /// 保存済みのツイートだけを検索する
+    async fn find_local(&self, query: &LocalQuery) -> Result<Vec<Tweet>>;
     async fn get_tweets_by_hashtag(&self, hashtag: &str) -> Result<Vec<Tweet>>;
-    async fn get_tweets_after_id(&self, query: &str, id: &TweetID) -> Result<Vec<Tweet>>;
+    async fn get_tweets_after_id(&self, query: &SearchQuery, id: &TweetID) -> Result<Vec<Tweet>>;
     async fn delete(&self, id: &TweetID) -> Result<()>;
+    /// ユーザーとして呼ぶ。応答で結果を確かめ、違えば `ActionError::Unconfirmed`
     async fn delete_tweet(&self, id: &TweetID) -> Result<()>;
     async fn favorite_tweet(&self, id: &TweetID) -> Result<()>;
+    async fn unfavorite_tweet(&self, id: &TweetID) -> Result<()>;
+    async fn retweet(&self, id: &TweetID) -> Result<()>;
+    async fn unretweet(&self, id: &TweetID) -> Result<()>;
+    /// ログインしたユーザーがいいねしたツイート。新しい順
+    async fn liked_tweets(&self, max_results: usize) -> Result<Vec<Tweet>>;
+    /// `POST /2/tweets` で投稿し、投稿したツイートを返す
+    async fn post_tweet(&self, draft: &Draft) -> Result<Tweet>;
+}
+
+#[async_trait]
+pub trait IFixtureRepository {
+    /// 同じ `id` があれば置き換える
+    async fn save_matches(&self, matches: Vec<Match>) -> Result<()>;
+    /// キックオフ順
+    async fn list(&self) -> Result<Vec<Match>>;
+}
+
+#[async_trait]
+pub trait IEventRepository {
+    async fn save(&self, event: MatchEvent) -> Result<()>;
+    /// 新しい順
+    async fn list(&self, limit: i64) -> Result<Vec<MatchEvent>>;
+}
+
+#[async_trait]
+pub trait IUserRepository {
+    /// 同じ `id` があれば置き換える
+    async fn save_users(&self, users: Vec<User>) -> Result<()>;
+    /// 保存済みのユーザー。無いものは含まない
+    async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<User>>;
+    /// `/2/users` から取ってくる。見つからないユーザーは含まず、部分エラーとして返す
+    async fn lookup(&self, ids: &[String]) -> Result<(Vec<User>, Vec<ServiceError>)>;
+}
+
+#[async_trait]
+pub trait IMediaRepository {
+    /// `media` は `attachments.media_keys` の順
+    async fn save_media(&self, tweet_id: &TweetID, media: Vec<Media>) -> Result<()>;
+    /// `(tweet_id, media)`。ツイートごとに添付の順
+    async fn find_by_tweet_ids(&self, ids: &[String]) -> Result<Vec<(String, Media)>>;
+    /// 画像を取ってくる
+    async fn download(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+#[async_trait]
+pub trait IMetricRepository {
+    /// 同じツイートを同じ時刻に見たものは置き換える
+    async fn save_snapshots(&self, snapshots: Vec<MetricSnapshot>) -> Result<()>;
+    /// `since` より後に見たもの。ツイートごとに古い順
+    async fn find_since(&self, since: &DateTime<Utc>) -> Result<Vec<MetricSnapshot>>;
+}
+
+#[async_trait]
+pub trait IActionRepository {
+    async fn save(&self, action: Action) -> Result<()>;
+    /// `kinds` のどれかの記録。古い順
+    async fn list(&self, kinds: &[ActionKind]) -> Result<Vec<Action>>;
+}
+
+#[async_trait]
+pub trait IAuthRepository {
+    /// ブラウザで開く認可画面の URL
+    fn authorize_url(&self, redirect_uri: &str, state: &str, pkce: &Pkce) -> Result<String>;
+    /// 認可コードをトークンに替え、ログインしたユーザーも入れて返す
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        pkce: &Pkce,
+    ) -> Result<OAuthToken>;
+    /// 保存済みのトークン。ログインしていなければ `None`
+    async fn load_token(&self) -> Result<Option<OAuthToken>>;
+    async fn save_token(&self, token: &OAuthToken) -> Result<()>;
+    async fn delete_token(&self) -> Result<()>;
+}
+
+/// `search/stream` で届いたツイートと部分エラー
+pub type TweetStream = BoxStream<'static, Result<StreamMessage>>;
+
+#[async_trait]
+pub trait IStreamRepository {
+    async fn list_rules(&self) -> Result<Vec<StreamRule>>;
+    /// 振られた `id` 入りで返す
+    async fn add_rules(&self, rules: Vec<StreamRule>) -> Result<Vec<StreamRule>>;
+    /// 消した数
+    async fn delete_rules(&self, ids: Vec<String>) -> Result<usize>;
+    /// つなぎ直しはしない。切れたり止まったりしたら `Err` を流して終わる
+    async fn connect(&self) -> Result<TweetStream>;
+}
+
+/// 組み込みのエントリは含まない
+#[async_trait]
+pub trait IWatchRepository {
+    async fn list(&self) -> Result<Vec<WatchEntry>>;
+    /// 同じ名前があれば置き換える
+    async fn save(&self, entry: WatchEntry) -> Result<()>;
+    /// 消したら true
+    async fn delete(&self, name: &str) -> Result<bool>;
 }
 
 #[async_trait]
+#[allow(dead_code)]
 pub trait IHttpClient {
     async fn get(
         &self,
@@ -1,5 +1,50 @@
+mod action;
+pub use action::*;
+
+mod auth;
+pub use auth::*;
+
+mod draft;
+pub use draft::*;
+
+mod fixture;
+pub use fixture::*;
+
 mod identity;
 pub use identity::*;
 
+mod match_event;
+pub use match_event::*;
+
+mod local_query;
+pub use local_query::*;
+
+mod media;
+pub use media::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod rate_limit;
+pub use rate_limit::*;
+
+mod search_query;
+pub use search_query::*;
+
+mod stream_rule;
+pub use stream_rule::*;
+
+mod thread;
+pub use thread::*;
+
 mod tweet;
 pub use tweet::*;
+
+mod twitter_api_error;
+pub use twitter_api_error::*;
+
+mod user;
+pub use user::*;
+
+mod watch_entry;
+pub use watch_entry::*;
@@ -0,0 +1,183 @@
+use crate::error::*;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum ActionError {
+    UnknownKind,
+    /// API は成功を返したが、結果 (`liked` など) が期待と違った
+    Unconfirmed,
+}
+
+impl IServiceError for ActionError {
+    fn error_type(&self) -> String {
+        use ActionError::*;
+
+        match self {
+            UnknownKind => "unknown_action",
+            Unconfirmed => "action_unconfirmed",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use ActionError::*;
+
+        match self {
+            UnknownKind => http::StatusCode::BAD_REQUEST,
+            Unconfirmed => http::StatusCode::CONFLICT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionKind {
+    Like,
+    Unlike,
+    Retweet,
+    Unretweet,
+    Delete,
+}
+
+impl ActionKind {
+    pub const NAMES: [&'static str; 5] = ["like", "unlike", "retweet", "unretweet", "delete"];
+
+    pub fn parse(name: &str) -> Result<ActionKind> {
+        use ActionKind::*;
+
+        match name {
+            "like" => Ok(Like),
+            "unlike" => Ok(Unlike),
+            "retweet" => Ok(Retweet),
+            "unretweet" => Ok(Unretweet),
+            "delete" => Ok(Delete),
+            _ => Err(ServiceError::new(
+                ActionError::UnknownKind,
+                anyhow::anyhow!("unknown action `{}`", name),
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        use ActionKind::*;
+
+        match self {
+            Like => "like",
+            Unlike => "unlike",
+            Retweet => "retweet",
+            Unretweet => "unretweet",
+            Delete => "delete",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        use ActionKind::*;
+
+        match self {
+            Like => "いいねしました",
+            Unlike => "いいねを取り消しました",
+            Retweet => "リツイートしました",
+            Unretweet => "リツイートを取り消しました",
+            Delete => "削除しました",
+        }
+    }
+}
+
+/// `like` などを実行した記録。失敗したときも残す
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    pub id: String,
+    pub kind: ActionKind,
+    pub tweet_id: String,
+    pub performed_at: DateTime<Utc>,
+    /// 失敗したときのエラー。成功なら `None`
+    pub error: Option<String>,
+}
+
+impl Action {
+    pub fn new(
+        kind: ActionKind,
+        tweet_id: &str,
+        performed_at: DateTime<Utc>,
+        result: &Result<()>,
+    ) -> Action {
+        Action {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            tweet_id: tweet_id.to_string(),
+            performed_at,
+            error: result.as_ref().err().map(ToString::to_string),
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 成功した `like` / `unlike` の最後がいいねのツイート。新しくいいねした順
+pub fn liked_tweet_ids(actions: &[Action]) -> Vec<String> {
+    let mut latest: HashMap<&str, &Action> = HashMap::new();
+    for action in actions.iter().filter(|action| {
+        action.succeeded() && matches!(action.kind, ActionKind::Like | ActionKind::Unlike)
+    }) {
+        let newer = latest
+            .get(action.tweet_id.as_str())
+            .is_none_or(|it| it.performed_at <= action.performed_at);
+        if newer {
+            latest.insert(&action.tweet_id, action);
+        }
+    }
+    let mut liked = latest
+        .into_values()
+        .filter(|action| action.kind == ActionKind::Like)
+        .collect::<Vec<_>>();
+    liked.sort_by_key(|action| std::cmp::Reverse(action.performed_at));
+    liked
+        .into_iter()
+        .map(|action| action.tweet_id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn action(kind: ActionKind, tweet_id: &str, minute: u32, ok: bool) -> Action {
+        let result = match ok {
+            true => Ok(()),
+            false => Err(ServiceError::new(
+                ActionError::Unconfirmed,
+                anyhow::anyhow!("liked: false"),
+            )),
+        };
+        Action::new(
+            kind,
+            tweet_id,
+            Utc.with_ymd_and_hms(2022, 12, 5, 16, minute, 0).unwrap(),
+            &result,
+        )
+    }
+
+    #[test]
+    fn it_should_list_currently_liked_tweets() {
+        use ActionKind::*;
+
+        let actions = vec![
+            action(Like, "1", 0, true),
+            action(Like, "2", 1, true),
+            action(Unlike, "1", 2, true),
+            // 失敗した取り消しは数えない
+            action(Unlike, "2", 3, false),
+            action(Like, "3", 4, true),
+            action(Retweet, "4", 5, true),
+            action(Like, "5", 6, false),
+        ];
+        assert_eq!(liked_tweet_ids(&actions), vec!["3", "2"]);
+        assert_eq!(
+            actions[3].error.as_deref(),
+            Some("action_unconfirmed: liked: false")
+        );
+    }
+}
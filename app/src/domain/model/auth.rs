@@ -0,0 +1,231 @@
+use crate::error::*;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use serde::*;
+use sha2::{Digest, Sha256};
+
+/// 期限のこの秒数前からリフレッシュする
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// ユーザーとして呼ぶ必要があるエンドポイント。それ以外はアプリの Bearer トークンで呼ぶ
+pub const USER_CONTEXT_ENDPOINTS: &[&str] = &[
+    "GET /2/users/me",
+    "POST /2/tweets",
+    "DELETE /2/tweets/:id",
+    "POST /2/users/:id/likes",
+    "DELETE /2/users/:id/likes/:id",
+    "POST /2/users/:id/retweets",
+    "DELETE /2/users/:id/retweets/:id",
+];
+
+#[derive(Debug)]
+pub enum AuthError {
+    NotLoggedIn,
+    MissingClientId,
+    InvalidCallback,
+    Denied,
+    TokenRejected,
+}
+
+impl IServiceError for AuthError {
+    fn error_type(&self) -> String {
+        use AuthError::*;
+
+        match self {
+            NotLoggedIn => "not_logged_in",
+            MissingClientId => "missing_client_id",
+            InvalidCallback => "invalid_callback",
+            Denied => "authorization_denied",
+            TokenRejected => "token_rejected",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use AuthError::*;
+
+        match self {
+            NotLoggedIn => http::StatusCode::UNAUTHORIZED,
+            MissingClientId => http::StatusCode::BAD_REQUEST,
+            InvalidCallback => http::StatusCode::BAD_REQUEST,
+            Denied => http::StatusCode::FORBIDDEN,
+            TokenRejected => http::StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// API を呼ぶときの資格情報
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    /// アプリの Bearer トークン
+    AppOnly,
+    /// `auth login` で取ったユーザーのトークン
+    User,
+}
+
+impl Auth {
+    /// `endpoint` は `RateLimits` と同じ `POST /2/users/:id/likes` の形
+    pub fn for_endpoint(endpoint: &str) -> Auth {
+        if USER_CONTEXT_ENDPOINTS.contains(&endpoint) {
+            Auth::User
+        } else {
+            Auth::AppOnly
+        }
+    }
+}
+
+/// OAuth 2.0 のユーザーのトークン。データディレクトリに保存する
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    /// `offline.access` を付けたときだけ。使うたびに新しいものに替わる
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub scope: String,
+    /// ログインしたユーザー。いいねなど `/2/users/:id/...` に使う
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+impl OAuthToken {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at - Duration::seconds(EXPIRY_MARGIN_SECS) <= now
+    }
+}
+
+/// PKCE の `code_verifier` と `code_challenge` (S256)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn new(verifier: String) -> Pkce {
+        let challenge =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(&verifier));
+        Pkce {
+            verifier,
+            challenge,
+        }
+    }
+
+    /// 64 文字のランダムな `code_verifier` で作る
+    pub fn generate() -> Pkce {
+        Pkce::new(format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        ))
+    }
+}
+
+/// ログインを始めるときに作り、リダイレクトが戻ってきたら確かめる
+#[derive(Debug, Clone)]
+pub struct LoginRequest {
+    pub url: String,
+    pub redirect_uri: String,
+    pub state: String,
+    pub pkce: Pkce,
+}
+
+/// リダイレクト先に付いてきたクエリ
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AuthCallback {
+    /// `/callback?state=...&code=...` のようなリクエストの対象から読む
+    pub fn parse(target: &str) -> Result<AuthCallback> {
+        let url = url::Url::parse("http://127.0.0.1")
+            .and_then(|base| base.join(target))
+            .map_err(|err| ServiceError::new(AuthError::InvalidCallback, err))?;
+        let mut callback = AuthCallback::default();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "code" => callback.code = Some(value.into_owned()),
+                "state" => callback.state = Some(value.into_owned()),
+                "error" => callback.error = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+        Ok(callback)
+    }
+
+    /// `state` が `LoginRequest::state` と合っていれば認可コードを返す
+    pub fn code_for(self, state: &str) -> Result<String> {
+        if let Some(error) = self.error {
+            return Err(ServiceError::new(
+                AuthError::Denied,
+                anyhow::anyhow!("authorization failed: {}", error),
+            ));
+        }
+        if self.state.as_deref() != Some(state) {
+            return Err(ServiceError::new(
+                AuthError::InvalidCallback,
+                anyhow::anyhow!("state does not match"),
+            ));
+        }
+        self.code.ok_or_else(|| {
+            ServiceError::new(
+                AuthError::InvalidCallback,
+                anyhow::anyhow!("code is missing"),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_derive_challenge_from_verifier() {
+        // RFC 7636 Appendix B
+        let pkce = Pkce::new("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+        assert_eq!(
+            pkce.challenge,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(Pkce::generate().verifier.len(), 64);
+    }
+
+    #[test]
+    fn it_should_pick_auth_per_endpoint() {
+        assert_eq!(Auth::for_endpoint("POST /2/users/:id/likes"), Auth::User);
+        assert_eq!(Auth::for_endpoint("DELETE /2/tweets/:id"), Auth::User);
+        assert_eq!(Auth::for_endpoint("GET /2/tweets/:id"), Auth::AppOnly);
+        assert_eq!(
+            Auth::for_endpoint("GET /2/tweets/search/recent"),
+            Auth::AppOnly
+        );
+    }
+
+    #[test]
+    fn it_should_check_callback_state() {
+        let code = AuthCallback::parse("/callback?state=abc&code=xyz%3D")
+            .unwrap()
+            .code_for("abc")
+            .unwrap();
+        assert_eq!(code, "xyz=");
+
+        let err = AuthCallback::parse("/callback?state=other&code=xyz")
+            .unwrap()
+            .code_for("abc")
+            .unwrap_err();
+        assert!(err.is_error_of(AuthError::InvalidCallback));
+
+        let err = AuthCallback::parse("/callback?error=access_denied&state=abc")
+            .unwrap()
+            .code_for("abc")
+            .unwrap_err();
+        assert!(err.is_error_of(AuthError::Denied));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::domain::model::TweetID;
+use crate::error::*;
+
+/// 重み付きの文字数の上限。ASCII などは 1、日本語や絵文字は 2 と数える
+pub const MAX_WEIGHTED_LENGTH: usize = 280;
+/// URL は t.co で短縮されるので長さにかかわらずこの長さで数える
+const URL_LENGTH: usize = 23;
+/// 重み 1 で数える範囲 (twitter-text の設定と同じ)
+const LIGHT_RANGES: [(u32, u32); 4] = [
+    (0x0000, 0x10FF),
+    (0x2000, 0x200D),
+    (0x2010, 0x201F),
+    (0x2032, 0x2037),
+];
+
+#[derive(Debug)]
+pub enum PostError {
+    EmptyText,
+    TooLong,
+    UnknownTemplate,
+    Cancelled,
+}
+
+impl IServiceError for PostError {
+    fn error_type(&self) -> String {
+        use PostError::*;
+
+        match self {
+            EmptyText => "empty_text",
+            TooLong => "too_long",
+            UnknownTemplate => "unknown_template",
+            Cancelled => "cancelled",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
+/// 投稿する前のツイート
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Draft {
+    pub text: String,
+    pub in_reply_to: Option<TweetID>,
+}
+
+impl Draft {
+    /// 本文に無いハッシュタグを末尾に足し、長さを確かめる
+    pub fn new(text: &str, hashtags: &[String], in_reply_to: Option<TweetID>) -> Result<Draft> {
+        let mut text = text.trim().to_string();
+        if text.is_empty() {
+            return Err(ServiceError::only(PostError::EmptyText));
+        }
+        for tag in hashtags {
+            let tag = tag.trim();
+            if tag.is_empty() || text.split_whitespace().any(|word| word == tag) {
+                continue;
+            }
+            text.push(' ');
+            text.push_str(tag);
+        }
+        let length = weighted_length(&text);
+        if length > MAX_WEIGHTED_LENGTH {
+            return Err(ServiceError::new(
+                PostError::TooLong,
+                anyhow::anyhow!("{} / {} 文字です", length, MAX_WEIGHTED_LENGTH),
+            ));
+        }
+        Ok(Draft { text, in_reply_to })
+    }
+
+    pub fn weighted_length(&self) -> usize {
+        weighted_length(&self.text)
+    }
+}
+
+/// `#SAMURAIBLUE #がんばれ日本` のようなテンプレートを 1 つずつのハッシュタグにする
+pub fn hashtags_from_template(template: &str) -> Vec<String> {
+    template
+        .split_whitespace()
+        .map(|tag| match tag.starts_with('#') {
+            true => tag.to_string(),
+            false => format!("#{}", tag),
+        })
+        .collect()
+}
+
+/// Twitter と同じ数え方の文字数。URL は 23、日本語は 2 と数える
+pub fn weighted_length(text: &str) -> usize {
+    text.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let trimmed = word.trim_end();
+            if trimmed.starts_with("https://") || trimmed.starts_with("http://") {
+                URL_LENGTH + (word.len() - trimmed.len())
+            } else {
+                word.chars().map(char_weight).sum()
+            }
+        })
+        .sum()
+}
+
+fn char_weight(c: char) -> usize {
+    let code = c as u32;
+    if LIGHT_RANGES
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&code))
+    {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_count_cjk_double() {
+        assert_eq!(weighted_length("GOAL"), 4);
+        assert_eq!(weighted_length("ゴール!"), 7);
+        assert_eq!(
+            weighted_length("同点 https://example.com/very/long/path"),
+            5 + 23
+        );
+        assert_eq!(weighted_length("⚽"), 2);
+
+        assert!(Draft::new(&"あ".repeat(140), &[], None).is_ok());
+        let err = Draft::new(&"あ".repeat(141), &[], None).unwrap_err();
+        assert!(err.is_error_of(PostError::TooLong));
+        assert!(Draft::new(&"a".repeat(280), &[], None).is_ok());
+    }
+
+    #[test]
+    fn it_should_append_missing_hashtags() {
+        let draft = Draft::new(
+            "いけー #SAMURAIBLUE",
+            &hashtags_from_template("#SAMURAIBLUE がんばれ日本"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(draft.text, "いけー #SAMURAIBLUE #がんばれ日本");
+        let err = Draft::new("  ", &[], None).unwrap_err();
+        assert!(err.is_error_of(PostError::EmptyText));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::domain::model::SearchQuery;
+use crate::error::*;
+use chrono::{DateTime, Duration, Utc};
+use serde::*;
+
+/// キックオフから試合終了までの目安 (ハーフタイム込み)
+pub const MATCH_DURATION_MINUTES: i64 = 115;
+
+#[derive(Debug)]
+pub enum FixtureError {
+    InvalidFile,
+    UnknownTeam,
+    UnknownStage,
+    NotFound,
+}
+
+impl IServiceError for FixtureError {
+    fn error_type(&self) -> String {
+        use FixtureError::*;
+
+        match self {
+            InvalidFile => "invalid_fixture_file",
+            UnknownTeam => "unknown_team",
+            UnknownStage => "unknown_stage",
+            NotFound => "match_not_found",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use FixtureError::*;
+
+        match self {
+            InvalidFile | UnknownTeam | UnknownStage => http::StatusCode::BAD_REQUEST,
+            NotFound => http::StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Team {
+    /// FIFA の国コード (JPN, CRO, ...)
+    pub code: String,
+    pub name: String,
+    /// チームのハッシュタグ (`#` なし)
+    #[serde(default)]
+    pub hashtags: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Stage {
+    /// グループ名 (A-H)
+    Group(String),
+    RoundOf16,
+    QuarterFinal,
+    SemiFinal,
+    ThirdPlace,
+    Final,
+}
+
+impl Stage {
+    /// `group-e`, `round-of-16`, `quarter-final`, `semi-final`, `third-place`, `final`
+    pub fn parse(name: &str) -> Result<Stage> {
+        use Stage::*;
+
+        match name {
+            "round-of-16" => Ok(RoundOf16),
+            "quarter-final" => Ok(QuarterFinal),
+            "semi-final" => Ok(SemiFinal),
+            "third-place" => Ok(ThirdPlace),
+            "final" => Ok(Final),
+            _ => match name.strip_prefix("group-") {
+                Some(group) if !group.is_empty() => Ok(Group(group.to_uppercase())),
+                _ => Err(ServiceError::new(
+                    FixtureError::UnknownStage,
+                    anyhow::anyhow!("unknown stage `{}`", name),
+                )),
+            },
+        }
+    }
+
+    pub fn as_str(&self) -> String {
+        use Stage::*;
+
+        match self {
+            Group(group) => format!("group-{}", group.to_lowercase()),
+            RoundOf16 => "round-of-16".to_string(),
+            QuarterFinal => "quarter-final".to_string(),
+            SemiFinal => "semi-final".to_string(),
+            ThirdPlace => "third-place".to_string(),
+            Final => "final".to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        use Stage::*;
+
+        match self {
+            Group(group) => format!("グループ{}", group),
+            RoundOf16 => "ラウンド16".to_string(),
+            QuarterFinal => "準々決勝".to_string(),
+            SemiFinal => "準決勝".to_string(),
+            ThirdPlace => "3位決定戦".to_string(),
+            Final => "決勝".to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for Stage {
+    type Error = String;
+
+    fn try_from(name: String) -> std::result::Result<Self, Self::Error> {
+        Stage::parse(&name).map_err(|err| err.to_string())
+    }
+}
+
+impl From<Stage> for String {
+    fn from(stage: Stage) -> Self {
+        stage.as_str()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchStatus {
+    Upcoming,
+    Live,
+    Finished,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub id: String,
+    pub stage: Stage,
+    pub home: Team,
+    pub away: Team,
+    pub kickoff: DateTime<Utc>,
+    pub venue: Option<String>,
+}
+
+impl Match {
+    pub fn status(&self, now: DateTime<Utc>) -> MatchStatus {
+        if now < self.kickoff {
+            MatchStatus::Upcoming
+        } else if now < self.kickoff + Duration::minutes(MATCH_DURATION_MINUTES) {
+            MatchStatus::Live
+        } else {
+            MatchStatus::Finished
+        }
+    }
+
+    pub fn involves(&self, code: &str) -> bool {
+        self.home.code.eq_ignore_ascii_case(code) || self.away.code.eq_ignore_ascii_case(code)
+    }
+
+    /// `#JPNCRO` のような試合のハッシュタグと、両チームのハッシュタグ
+    pub fn hashtags(&self) -> Vec<String> {
+        let mut hashtags = vec![format!("{}{}", self.home.code, self.away.code)];
+        for hashtag in self.home.hashtags.iter().chain(&self.away.hashtags) {
+            if !hashtags.contains(hashtag) {
+                hashtags.push(hashtag.clone());
+            }
+        }
+        hashtags
+    }
+
+    pub fn search_query(&self) -> SearchQuery {
+        SearchQuery::new().any_of(
+            self.hashtags()
+                .into_iter()
+                .map(|hashtag| SearchQuery::new().hashtag(hashtag))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn team(code: &str, hashtags: &[&str]) -> Team {
+        Team {
+            code: code.to_string(),
+            name: code.to_string(),
+            hashtags: hashtags.iter().map(|it| it.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn it_should_derive_query_from_teams() {
+        let game = Match {
+            id: "r16-jpn-cro".to_string(),
+            stage: Stage::parse("round-of-16").unwrap(),
+            home: team("JPN", &["SAMURAIBLUE", "WorldCup"]),
+            away: team("CRO", &["WorldCup"]),
+            kickoff: Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap(),
+            venue: None,
+        };
+        assert_eq!(
+            game.search_query().build().unwrap(),
+            "(#JPNCRO OR #SAMURAIBLUE OR #WorldCup) -is:retweet"
+        );
+        assert!(game.involves("cro"));
+
+        let kickoff = game.kickoff;
+        assert_eq!(
+            game.status(kickoff - Duration::minutes(1)),
+            MatchStatus::Upcoming
+        );
+        assert_eq!(
+            game.status(kickoff + Duration::minutes(90)),
+            MatchStatus::Live
+        );
+        assert_eq!(
+            game.status(kickoff + Duration::minutes(MATCH_DURATION_MINUTES)),
+            MatchStatus::Finished
+        );
+    }
+
+    #[test]
+    fn it_should_parse_stages() {
+        assert_eq!(
+            Stage::parse("group-e").unwrap(),
+            Stage::Group("E".to_string())
+        );
+        assert_eq!(Stage::parse("group-e").unwrap().as_str(), "group-e");
+        assert!(Stage::parse("group-").is_err());
+        assert!(Stage::parse("round-of-32").is_err());
+    }
+}
@@ -1,8 +1,70 @@
+use crate::error::*;
 use std::hash::Hash;
 
+#[derive(Debug)]
+pub enum IdentityError {
+    InvalidTweetId,
+}
+
+impl IServiceError for IdentityError {
+    fn error_type(&self) -> String {
+        use IdentityError::*;
+
+        match self {
+            InvalidTweetId => "invalid_tweet_id",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Default, Eq, Hash)]
 pub struct TweetID(pub String);
 
+/// ツイートの URL を出すサイト
+const STATUS_HOSTS: [&str; 2] = ["twitter.com", "x.com"];
+
+impl TweetID {
+    /// `1599763545434767360` か `https://x.com/JFA/status/1599763545434767360` の形
+    pub fn parse(input: &str) -> Result<TweetID> {
+        let input = input.trim();
+        let invalid = || {
+            ServiceError::new(
+                IdentityError::InvalidTweetId,
+                anyhow::anyhow!("`{}` is not a tweet ID or URL", input),
+            )
+        };
+        let is_id = |value: &str| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+        if is_id(input) {
+            return Ok(TweetID(input.to_string()));
+        }
+
+        let url = if input.contains("://") {
+            url::Url::parse(input)
+        } else {
+            url::Url::parse(&format!("https://{}", input))
+        }
+        .map_err(|_| invalid())?;
+        let host = url.host_str().ok_or_else(invalid)?;
+        let known = STATUS_HOSTS
+            .iter()
+            .any(|it| host == *it || host.ends_with(&format!(".{}", it)));
+        if !known {
+            return Err(invalid());
+        }
+        // `/<user>/status/<id>`, `/i/web/status/<id>`, `/<user>/statuses/<id>`
+        let segments = url.path_segments().ok_or_else(invalid)?.collect::<Vec<_>>();
+        segments
+            .windows(2)
+            .find(|pair| matches!(pair[0], "status" | "statuses") && is_id(pair[1]))
+            .map(|pair| TweetID(pair[1].to_string()))
+            .ok_or_else(invalid)
+    }
+}
+
 // TweetID to String
 impl From<TweetID> for String {
     fn from(tweet_id: TweetID) -> Self {
@@ -16,3 +78,32 @@ impl From<String> for TweetID {
         TweetID(tweet_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_ids_and_urls() {
+        let id = TweetID("1599763545434767360".to_string());
+        for input in [
+            "1599763545434767360",
+            "https://twitter.com/JFA/status/1599763545434767360",
+            "https://x.com/JFA/status/1599763545434767360?s=20",
+            "https://mobile.twitter.com/i/web/status/1599763545434767360",
+            "x.com/JFA/status/1599763545434767360/photo/1",
+        ] {
+            assert_eq!(TweetID::parse(input).unwrap(), id, "{}", input);
+        }
+        for input in [
+            "",
+            "12a",
+            "https://example.com/JFA/status/1599763545434767360",
+            "https://x.com/JFA",
+            "https://notx.com/JFA/status/1",
+        ] {
+            let err = TweetID::parse(input).unwrap_err();
+            assert!(err.is_error_of(IdentityError::InvalidTweetId), "{}", input);
+        }
+    }
+}
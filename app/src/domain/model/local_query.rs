@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// 保存済みのツイート (`tweet_records`) の検索条件
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocalQuery {
+    /// すべて本文に含む
+    pub texts: Vec<String>,
+    /// どれかの `author_id`
+    pub authors: Vec<String>,
+    pub lang: Option<String>,
+    /// 同じスレッドのツイート
+    pub conversation_id: Option<String>,
+    /// `created_at >= since`
+    pub since: Option<DateTime<Utc>>,
+    /// `created_at < until`
+    pub until: Option<DateTime<Utc>>,
+    /// `Some(false)` は `possibly_sensitive` が未設定のものも含む
+    pub sensitive: Option<bool>,
+    pub order: Order,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// `tweet_records.created_at` と同じ形 (`2022-12-05T15:00:00.000Z`)。文字列のまま比較できる
+pub fn to_created_at(at: &DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn it_should_format_like_twitter() {
+        let at = Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap();
+        assert_eq!(to_created_at(&at), "2022-12-05T15:00:00.000Z");
+    }
+}
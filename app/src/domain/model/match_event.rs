@@ -0,0 +1,163 @@
+use crate::error::*;
+use chrono::{DateTime, Utc};
+
+/// イベントの手がかりにするキーワード
+pub const EVENT_KEYWORDS: [&str; 4] = ["ゴール", "GOAL", "PK", "同点"];
+
+#[derive(Debug)]
+pub enum MatchEventError {
+    UnknownKind,
+    /// `event_hook` のコマンドを起動できなかった
+    HookFailed,
+}
+
+impl IServiceError for MatchEventError {
+    fn error_type(&self) -> String {
+        match self {
+            MatchEventError::UnknownKind => "unknown_event_kind",
+            MatchEventError::HookFailed => "event_hook_failed",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        match self {
+            MatchEventError::UnknownKind => http::StatusCode::BAD_REQUEST,
+            MatchEventError::HookFailed => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchEventKind {
+    Goal,
+    Penalty,
+    Equalizer,
+    /// キーワードなしでツイートが急増した
+    Spike,
+}
+
+impl MatchEventKind {
+    pub fn parse(name: &str) -> Result<MatchEventKind> {
+        use MatchEventKind::*;
+
+        match name {
+            "goal" => Ok(Goal),
+            "penalty" => Ok(Penalty),
+            "equalizer" => Ok(Equalizer),
+            "spike" => Ok(Spike),
+            _ => Err(ServiceError::new(
+                MatchEventError::UnknownKind,
+                anyhow::anyhow!("unknown event kind `{}`", name),
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        use MatchEventKind::*;
+
+        match self {
+            Goal => "goal",
+            Penalty => "penalty",
+            Equalizer => "equalizer",
+            Spike => "spike",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        use MatchEventKind::*;
+
+        match self {
+            Goal => "ゴール?",
+            Penalty => "PK?",
+            Equalizer => "同点?",
+            Spike => "盛り上がり",
+        }
+    }
+
+    /// `EVENT_KEYWORDS` のどれに当たるか
+    fn of_keyword(keyword: &str) -> MatchEventKind {
+        use MatchEventKind::*;
+
+        match keyword {
+            "PK" => Penalty,
+            "同点" => Equalizer,
+            _ => Goal,
+        }
+    }
+
+    /// キーワードを含むツイートの数が一番多い種類。同数なら具体的なもの (同点 > PK > ゴール) を優先する
+    pub fn from_texts<'a>(texts: impl IntoIterator<Item = &'a str>) -> MatchEventKind {
+        use MatchEventKind::*;
+
+        let mut counts = [(Equalizer, 0), (Penalty, 0), (Goal, 0)];
+        for text in texts {
+            let text = text.to_uppercase();
+            for (kind, count) in counts.iter_mut() {
+                if EVENT_KEYWORDS.iter().any(|keyword| {
+                    MatchEventKind::of_keyword(keyword) == *kind && text.contains(keyword)
+                }) {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .fold(
+                None,
+                |best: Option<(MatchEventKind, usize)>, &(kind, count)| match best {
+                    Some((_, best_count)) if best_count >= count => best,
+                    _ => Some((kind, count)),
+                },
+            )
+            .map_or(Spike, |(kind, _)| kind)
+    }
+}
+
+/// `search/recent` の勢いから推定した試合の出来事
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchEvent {
+    pub id: String,
+    pub kind: MatchEventKind,
+    pub detected_at: DateTime<Utc>,
+    pub query: String,
+    pub tweets_per_minute: f64,
+    /// 直前の窓の平均 (tweets/min)
+    pub baseline: f64,
+    /// 平均から標準偏差いくつ分離れたか
+    pub score: f64,
+    /// キーワードを含むツイート数
+    pub keyword_hits: usize,
+    pub sample_tweet_id: Option<String>,
+}
+
+impl MatchEvent {
+    /// `ゴール? 120 tweets/min (平均 15, z=7.0)`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {:.0} tweets/min (平均 {:.0}, z={:.1})",
+            self.kind.label(),
+            self.tweets_per_minute,
+            self.baseline,
+            self.score
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_pick_kind_from_keywords() {
+        use MatchEventKind::*;
+
+        assert_eq!(MatchEventKind::from_texts(["ゴール!!", "goal"]), Goal);
+        assert_eq!(MatchEventKind::from_texts(["同点ゴール", "ゴール"]), Goal);
+        assert_eq!(MatchEventKind::from_texts(["同点ゴール!"]), Equalizer);
+        assert_eq!(MatchEventKind::from_texts(["PK戦へ", "pk"]), Penalty);
+        assert_eq!(MatchEventKind::from_texts(["がんばれ"]), Spike);
+        assert_eq!(MatchEventKind::parse("equalizer").unwrap(), Equalizer);
+    }
+}
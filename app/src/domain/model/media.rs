@@ -0,0 +1,109 @@
+use crate::domain::model::Tweet;
+use serde::*;
+
+/// 添付の写真・動画・GIF。`includes.media` で返ってくる
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Media {
+    pub media_key: String,
+    /// `photo`, `video`, `animated_gif`
+    #[serde(rename = "type")]
+    pub media_type: String,
+    /// 写真のときだけ
+    pub url: Option<String>,
+    /// 動画・GIF のサムネイル
+    pub preview_image_url: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt_text: Option<String>,
+}
+
+impl Media {
+    pub fn is_photo(&self) -> bool {
+        self.media_type == "photo"
+    }
+
+    /// プレビューに使う画像。写真はそのもの、動画と GIF はサムネイル
+    pub fn image_url(&self) -> Option<&str> {
+        if self.is_photo() {
+            self.url.as_deref()
+        } else {
+            self.preview_image_url.as_deref()
+        }
+    }
+
+    /// `1:05` の形の再生時間
+    pub fn duration(&self) -> Option<String> {
+        let secs = self.duration_ms? / 1000;
+        Some(format!("{}:{:02}", secs / 60, secs % 60))
+    }
+
+    /// `📷 photo 1200x675` / `🎞 video 0:42`
+    pub fn summary(&self) -> String {
+        let mut summary = match self.media_type.as_str() {
+            "photo" => "📷 photo".to_string(),
+            "animated_gif" => "🎞 gif".to_string(),
+            other => format!("🎞 {}", other),
+        };
+        if let Some(duration) = self.duration() {
+            summary.push_str(&format!(" {}", duration));
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            summary.push_str(&format!(" {}x{}", width, height));
+        }
+        if let Some(alt_text) = &self.alt_text {
+            summary.push_str(&format!(" \"{}\"", alt_text.replace('\n', " ")));
+        }
+        summary
+    }
+}
+
+/// `attachments.media_keys` の順に `tweet.media` に入れる
+pub fn attach_media(tweets: &mut [Tweet], media: &[Media]) {
+    for tweet in tweets {
+        let keys = tweet.media_keys();
+        if keys.is_empty() {
+            continue;
+        }
+        tweet.media = keys
+            .iter()
+            .filter_map(|key| media.iter().find(|it| &it.media_key == key))
+            .cloned()
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_attach_media_in_key_order() {
+        let media: Vec<Media> = serde_json::from_str(
+            r#"[
+                {"media_key": "7_1", "type": "video", "duration_ms": 65400,
+                 "preview_image_url": "https://pbs.twimg.com/thumb.jpg", "width": 1280, "height": 720},
+                {"media_key": "3_1", "type": "photo", "url": "https://pbs.twimg.com/photo.jpg"}
+            ]"#,
+        )
+        .unwrap();
+        let mut tweets = vec![serde_json::from_value::<Tweet>(serde_json::json!({
+            "id": "1", "text": "ゴール!", "author_id": "2", "created_at": "2022-12-05T15:43:00.000Z",
+            "attachments": {"media_keys": ["3_1", "7_1", "unknown"]}
+        }))
+        .unwrap()];
+        attach_media(&mut tweets, &media);
+
+        let attached = &tweets[0].media;
+        assert_eq!(attached.len(), 2);
+        assert_eq!(
+            attached[0].image_url(),
+            Some("https://pbs.twimg.com/photo.jpg")
+        );
+        assert_eq!(attached[1].summary(), "🎞 video 1:05 1280x720");
+        assert_eq!(
+            attached[1].image_url(),
+            Some("https://pbs.twimg.com/thumb.jpg")
+        );
+    }
+}
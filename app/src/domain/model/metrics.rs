@@ -0,0 +1,232 @@
+use crate::error::*;
+use chrono::{DateTime, Duration, Utc};
+use serde::*;
+
+#[derive(Debug)]
+pub enum MetricsError {
+    UnknownRanking,
+    InvalidWindow,
+}
+
+impl IServiceError for MetricsError {
+    fn error_type(&self) -> String {
+        use MetricsError::*;
+
+        match self {
+            UnknownRanking => "unknown_ranking",
+            InvalidWindow => "invalid_window",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
+/// `public_metrics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublicMetrics {
+    pub like_count: u64,
+    pub retweet_count: u64,
+    pub reply_count: u64,
+    pub quote_count: u64,
+}
+
+impl PublicMetrics {
+    /// いいね・リツイート・リプライ・引用の合計
+    pub fn engagement(&self) -> u64 {
+        self.like_count + self.retweet_count + self.reply_count + self.quote_count
+    }
+
+    /// `♥ 120 🔁 30 💬 4 ❝ 2`
+    pub fn summary(&self) -> String {
+        format!(
+            "♥ {} 🔁 {} 💬 {} ❝ {}",
+            self.like_count, self.retweet_count, self.reply_count, self.quote_count
+        )
+    }
+}
+
+/// ある時点で見たツイートの数字
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricSnapshot {
+    pub tweet_id: String,
+    pub observed_at: DateTime<Utc>,
+    pub metrics: PublicMetrics,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ranking {
+    /// 最新の合計
+    Engagement,
+    /// 期間内に 1 分あたりどれだけ増えたか
+    Velocity,
+}
+
+impl Ranking {
+    pub const NAMES: [&'static str; 2] = ["engagement", "velocity"];
+
+    pub fn parse(name: &str) -> Result<Ranking> {
+        match name {
+            "engagement" => Ok(Ranking::Engagement),
+            "velocity" => Ok(Ranking::Velocity),
+            _ => Err(ServiceError::new(
+                MetricsError::UnknownRanking,
+                anyhow::anyhow!("unknown ranking `{}`", name),
+            )),
+        }
+    }
+}
+
+/// `30m`, `2h`, `1d`
+pub fn parse_window(value: &str) -> Result<Duration> {
+    let invalid = || {
+        ServiceError::new(
+            MetricsError::InvalidWindow,
+            anyhow::anyhow!("invalid window `{}` (e.g. 30m, 2h, 1d)", value),
+        )
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let amount = value[..value.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+    let window = match unit {
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        _ => return Err(invalid()),
+    };
+    if amount <= 0 {
+        return Err(invalid());
+    }
+    Ok(window)
+}
+
+/// `top` の 1 件
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedTweet {
+    pub tweet_id: String,
+    pub metrics: PublicMetrics,
+    /// 1 分あたりの増え方
+    pub velocity: f64,
+}
+
+/// ツイートごとに最新の数字と増え方を出して並べる
+///
+/// 期間内のスナップショットが 1 つだけなら、投稿から今までの平均を増え方とする。
+pub fn rank(
+    snapshots: &[MetricSnapshot],
+    created_at: impl Fn(&str) -> Option<DateTime<Utc>>,
+    ranking: Ranking,
+    now: DateTime<Utc>,
+) -> Vec<RankedTweet> {
+    let mut by_tweet: indexmap::IndexMap<&str, (&MetricSnapshot, &MetricSnapshot)> =
+        indexmap::IndexMap::new();
+    for snapshot in snapshots {
+        by_tweet
+            .entry(&snapshot.tweet_id)
+            .and_modify(|(first, last)| {
+                if snapshot.observed_at < first.observed_at {
+                    *first = snapshot;
+                }
+                if snapshot.observed_at >= last.observed_at {
+                    *last = snapshot;
+                }
+            })
+            .or_insert((snapshot, snapshot));
+    }
+
+    let per_minute = |gained: u64, since: DateTime<Utc>, until: DateTime<Utc>| {
+        let minutes = (until - since).num_seconds() as f64 / 60.0;
+        gained as f64 / minutes.max(1.0)
+    };
+    let mut ranked = by_tweet
+        .into_iter()
+        .map(|(tweet_id, (first, last))| {
+            let velocity = if first.observed_at < last.observed_at {
+                per_minute(
+                    last.metrics
+                        .engagement()
+                        .saturating_sub(first.metrics.engagement()),
+                    first.observed_at,
+                    last.observed_at,
+                )
+            } else {
+                let since = created_at(tweet_id).unwrap_or(last.observed_at);
+                per_minute(last.metrics.engagement(), since, now)
+            };
+            RankedTweet {
+                tweet_id: tweet_id.to_string(),
+                metrics: last.metrics,
+                velocity,
+            }
+        })
+        .collect::<Vec<_>>();
+    match ranking {
+        Ranking::Engagement => ranked.sort_by_key(|it| std::cmp::Reverse(it.metrics.engagement())),
+        Ranking::Velocity => ranked.sort_by(|a, b| b.velocity.total_cmp(&a.velocity)),
+    }
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot(tweet_id: &str, minute: u32, likes: u64) -> MetricSnapshot {
+        MetricSnapshot {
+            tweet_id: tweet_id.to_string(),
+            observed_at: Utc.with_ymd_and_hms(2022, 12, 5, 16, minute, 0).unwrap(),
+            metrics: PublicMetrics {
+                like_count: likes,
+                ..PublicMetrics::default()
+            },
+        }
+    }
+
+    #[test]
+    fn it_should_rank_by_engagement_and_velocity() {
+        let now = Utc.with_ymd_and_hms(2022, 12, 5, 16, 30, 0).unwrap();
+        let snapshots = vec![
+            // 大きいがほとんど伸びていない
+            snapshot("old", 0, 1000),
+            snapshot("old", 20, 1010),
+            // 小さいが急に伸びている
+            snapshot("goal", 10, 0),
+            snapshot("goal", 20, 300),
+            // 1 回しか見ていないので投稿からの平均
+            snapshot("once", 20, 100),
+        ];
+        let created_at = |_: &str| Some(Utc.with_ymd_and_hms(2022, 12, 5, 16, 10, 0).unwrap());
+
+        let ranked = rank(&snapshots, created_at, Ranking::Engagement, now);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|it| it.tweet_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["old", "goal", "once"]
+        );
+
+        let ranked = rank(&snapshots, created_at, Ranking::Velocity, now);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|it| (it.tweet_id.as_str(), it.velocity))
+                .collect::<Vec<_>>(),
+            vec![("goal", 30.0), ("once", 5.0), ("old", 0.5)]
+        );
+    }
+
+    #[test]
+    fn it_should_parse_windows() {
+        assert_eq!(parse_window("30m").unwrap(), Duration::minutes(30));
+        assert_eq!(parse_window("2h").unwrap(), Duration::hours(2));
+        for window in ["", "h", "0h", "-1d", "2w", "1１"] {
+            assert!(parse_window(window).is_err(), "{}", window);
+        }
+    }
+}
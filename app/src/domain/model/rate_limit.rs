@@ -0,0 +1,234 @@
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `search/recent` のレート制限のキー
+pub const SEARCH_RECENT_ENDPOINT: &str = "GET /2/tweets/search/recent";
+/// `search/stream` の接続のレート制限のキー
+pub const SEARCH_STREAM_ENDPOINT: &str = "GET /2/tweets/search/stream";
+/// ヘッダが無い 429 は Twitter の窓 (15 分) が明けるまで待つ
+const DEFAULT_RESET_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// リセットの時刻ちょうどだとまだ弾かれることがある
+const RESET_MARGIN: Duration = Duration::from_secs(1);
+
+/// `x-rate-limit-limit` / `x-rate-limit-remaining` / `x-rate-limit-reset`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: DateTime<Utc>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        let value = |name: &str| -> Option<i64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+        Some(RateLimit {
+            limit: value("x-rate-limit-limit")? as u32,
+            remaining: value("x-rate-limit-remaining")? as u32,
+            reset: Utc
+                .timestamp_opt(value("x-rate-limit-reset")?, 0)
+                .single()?,
+        })
+    }
+
+    /// リセットまでの時間。過ぎていれば 0
+    pub fn until_reset(&self, now: DateTime<Utc>) -> Duration {
+        (self.reset - now).to_std().unwrap_or(Duration::ZERO)
+    }
+}
+
+/// `GET /2/tweets/1234/liking_users` → `GET /2/tweets/:id/liking_users`
+pub fn endpoint(method: &str, url: &str) -> String {
+    let path = url::Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.split('?').next().unwrap_or_default().to_string());
+    let path = path
+        .split('/')
+        .enumerate()
+        .map(|(i, segment)| {
+            // 先頭の `/2` はバージョン
+            if i > 1 && !segment.is_empty() && segment.bytes().all(|it| it.is_ascii_digit()) {
+                ":id"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{} {}", method.to_uppercase(), path)
+}
+
+#[derive(Debug, Default)]
+struct State {
+    limits: HashMap<String, RateLimit>,
+    /// エンドポイントごとにポーリングしている数。残りを分け合う
+    pollers: HashMap<String, usize>,
+}
+
+/// エンドポイントごとのレート制限。`HttpClient` が更新し、ポーリングの間隔に使う
+#[derive(Clone, Debug, Default)]
+pub struct RateLimits {
+    state: Arc<Mutex<State>>,
+}
+
+impl RateLimits {
+    pub fn get(&self, endpoint: &str) -> Option<RateLimit> {
+        self.state.lock().unwrap().limits.get(endpoint).copied()
+    }
+
+    /// レスポンスのヘッダを反映する。ヘッダの無い 429 は残り 0 とみなす
+    pub fn record(&self, endpoint: String, status: http::StatusCode, headers: &HeaderMap) {
+        let limit = match RateLimit::from_headers(headers) {
+            Some(limit) => limit,
+            None if status == http::StatusCode::TOO_MANY_REQUESTS => {
+                let mut limit = self.get(&endpoint).unwrap_or(RateLimit {
+                    limit: 0,
+                    remaining: 0,
+                    reset: Utc::now(),
+                });
+                limit.remaining = 0;
+                if limit.reset <= Utc::now() {
+                    limit.reset =
+                        Utc::now() + chrono::Duration::from_std(DEFAULT_RESET_WINDOW).unwrap();
+                }
+                limit
+            }
+            None => return,
+        };
+        self.state.lock().unwrap().limits.insert(endpoint, limit);
+    }
+
+    /// ポーリングしている間は残りを分け合う。戻り値を drop すると抜ける
+    pub fn register(&self, endpoint: &str) -> Registration {
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .pollers
+            .entry(endpoint.to_string())
+            .or_default() += 1;
+        Registration {
+            rate_limits: self.clone(),
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    /// 次のリクエストまで待つ時間
+    ///
+    /// 残りをリセットまでに均等に使う間隔と `base` の長い方にする。残りが無ければリセットまで待つ。
+    pub fn pace(&self, endpoint: &str, base: Duration, now: DateTime<Utc>) -> Duration {
+        let state = self.state.lock().unwrap();
+        let Some(limit) = state.limits.get(endpoint) else {
+            return base;
+        };
+        let until_reset = limit.until_reset(now);
+        if until_reset.is_zero() {
+            return base;
+        }
+        let pollers = state.pollers.get(endpoint).copied().unwrap_or(0).max(1);
+        let budget = limit.remaining as f64 / pollers as f64;
+        if budget < 1.0 {
+            return until_reset + RESET_MARGIN;
+        }
+        until_reset.div_f64(budget).max(base)
+    }
+}
+
+pub struct Registration {
+    rate_limits: RateLimits,
+    endpoint: String,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut state = self.rate_limits.state.lock().unwrap();
+        if let Some(count) = state.pollers.get_mut(&self.endpoint) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(limit: u32, remaining: u32, reset: i64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-rate-limit-limit", limit.into());
+        headers.insert("x-rate-limit-remaining", remaining.into());
+        headers.insert("x-rate-limit-reset", reset.into());
+        headers
+    }
+
+    #[test]
+    fn it_should_normalize_endpoints() {
+        assert_eq!(
+            endpoint(
+                "get",
+                "https://api.twitter.com/2/tweets/search/recent?query=a&since_id=1"
+            ),
+            SEARCH_RECENT_ENDPOINT
+        );
+        assert_eq!(
+            endpoint(
+                "DELETE",
+                "https://api.twitter.com/2/tweets/1599771355208675328"
+            ),
+            "DELETE /2/tweets/:id"
+        );
+    }
+
+    #[test]
+    fn it_should_pace_within_budget() {
+        let now = Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap();
+        let base = Duration::from_secs(5);
+        let rate_limits = RateLimits::default();
+        assert_eq!(rate_limits.pace(SEARCH_RECENT_ENDPOINT, base, now), base);
+
+        // 15 分で 450 回なら 5 秒より短くしない
+        let reset = (now + chrono::Duration::minutes(15)).timestamp();
+        let ok = http::StatusCode::OK;
+        rate_limits.record(
+            SEARCH_RECENT_ENDPOINT.to_string(),
+            ok,
+            &headers(450, 450, reset),
+        );
+        assert_eq!(rate_limits.pace(SEARCH_RECENT_ENDPOINT, base, now), base);
+
+        // 残り 60 回を 2 つで分けると 30 秒ごと
+        rate_limits.record(
+            SEARCH_RECENT_ENDPOINT.to_string(),
+            ok,
+            &headers(450, 60, reset),
+        );
+        let first = rate_limits.register(SEARCH_RECENT_ENDPOINT);
+        let second = rate_limits.register(SEARCH_RECENT_ENDPOINT);
+        assert_eq!(
+            rate_limits.pace(SEARCH_RECENT_ENDPOINT, base, now),
+            Duration::from_secs(30)
+        );
+        drop(second);
+        assert_eq!(
+            rate_limits.pace(SEARCH_RECENT_ENDPOINT, base, now),
+            Duration::from_secs(15)
+        );
+        drop(first);
+
+        // 429 はリセットまで待つ
+        rate_limits.record(
+            SEARCH_RECENT_ENDPOINT.to_string(),
+            http::StatusCode::TOO_MANY_REQUESTS,
+            &headers(450, 0, reset),
+        );
+        assert_eq!(
+            rate_limits.pace(SEARCH_RECENT_ENDPOINT, base, now),
+            Duration::from_secs(15 * 60 + 1)
+        );
+        assert_eq!(
+            rate_limits.get(SEARCH_RECENT_ENDPOINT).unwrap().remaining,
+            0
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use crate::error::*;
+
+/// Twitter API v2 の `query` パラメータの上限 (Essential / Elevated)
+pub const MAX_QUERY_LENGTH: usize = 512;
+
+#[derive(Debug)]
+pub enum SearchQueryError {
+    Empty,
+    TooLong,
+    MissingStandaloneOperator,
+    /// フレーズ検索の `"..."` の中に `"` は書けない
+    InvalidKeyword,
+}
+
+impl IServiceError for SearchQueryError {
+    fn error_type(&self) -> String {
+        use SearchQueryError::*;
+
+        match self {
+            Empty => "empty_query",
+            TooLong => "query_too_long",
+            MissingStandaloneOperator => "missing_standalone_operator",
+            InvalidKeyword => "invalid_keyword",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Term {
+    Keyword(String),
+    /// 設定ファイルなどに書かれた演算子込みのクエリをそのまま使う
+    Raw(String),
+    Hashtag(String),
+    From(String),
+    /// 同じスレッドのツイート
+    Conversation(String),
+    Lang(String),
+    HasMedia,
+    IsReply,
+    AnyOf(Vec<SearchQuery>),
+    Not(Box<SearchQuery>),
+}
+
+impl Term {
+    /// `lang:` や `has:` などは単体では使えない (conjunction-required operators)
+    fn is_standalone(&self) -> bool {
+        use Term::*;
+
+        match self {
+            Keyword(_) | Raw(_) | Hashtag(_) | From(_) | Conversation(_) => true,
+            AnyOf(queries) => queries.iter().all(SearchQuery::has_standalone),
+            Lang(_) | HasMedia | IsReply | Not(_) => false,
+        }
+    }
+
+    /// 否定するときに括弧が不要かどうか
+    fn is_atomic(&self) -> bool {
+        use Term::*;
+
+        match self {
+            Raw(raw) => !raw.chars().any(char::is_whitespace),
+            AnyOf(queries) => queries.len() > 1 || queries.iter().all(SearchQuery::is_atomic),
+            _ => true,
+        }
+    }
+
+    fn render(&self) -> Result<String> {
+        use Term::*;
+
+        Ok(match self {
+            Keyword(keyword) => quote(keyword)?,
+            Raw(raw) => raw.clone(),
+            Hashtag(tag) => format!("#{}", tag.trim_start_matches('#')),
+            From(user) => format!("from:{}", user.trim_start_matches('@')),
+            Conversation(id) => format!("conversation_id:{}", id),
+            Lang(lang) => format!("lang:{}", lang),
+            HasMedia => "has:media".to_string(),
+            IsReply => "is:reply".to_string(),
+            AnyOf(queries) => {
+                let rendered = queries
+                    .iter()
+                    .map(SearchQuery::render_terms)
+                    .collect::<Result<Vec<_>>>()?;
+                if rendered.len() == 1 {
+                    rendered[0].clone()
+                } else {
+                    format!("({})", rendered.join(" OR "))
+                }
+            }
+            Not(query) => {
+                if query.is_atomic() {
+                    format!("-{}", query.render_terms()?)
+                } else {
+                    format!("-({})", query.render_terms()?)
+                }
+            }
+        })
+    }
+}
+
+fn quote(keyword: &str) -> Result<String> {
+    if !keyword.chars().any(char::is_whitespace) {
+        return Ok(keyword.to_string());
+    }
+    if keyword.contains('"') {
+        return Err(ServiceError::new(
+            SearchQueryError::InvalidKeyword,
+            anyhow::anyhow!("keyword `{}` cannot contain `\"` inside a phrase", keyword),
+        ));
+    }
+    Ok(format!("\"{}\"", keyword))
+}
+
+/// 検索クエリのビルダー
+///
+/// ```ignore
+/// let query = SearchQuery::new()
+///     .keyword("ワールドカップ")
+///     .hashtag("SAMURAIBLUE")
+///     .lang("ja");
+/// assert_eq!(query.build()?, "ワールドカップ #SAMURAIBLUE lang:ja -is:retweet");
+/// ```
+///
+/// リツイートはデフォルトで除外する。
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    terms: Vec<Term>,
+    include_retweets: bool,
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.terms.push(Term::Keyword(keyword.into()));
+        self
+    }
+
+    pub fn raw(mut self, raw: impl Into<String>) -> Self {
+        self.terms.push(Term::Raw(raw.into()));
+        self
+    }
+
+    pub fn hashtag(mut self, hashtag: impl Into<String>) -> Self {
+        self.terms.push(Term::Hashtag(hashtag.into()));
+        self
+    }
+
+    pub fn author(mut self, username: impl Into<String>) -> Self {
+        self.terms.push(Term::From(username.into()));
+        self
+    }
+
+    pub fn conversation(mut self, id: impl Into<String>) -> Self {
+        self.terms.push(Term::Conversation(id.into()));
+        self
+    }
+
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.terms.push(Term::Lang(lang.into()));
+        self
+    }
+
+    pub fn has_media(mut self) -> Self {
+        self.terms.push(Term::HasMedia);
+        self
+    }
+
+    pub fn only_replies(mut self) -> Self {
+        self.terms.push(Term::IsReply);
+        self
+    }
+
+    pub fn include_retweets(mut self, include: bool) -> Self {
+        self.include_retweets = include;
+        self
+    }
+
+    /// `(a OR b OR c)`
+    pub fn any_of(mut self, queries: Vec<SearchQuery>) -> Self {
+        if !queries.is_empty() {
+            self.terms.push(Term::AnyOf(queries));
+        }
+        self
+    }
+
+    /// `-a` / `-(a b)`
+    pub fn exclude(mut self, query: SearchQuery) -> Self {
+        if !query.terms.is_empty() {
+            self.terms.push(Term::Not(Box::new(query)));
+        }
+        self
+    }
+
+    fn has_standalone(&self) -> bool {
+        self.terms.iter().any(Term::is_standalone)
+    }
+
+    fn is_atomic(&self) -> bool {
+        self.terms.len() == 1 && self.terms[0].is_atomic()
+    }
+
+    fn render_terms(&self) -> Result<String> {
+        Ok(self
+            .terms
+            .iter()
+            .map(Term::render)
+            .collect::<Result<Vec<_>>>()?
+            .join(" "))
+    }
+
+    /// API に渡すクエリ文字列を組み立てる (URL エンコード前)
+    pub fn build(&self) -> Result<String> {
+        if self.terms.is_empty() {
+            return Err(ServiceError::only(SearchQueryError::Empty));
+        }
+        if !self.has_standalone() {
+            return Err(ServiceError::new(
+                SearchQueryError::MissingStandaloneOperator,
+                anyhow::anyhow!("query needs a keyword, #hashtag or from: operator"),
+            ));
+        }
+
+        let mut query = self.render_terms()?;
+        if !self.include_retweets {
+            query.push_str(" -is:retweet");
+        }
+
+        let length = query.chars().count();
+        if length > MAX_QUERY_LENGTH {
+            return Err(ServiceError::new(
+                SearchQueryError::TooLong,
+                anyhow::anyhow!("query is {} characters (max {})", length, MAX_QUERY_LENGTH),
+            ));
+        }
+
+        Ok(query)
+    }
+
+    /// `query=...` の形で URL エンコードしたもの
+    pub fn to_query_param(&self) -> Result<String> {
+        let query = self.build()?;
+        Ok(url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("query", &query)
+            .finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_build_operators() {
+        let query = SearchQuery::new()
+            .keyword("ワールドカップ")
+            .hashtag("#SAMURAIBLUE")
+            .author("@JFA")
+            .lang("ja")
+            .has_media()
+            .only_replies();
+        assert_eq!(
+            query.build().unwrap(),
+            "ワールドカップ #SAMURAIBLUE from:JFA lang:ja has:media is:reply -is:retweet"
+        );
+        assert_eq!(
+            SearchQuery::new()
+                .conversation("1599763545434767360")
+                .build()
+                .unwrap(),
+            "conversation_id:1599763545434767360 -is:retweet"
+        );
+    }
+
+    #[test]
+    fn it_should_build_groups_and_negation() {
+        let query = SearchQuery::new()
+            .any_of(vec![
+                SearchQuery::new().keyword("本田圭佑"),
+                SearchQuery::new().keyword("Keisuke Honda"),
+            ])
+            .exclude(SearchQuery::new().keyword("ドイツ"))
+            .exclude(SearchQuery::new().keyword("PK").hashtag("負け"))
+            .exclude(SearchQuery::new().any_of(vec![
+                SearchQuery::new().keyword("a"),
+                SearchQuery::new().keyword("b"),
+            ]))
+            .include_retweets(true);
+        assert_eq!(
+            query.build().unwrap(),
+            "(本田圭佑 OR \"Keisuke Honda\") -ドイツ -(PK #負け) -(a OR b)"
+        );
+    }
+
+    #[test]
+    fn it_should_require_standalone_operator() {
+        let err = SearchQuery::new().lang("ja").build().unwrap_err();
+        assert!(err.is_error_of(SearchQueryError::MissingStandaloneOperator));
+
+        let err = SearchQuery::new().build().unwrap_err();
+        assert!(err.is_error_of(SearchQueryError::Empty));
+    }
+
+    #[test]
+    fn it_should_reject_quote_in_phrase() {
+        let err = SearchQuery::new()
+            .keyword("Keisuke \"KH\" Honda")
+            .build()
+            .unwrap_err();
+        assert!(err.is_error_of(SearchQueryError::InvalidKeyword));
+
+        let err = SearchQuery::new()
+            .hashtag("SAMURAIBLUE")
+            .exclude(SearchQuery::new().keyword("\"PK 負け"))
+            .build()
+            .unwrap_err();
+        assert!(err.is_error_of(SearchQueryError::InvalidKeyword));
+    }
+
+    #[test]
+    fn it_should_check_length() {
+        let query = SearchQuery::new().keyword("あ".repeat(MAX_QUERY_LENGTH));
+        let err = query.build().unwrap_err();
+        assert!(err.is_error_of(SearchQueryError::TooLong));
+    }
+
+    #[test]
+    fn it_should_encode_query() {
+        let query = SearchQuery::new().keyword("W杯&日本").hashtag("日本代表");
+        assert_eq!(
+            query.to_query_param().unwrap(),
+            "query=W%E6%9D%AF%26%E6%97%A5%E6%9C%AC+%23%E6%97%A5%E6%9C%AC%E4%BB%A3%E8%A1%A8+-is%3Aretweet"
+        );
+    }
+}
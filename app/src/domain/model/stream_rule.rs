@@ -0,0 +1,66 @@
+use crate::error::*;
+use serde::*;
+
+#[derive(Debug)]
+pub enum StreamRuleError {
+    EmptyRule,
+    NoRules,
+}
+
+impl IServiceError for StreamRuleError {
+    fn error_type(&self) -> String {
+        use StreamRuleError::*;
+
+        match self {
+            EmptyRule => "empty_rule",
+            NoRules => "no_stream_rules",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
+/// `search/stream` のルール。`id` は追加したときに振られる
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+impl StreamRule {
+    pub fn new(value: &str, tag: Option<&str>) -> Result<StreamRule> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(ServiceError::only(StreamRuleError::EmptyRule));
+        }
+        Ok(StreamRule {
+            id: None,
+            value: value.to_string(),
+            tag: tag
+                .map(str::trim)
+                .filter(|it| !it.is_empty())
+                .map(String::from),
+        })
+    }
+}
+
+/// ストリームで届いたツイートが当たったルール
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchingRule {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+impl MatchingRule {
+    /// タグが無ければ ID
+    pub fn label(&self) -> &str {
+        self.tag.as_deref().unwrap_or(&self.id)
+    }
+}
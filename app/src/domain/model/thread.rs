@@ -0,0 +1,147 @@
+use crate::domain::model::Tweet;
+use std::collections::{HashMap, HashSet};
+
+/// `thread` で表示する 1 件
+#[derive(Debug, Clone)]
+pub struct ThreadEntry {
+    pub tweet: Tweet,
+    /// 返信の深さ。返信先が見つからないツイートは 0
+    pub depth: usize,
+    /// スレッド内で見つかった直接の返信の数
+    pub replies: usize,
+    /// 引用したツイート
+    pub quoted: Option<Tweet>,
+}
+
+/// `replied_to` をたどって返信の木にし、深さ優先で並べる。兄弟は古い順
+///
+/// 同じ ID が複数あれば後のものを使う。
+pub fn build_thread(tweets: Vec<Tweet>) -> Vec<ThreadEntry> {
+    let mut by_id: HashMap<String, Tweet> = HashMap::new();
+    for tweet in tweets {
+        by_id.insert(tweet.id.clone(), tweet);
+    }
+    let order = |tweets: &mut Vec<&Tweet>| {
+        tweets.sort_by(|a, b| (&a.created_at, &a.id).cmp(&(&b.created_at, &b.id)));
+    };
+
+    let mut children: HashMap<&str, Vec<&Tweet>> = HashMap::new();
+    let mut roots = Vec::new();
+    for tweet in by_id.values() {
+        match tweet.replied_to().filter(|id| by_id.contains_key(id)) {
+            Some(parent) => children
+                .entry(by_id[&parent].id.as_str())
+                .or_default()
+                .push(tweet),
+            None => roots.push(tweet),
+        }
+    }
+    order(&mut roots);
+    for replies in children.values_mut() {
+        order(replies);
+    }
+
+    let mut entries = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = roots
+        .into_iter()
+        .rev()
+        .map(|it| (it, 0))
+        .collect::<Vec<_>>();
+    loop {
+        while let Some((tweet, depth)) = stack.pop() {
+            if !visited.insert(tweet.id.as_str()) {
+                continue;
+            }
+            let replies = children.get(tweet.id.as_str()).cloned().unwrap_or_default();
+            entries.push(ThreadEntry {
+                tweet: tweet.clone(),
+                depth,
+                replies: replies.len(),
+                quoted: tweet.quoted().and_then(|id| by_id.get(&id).cloned()),
+            });
+            stack.extend(replies.into_iter().rev().map(|it| (it, depth + 1)));
+        }
+        // 返信先が循環していると根からたどれないので、残りは根として並べる
+        let mut rest = by_id
+            .values()
+            .filter(|tweet| !visited.contains(tweet.id.as_str()))
+            .collect::<Vec<_>>();
+        order(&mut rest);
+        match rest.first() {
+            Some(tweet) => stack.push((tweet, 0)),
+            None => break,
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str, minute: u32, references: &[(&str, &str)]) -> Tweet {
+        let referenced_tweets = references
+            .iter()
+            .map(|(kind, id)| serde_json::json!({ "type": kind, "id": id }))
+            .collect::<Vec<_>>();
+        let mut tweet = Tweet::new(
+            id.to_string(),
+            format!("tweet {}", id),
+            "1".to_string(),
+            format!("2022-12-05T16:{:02}:00.000Z", minute),
+            None,
+            None,
+            None,
+            None,
+            None,
+            (!referenced_tweets.is_empty()).then_some(referenced_tweets),
+            None,
+            None,
+        );
+        tweet.conversation_id = Some("root".to_string());
+        tweet
+    }
+
+    fn shape(entries: &[ThreadEntry]) -> Vec<(&str, usize, usize)> {
+        entries
+            .iter()
+            .map(|it| (it.tweet.id.as_str(), it.depth, it.replies))
+            .collect()
+    }
+
+    #[test]
+    fn it_should_nest_replies_in_order() {
+        let entries = build_thread(vec![
+            tweet("b", 3, &[("replied_to", "root")]),
+            tweet("a", 1, &[("replied_to", "root")]),
+            tweet("a1", 2, &[("replied_to", "a"), ("quoted", "b")]),
+            tweet("root", 0, &[]),
+            // 返信先が手元に無い
+            tweet("orphan", 4, &[("replied_to", "missing")]),
+        ]);
+        assert_eq!(
+            shape(&entries),
+            vec![
+                ("root", 0, 2),
+                ("a", 1, 1),
+                ("a1", 2, 0),
+                ("b", 1, 0),
+                ("orphan", 0, 0)
+            ]
+        );
+        assert_eq!(
+            entries[2].quoted.as_ref().map(|it| it.id.as_str()),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn it_should_not_loop_on_cycles() {
+        let entries = build_thread(vec![
+            tweet("x", 1, &[("replied_to", "y")]),
+            tweet("y", 2, &[("replied_to", "x")]),
+        ]);
+        assert_eq!(shape(&entries), vec![("x", 0, 1), ("y", 1, 1)]);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::domain::model::{MatchingRule, Media, MetricSnapshot, PublicMetrics, User};
+use crate::error::ServiceError;
 use serde::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,9 +17,46 @@ pub struct Tweet {
     pub referenced_tweets: Option<Vec<serde_json::Value>>,
     pub source: Option<String>,
     pub withheld: Option<serde_json::Value>,
+    /// スレッドの最初のツイートの ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    /// いいねなどの数。`tweet_metric_snapshots` に残す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_metrics: Option<PublicMetrics>,
+    /// `{"media_keys": [...]}`。保存はせず、`media` を引くのに使う
+    #[serde(default, skip_serializing)]
+    pub attachments: Option<serde_json::Value>,
+    /// `includes.media` か保存済みの添付
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<Media>,
+    /// `includes.users` か保存済みのユーザー。無ければ `author_id` だけで表示する
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<User>,
+    /// `search/stream` で届いたときに当たったルール
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matching_rules: Vec<MatchingRule>,
+}
+
+/// `search/recent` の 1 ページ分
+#[derive(Debug, Default)]
+pub struct TweetPage {
+    pub tweets: Vec<Tweet>,
+    pub next_token: Option<String>,
+    /// 一緒に返ってきた部分エラー
+    pub errors: Vec<ServiceError>,
+}
+
+/// `search/stream` から届くもの
+#[derive(Debug)]
+pub enum StreamMessage {
+    /// 当たったルール入りのツイート
+    Tweet(Box<Tweet>),
+    /// ツイートと一緒に届いた部分エラーか、読めなかった行。接続は続く
+    Problem(ServiceError),
 }
 
 impl Tweet {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         text: String,
@@ -45,6 +84,122 @@ impl Tweet {
             referenced_tweets,
             source,
             withheld,
+            conversation_id: None,
+            public_metrics: None,
+            attachments: None,
+            media: Vec::new(),
+            author: None,
+            matching_rules: Vec::new(),
         }
     }
+
+    /// `@username (name)`。著者が分からなければ `author_id`
+    pub fn author_label(&self) -> String {
+        self.author
+            .as_ref()
+            .map_or_else(|| self.author_id.clone(), User::label)
+    }
+
+    /// `entities.<kind>[].<key>` を集める
+    fn entity_values(&self, kind: &str, key: &str) -> Vec<String> {
+        self.entities
+            .as_ref()
+            .and_then(|entities| entities.get(kind))
+            .and_then(|values| values.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.get(key).and_then(|it| it.as_str()))
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn hashtags(&self) -> Vec<String> {
+        self.entity_values("hashtags", "tag")
+    }
+
+    pub fn mentions(&self) -> Vec<String> {
+        self.entity_values("mentions", "username")
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.entity_values("urls", "expanded_url")
+    }
+
+    pub fn media_keys(&self) -> Vec<String> {
+        self.attachments
+            .as_ref()
+            .and_then(|attachments| attachments.get("media_keys"))
+            .and_then(|keys| keys.as_array())
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|key| key.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// `(type, id)` (`replied_to`, `quoted`, `retweeted`)
+    pub fn references(&self) -> Vec<(String, String)> {
+        self.referenced_tweets
+            .iter()
+            .flatten()
+            .filter_map(|reference| {
+                let kind = reference.get("type")?.as_str()?;
+                let id = reference.get("id")?.as_str()?;
+                Some((kind.to_string(), id.to_string()))
+            })
+            .collect()
+    }
+
+    /// 返信先のツイートの ID
+    pub fn replied_to(&self) -> Option<String> {
+        self.references()
+            .into_iter()
+            .find(|(kind, _)| kind == "replied_to")
+            .map(|(_, id)| id)
+    }
+
+    /// 引用したツイートの ID
+    pub fn quoted(&self) -> Option<String> {
+        self.references()
+            .into_iter()
+            .find(|(kind, _)| kind == "quoted")
+            .map(|(_, id)| id)
+    }
+
+    /// `[goal,jpn] ` の形のルールのタグ。ストリームで届いたものでなければ空
+    pub fn rule_labels(&self) -> String {
+        if self.matching_rules.is_empty() {
+            return String::new();
+        }
+        let labels = self
+            .matching_rules
+            .iter()
+            .map(MatchingRule::label)
+            .collect::<Vec<_>>();
+        format!("[{}] ", labels.join(","))
+    }
+
+    /// `created_at` (RFC 3339) をローカル時刻にする
+    pub fn created_at_local(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        chrono::DateTime::parse_from_rfc3339(&self.created_at)
+            .ok()
+            .map(|it| it.with_timezone(&chrono::Local))
+    }
+
+    /// `public_metrics` が返ってきていれば `observed_at` 時点の数字として残す
+    pub fn metric_snapshot(
+        &self,
+        observed_at: chrono::DateTime<chrono::Utc>,
+    ) -> Option<MetricSnapshot> {
+        Some(MetricSnapshot {
+            tweet_id: self.id.clone(),
+            observed_at,
+            metrics: self.public_metrics?,
+        })
+    }
 }
@@ -0,0 +1,50 @@
+use crate::error::*;
+use chrono::{DateTime, Utc};
+
+/// Twitter API が返したエラーの種類
+#[derive(Debug)]
+pub enum TwitterApiError {
+    Unauthorized,
+    Forbidden,
+    /// `reset` を過ぎれば使える
+    RateLimited {
+        reset: Option<DateTime<Utc>>,
+    },
+    InvalidQuery,
+    /// 削除されたか存在しない
+    NotFound,
+    ServiceUnavailable,
+    /// 上のどれにも当たらない problem details
+    Problem,
+}
+
+impl IServiceError for TwitterApiError {
+    fn error_type(&self) -> String {
+        use TwitterApiError::*;
+
+        match self {
+            Unauthorized => "unauthorized",
+            Forbidden => "forbidden",
+            RateLimited { .. } => "rate_limited",
+            InvalidQuery => "invalid_query",
+            NotFound => "not_found",
+            ServiceUnavailable => "service_unavailable",
+            Problem => "twitter_api_problem",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use TwitterApiError::*;
+
+        match self {
+            Unauthorized => http::StatusCode::UNAUTHORIZED,
+            Forbidden => http::StatusCode::FORBIDDEN,
+            RateLimited { .. } => http::StatusCode::TOO_MANY_REQUESTS,
+            InvalidQuery => http::StatusCode::BAD_REQUEST,
+            NotFound => http::StatusCode::NOT_FOUND,
+            ServiceUnavailable => http::StatusCode::SERVICE_UNAVAILABLE,
+            Problem => http::StatusCode::BAD_GATEWAY,
+        }
+    }
+}
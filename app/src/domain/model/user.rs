@@ -0,0 +1,70 @@
+use crate::domain::model::Tweet;
+use chrono::{DateTime, Duration, Utc};
+use serde::*;
+
+/// これより古いプロフィールは取り直す
+pub const USER_TTL_HOURS: i64 = 24;
+
+/// ツイートの著者。`includes.users` や `/2/users` で返ってくる
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    pub created_at: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub profile_image_url: Option<String>,
+    pub url: Option<String>,
+    pub protected: Option<bool>,
+    pub verified: Option<bool>,
+    pub public_metrics: Option<serde_json::Value>,
+    /// API から取ってきた時刻
+    #[serde(skip_serializing, default = "Utc::now")]
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl User {
+    /// `@username (name)`
+    pub fn label(&self) -> String {
+        format!("@{} ({})", self.username, self.name)
+    }
+
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        now - self.fetched_at >= Duration::hours(USER_TTL_HOURS)
+    }
+}
+
+/// `author_id` が一致するユーザーを `tweet.author` に入れる。見つからなければそのまま
+pub fn attach_authors(tweets: &mut [Tweet], users: &[User]) {
+    for tweet in tweets {
+        if let Some(user) = users.iter().find(|user| user.id == tweet.author_id) {
+            tweet.author = Some(user.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn it_should_parse_includes_user() {
+        let user: User = serde_json::from_str(
+            r#"{"id": "2244994945", "name": "Twitter Dev", "username": "TwitterDev", "verified": true,
+                "public_metrics": {"followers_count": 513958}}"#,
+        )
+        .unwrap();
+        assert_eq!(user.label(), "@TwitterDev (Twitter Dev)");
+
+        let now = user.fetched_at;
+        assert!(!user.is_stale(now + Duration::hours(1)));
+        assert!(user.is_stale(now + Duration::hours(USER_TTL_HOURS)));
+
+        let fetched_at = Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap();
+        assert!(!serde_json::to_string(&User { fetched_at, ..user })
+            .unwrap()
+            .contains("fetched_at"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::domain::model::{SearchQuery, SearchQueryError};
+use crate::error::*;
+
+#[derive(Debug)]
+pub enum WatchError {
+    NotFound,
+    Builtin,
+    Invalid,
+}
+
+impl IServiceError for WatchError {
+    fn error_type(&self) -> String {
+        use WatchError::*;
+
+        match self {
+            NotFound => "unknown_watch_entry",
+            Builtin => "builtin_watch_entry",
+            Invalid => "invalid_watch_entry",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use WatchError::*;
+
+        match self {
+            NotFound => http::StatusCode::NOT_FOUND,
+            Builtin => http::StatusCode::FORBIDDEN,
+            Invalid => http::StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Person,
+    Player,
+    Journalist,
+    Topic,
+}
+
+impl WatchKind {
+    pub const NAMES: [&'static str; 4] = ["person", "player", "journalist", "topic"];
+
+    pub fn parse(name: &str) -> Result<WatchKind> {
+        use WatchKind::*;
+
+        match name {
+            "person" => Ok(Person),
+            "player" => Ok(Player),
+            "journalist" => Ok(Journalist),
+            "topic" => Ok(Topic),
+            _ => Err(ServiceError::new(
+                WatchError::Invalid,
+                anyhow::anyhow!("unknown kind `{}`", name),
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        use WatchKind::*;
+
+        match self {
+            Person => "person",
+            Player => "player",
+            Journalist => "journalist",
+            Topic => "topic",
+        }
+    }
+}
+
+/// ウォッチリストの 1 件。`query` は `search/recent` のクエリをそのまま書く
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchEntry {
+    pub name: String,
+    pub kind: WatchKind,
+    pub query: String,
+    /// `#rrggbb`。無ければテーマの色
+    pub color: Option<String>,
+    /// 組み込みのエントリは消せない (同じ名前で追加すれば上書きできる)
+    pub builtin: bool,
+}
+
+impl WatchEntry {
+    pub fn new(name: &str, kind: WatchKind, query: &str, color: Option<&str>) -> Result<Self> {
+        let invalid =
+            |message: String| ServiceError::new(WatchError::Invalid, anyhow::anyhow!(message));
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(invalid(format!("invalid name `{}`", name)));
+        }
+        if query.trim().is_empty() {
+            return Err(ServiceError::only(SearchQueryError::Empty));
+        }
+        if let Some(color) = color {
+            parse_hex_color(color).ok_or_else(|| invalid(format!("invalid color `{}`", color)))?;
+        }
+        let entry = WatchEntry {
+            name: name.to_string(),
+            kind,
+            query: query.trim().to_string(),
+            color: color.map(str::to_lowercase),
+            builtin: false,
+        };
+        entry.search_query().build()?;
+        Ok(entry)
+    }
+
+    /// 最初から入っているエントリ
+    pub fn builtins() -> Vec<WatchEntry> {
+        vec![WatchEntry {
+            name: "keisuke".to_string(),
+            kind: WatchKind::Person,
+            query: "本田圭佑".to_string(),
+            color: None,
+            builtin: true,
+        }]
+    }
+
+    pub fn search_query(&self) -> SearchQuery {
+        SearchQuery::new().raw(&self.query)
+    }
+
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        self.color.as_deref().and_then(parse_hex_color)
+    }
+}
+
+/// `#1e50a2` → `(0x1e, 0x50, 0xa2)`
+pub fn parse_hex_color(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_validate_entries() {
+        let entry =
+            WatchEntry::new("mitoma", WatchKind::Player, " 三笘 ", Some("#1E50A2")).unwrap();
+        assert_eq!(entry.query, "三笘");
+        assert_eq!(entry.rgb(), Some((0x1e, 0x50, 0xa2)));
+
+        for (name, query, color) in [
+            ("two words", "q", None),
+            ("mitoma", "", None),
+            ("mitoma", "q", Some("blue")),
+            ("mitoma", "q", Some("#12345")),
+        ] {
+            assert!(WatchEntry::new(name, WatchKind::Topic, query, color).is_err());
+        }
+    }
+}
@@ -1,2 +1,32 @@
 mod tweet_service;
 pub use tweet_service::*;
+
+mod media_service;
+pub use media_service::*;
+
+mod metric_service;
+pub use metric_service::*;
+
+mod action_service;
+pub use action_service::*;
+
+mod auth_service;
+pub use auth_service::*;
+
+mod fixture_service;
+pub use fixture_service::*;
+
+mod stream_service;
+pub use stream_service::*;
+
+mod watch_service;
+pub use watch_service::*;
+
+mod event_detector;
+pub use event_detector::*;
+
+mod event_service;
+pub use event_service::*;
+
+mod poller;
+pub use poller::*;
@@ -0,0 +1,75 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::domain::service::TweetService;
+use crate::error::*;
+use chrono::Utc;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct ActionService {
+    tweet_repo: Arc<dyn ITweetRepository + Send + Sync>,
+    action_repo: Arc<dyn IActionRepository + Send + Sync>,
+    tweet: TweetService,
+}
+
+impl ActionService {
+    pub fn new(
+        tweet_repo: Arc<dyn ITweetRepository + Send + Sync>,
+        action_repo: Arc<dyn IActionRepository + Send + Sync>,
+        tweet: TweetService,
+    ) -> Self {
+        Self {
+            tweet_repo,
+            action_repo,
+            tweet,
+        }
+    }
+
+    /// API を呼び、成否にかかわらず `actions` に記録する。消せたツイートは手元からも消す
+    pub async fn perform(&self, kind: ActionKind, id: &TweetID) -> Result<Action> {
+        let result = match kind {
+            ActionKind::Like => self.tweet_repo.favorite_tweet(id).await,
+            ActionKind::Unlike => self.tweet_repo.unfavorite_tweet(id).await,
+            ActionKind::Retweet => self.tweet_repo.retweet(id).await,
+            ActionKind::Unretweet => self.tweet_repo.unretweet(id).await,
+            ActionKind::Delete => self.tweet_repo.delete_tweet(id).await,
+        };
+        let action = Action::new(kind, &id.0, Utc::now(), &result);
+        self.action_repo.save(action.clone()).await?;
+        result?;
+        // `deleted: true` を確かめてあるので、`history` や `show` に残らないようにする
+        if kind == ActionKind::Delete {
+            self.tweet_repo.delete(id).await?;
+        }
+        Ok(action)
+    }
+
+    /// 記録から今いいねしているツイート。保存していないものは ID だけ返す
+    pub async fn liked_local(&self, limit: usize) -> Result<(Vec<Tweet>, Vec<String>)> {
+        let actions = self
+            .action_repo
+            .list(&[ActionKind::Like, ActionKind::Unlike])
+            .await?;
+        let ids = liked_tweet_ids(&actions)
+            .into_iter()
+            .take(limit)
+            .collect::<Vec<_>>();
+        let mut found = self.tweet.find_by_ids(&ids).await?;
+        let mut tweets = Vec::new();
+        let mut missing = Vec::new();
+        for id in ids {
+            match found.iter().position(|tweet| tweet.id == id) {
+                Some(index) => tweets.push(found.swap_remove(index)),
+                None => missing.push(id),
+            }
+        }
+        Ok((tweets, missing))
+    }
+
+    /// `/2/users/:id/liked_tweets` から取って保存する
+    pub async fn liked_remote(&self, limit: usize) -> Result<Vec<Tweet>> {
+        let tweets = self.tweet_repo.liked_tweets(limit).await?;
+        self.tweet.save_tweets(tweets.clone()).await?;
+        Ok(tweets.into_iter().take(limit).collect())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct AuthService {
+    auth_repo: Arc<dyn IAuthRepository + Send + Sync>,
+}
+
+impl AuthService {
+    pub fn new(auth_repo: Arc<dyn IAuthRepository + Send + Sync>) -> Self {
+        Self { auth_repo }
+    }
+
+    /// PKCE と `state` を作り、ブラウザで開く URL を組み立てる
+    pub fn start_login(&self, redirect_uri: &str) -> Result<LoginRequest> {
+        let state = uuid::Uuid::new_v4().simple().to_string();
+        let pkce = Pkce::generate();
+        let url = self.auth_repo.authorize_url(redirect_uri, &state, &pkce)?;
+        Ok(LoginRequest {
+            url,
+            redirect_uri: redirect_uri.to_string(),
+            state,
+            pkce,
+        })
+    }
+
+    /// 戻ってきたリダイレクトを確かめてトークンに替え、保存する
+    pub async fn finish_login(
+        &self,
+        request: &LoginRequest,
+        callback: AuthCallback,
+    ) -> Result<OAuthToken> {
+        let code = callback.code_for(&request.state)?;
+        let token = self
+            .auth_repo
+            .exchange_code(&code, &request.redirect_uri, &request.pkce)
+            .await?;
+        self.auth_repo.save_token(&token).await?;
+        Ok(token)
+    }
+
+    pub async fn current(&self) -> Result<Option<OAuthToken>> {
+        self.auth_repo.load_token().await
+    }
+
+    pub async fn logout(&self) -> Result<()> {
+        self.auth_repo.delete_token().await
+    }
+}
@@ -0,0 +1,221 @@
+use crate::domain::model::*;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+/// ポーリングごとの件数から、ツイートの急増を見つける
+#[derive(Clone, Debug)]
+pub struct DetectorConfig {
+    /// 平均と分散を取る期間
+    pub window: Duration,
+    /// 平均から標準偏差いくつ分増えたらイベントにするか
+    pub threshold: f64,
+    /// 平均を出すのに必要なポーリング回数
+    pub min_samples: usize,
+    /// 1 回のポーリングでこれより少なければ無視する
+    pub min_tweets: usize,
+    /// イベントの後しばらくは同じ盛り上がりとみなす
+    pub cooldown: Duration,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::minutes(10),
+            threshold: 3.0,
+            min_samples: 3,
+            min_tweets: 5,
+            cooldown: Duration::minutes(3),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    at: DateTime<Utc>,
+    tweets_per_minute: f64,
+    hits_per_minute: f64,
+}
+
+pub struct EventDetector {
+    config: DetectorConfig,
+    query: String,
+    samples: VecDeque<Sample>,
+    last_polled_at: Option<DateTime<Utc>>,
+    last_event_at: Option<DateTime<Utc>>,
+}
+
+impl EventDetector {
+    pub fn new(config: DetectorConfig, query: String) -> Self {
+        Self {
+            config,
+            query,
+            samples: VecDeque::new(),
+            last_polled_at: None,
+            last_event_at: None,
+        }
+    }
+
+    /// クエリが変わったら窓を作り直す
+    pub fn reset(&mut self, query: String) {
+        *self = EventDetector::new(self.config.clone(), query);
+    }
+
+    /// 1 回のポーリング結果を足す。件数かキーワードの勢いが閾値を超えたらイベントを返す
+    pub fn observe(&mut self, tweets: &[Tweet], at: DateTime<Utc>) -> Option<MatchEvent> {
+        // 初回は DB の続きや直近のツイートがまとめて来るので勢いに数えない
+        let last = self.last_polled_at.replace(at)?;
+        let minutes = (at - last).num_milliseconds() as f64 / 60_000.0;
+        if minutes <= 0.0 {
+            return None;
+        }
+
+        let hits = tweets
+            .iter()
+            .filter(|tweet| {
+                let text = tweet.text.to_uppercase();
+                EVENT_KEYWORDS.iter().any(|keyword| text.contains(keyword))
+            })
+            .count();
+        let sample = Sample {
+            at,
+            tweets_per_minute: tweets.len() as f64 / minutes,
+            hits_per_minute: hits as f64 / minutes,
+        };
+
+        while let Some(oldest) = self.samples.front() {
+            if at - oldest.at > self.config.window {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+        let event = self.detect(tweets, hits, sample);
+        self.samples.push_back(sample);
+        event
+    }
+
+    fn detect(&mut self, tweets: &[Tweet], hits: usize, sample: Sample) -> Option<MatchEvent> {
+        if self.samples.len() < self.config.min_samples || tweets.len() < self.config.min_tweets {
+            return None;
+        }
+        if let Some(last) = self.last_event_at {
+            if sample.at - last < self.config.cooldown {
+                return None;
+            }
+        }
+
+        let (baseline, rate_score) = z_score(
+            self.samples.iter().map(|it| it.tweets_per_minute),
+            sample.tweets_per_minute,
+        );
+        let (_, hit_score) = z_score(
+            self.samples.iter().map(|it| it.hits_per_minute),
+            sample.hits_per_minute,
+        );
+        let score = if hits > 0 {
+            rate_score.max(hit_score)
+        } else {
+            rate_score
+        };
+        if score < self.config.threshold {
+            return None;
+        }
+
+        self.last_event_at = Some(sample.at);
+        let keyword_tweets = || {
+            tweets.iter().filter(|tweet| {
+                let text = tweet.text.to_uppercase();
+                EVENT_KEYWORDS.iter().any(|keyword| text.contains(keyword))
+            })
+        };
+        Some(MatchEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: MatchEventKind::from_texts(keyword_tweets().map(|tweet| tweet.text.as_str())),
+            detected_at: sample.at,
+            query: self.query.clone(),
+            tweets_per_minute: sample.tweets_per_minute,
+            baseline,
+            score,
+            keyword_hits: hits,
+            sample_tweet_id: keyword_tweets()
+                .chain(tweets)
+                .next()
+                .map(|tweet| tweet.id.clone()),
+        })
+    }
+}
+
+/// `(平均, z)`。分散が小さいときに少しの増加で反応しないよう、標準偏差は √平均 (ポアソン) を下限にする
+fn z_score(values: impl Iterator<Item = f64> + Clone, value: f64) -> (f64, f64) {
+    let n = values.clone().count() as f64;
+    let mean = values.clone().sum::<f64>() / n;
+    let variance = values.map(|it| (it - mean).powi(2)).sum::<f64>() / n;
+    let deviation = variance.sqrt().max(mean.sqrt()).max(1.0);
+    (mean, (value - mean) / deviation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tweets(texts: &[&str]) -> Vec<Tweet> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                Tweet::new(
+                    i.to_string(),
+                    text.to_string(),
+                    "1".to_string(),
+                    "2022-12-05T15:00:00.000Z".to_string(),
+                    None,
+                    None,
+                    None,
+                    Some("ja".to_string()),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    #[test]
+    fn it_should_detect_spike_with_keywords() {
+        let mut detector = EventDetector::new(DetectorConfig::default(), "#JPNCRO".to_string());
+        let quiet = ["がんばれ"; 6];
+        for minute in 0..6 {
+            assert_eq!(detector.observe(&tweets(&quiet), at(minute)), None);
+        }
+
+        let mut burst = vec!["前田ゴール!!!"; 20];
+        burst.extend(["同点ゴール"; 15]);
+        let event = detector.observe(&tweets(&burst), at(6)).unwrap();
+        assert_eq!(event.kind, MatchEventKind::Goal);
+        assert_eq!(event.keyword_hits, 35);
+        assert_eq!(event.baseline, 6.0);
+        assert!(event.score >= 3.0);
+        assert_eq!(event.query, "#JPNCRO");
+
+        // 続く盛り上がりはクールダウン中なので出さない
+        assert_eq!(detector.observe(&tweets(&burst), at(7)), None);
+    }
+
+    #[test]
+    fn it_should_ignore_steady_volume() {
+        let mut detector = EventDetector::new(DetectorConfig::default(), "q".to_string());
+        for minute in 0..20 {
+            let count = if minute % 2 == 0 { 8 } else { 12 };
+            assert_eq!(
+                detector.observe(&tweets(&vec!["PK"; count]), at(minute)),
+                None
+            );
+        }
+    }
+}
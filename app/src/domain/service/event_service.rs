@@ -0,0 +1,23 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct EventService {
+    event_repo: Arc<dyn IEventRepository + Send + Sync>,
+}
+
+impl EventService {
+    pub fn new(event_repo: Arc<dyn IEventRepository + Send + Sync>) -> Self {
+        Self { event_repo }
+    }
+
+    pub async fn save(&self, event: MatchEvent) -> Result<()> {
+        self.event_repo.save(event).await
+    }
+
+    pub async fn list(&self, limit: i64) -> Result<Vec<MatchEvent>> {
+        self.event_repo.list(limit).await
+    }
+}
@@ -0,0 +1,113 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct FixtureService {
+    fixture_repo: Arc<dyn IFixtureRepository + Send + Sync>,
+}
+
+impl FixtureService {
+    pub fn new(fixture_repo: Arc<dyn IFixtureRepository + Send + Sync>) -> Self {
+        Self { fixture_repo }
+    }
+
+    /// fixture ファイルから読んだ試合を DB に反映する
+    pub async fn sync(&self, matches: Vec<Match>) -> Result<()> {
+        self.fixture_repo.save_matches(matches).await
+    }
+
+    /// これからの試合 (試合中を含む) はキックオフ順、終わった試合は新しい順
+    pub async fn schedule(&self, now: DateTime<Utc>, past: bool) -> Result<Vec<Match>> {
+        let matches = self.fixture_repo.list().await?;
+        let (mut finished, upcoming): (Vec<_>, Vec<_>) = matches
+            .into_iter()
+            .partition(|game| game.status(now) == MatchStatus::Finished);
+        if past {
+            finished.reverse();
+            Ok(finished)
+        } else {
+            Ok(upcoming)
+        }
+    }
+
+    /// 試合の `id`、またはチームコード (試合中か次の試合、無ければ直近の試合)
+    pub async fn find(&self, key: &str, now: DateTime<Utc>) -> Result<Match> {
+        let matches = self.fixture_repo.list().await?;
+        if let Some(game) = matches.iter().find(|game| game.id == key) {
+            return Ok(game.clone());
+        }
+        let (finished, upcoming): (Vec<_>, Vec<_>) = matches
+            .into_iter()
+            .filter(|game| game.involves(key))
+            .partition(|game| game.status(now) == MatchStatus::Finished);
+        upcoming
+            .into_iter()
+            .next()
+            .or_else(|| finished.into_iter().last())
+            .ok_or_else(|| {
+                ServiceError::new(
+                    FixtureError::NotFound,
+                    anyhow::anyhow!("no match found for `{}`", key),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::testing::memory_db;
+    use crate::repository::FixtureRepository;
+    use chrono::{Duration, TimeZone};
+
+    async fn service(matches: Vec<Match>) -> FixtureService {
+        let repo = FixtureRepository::new(memory_db());
+        repo.save_matches(matches).await.unwrap();
+        FixtureService::new(Arc::new(repo))
+    }
+
+    fn game(id: &str, home: &str, away: &str, kickoff: DateTime<Utc>) -> Match {
+        let team = |code: &str| Team {
+            code: code.to_string(),
+            name: code.to_string(),
+            hashtags: vec![],
+        };
+        Match {
+            id: id.to_string(),
+            stage: Stage::Group("E".to_string()),
+            home: team(home),
+            away: team(away),
+            kickoff,
+            venue: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_find_next_match_of_team() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2022, 11, d, 13, 0, 0).unwrap();
+        let service = service(vec![
+            game("1", "GER", "JPN", day(23)),
+            game("2", "JPN", "CRC", day(27)),
+            game("3", "ESP", "GER", day(27) + Duration::hours(6)),
+        ])
+        .await;
+        let now = day(27) + Duration::minutes(30);
+
+        assert_eq!(service.find("JPN", now).await.unwrap().id, "2");
+        assert_eq!(service.find("GER", now).await.unwrap().id, "3");
+        assert_eq!(
+            service.find("GER", day(28)).await.unwrap().id,
+            "3",
+            "直近の試合"
+        );
+        assert_eq!(service.find("1", now).await.unwrap().id, "1");
+        assert!(service.find("BRA", now).await.is_err());
+
+        let ids = |matches: Vec<Match>| matches.into_iter().map(|it| it.id).collect::<Vec<_>>();
+        assert_eq!(ids(service.schedule(now, false).await.unwrap()), ["2", "3"]);
+        assert_eq!(ids(service.schedule(now, true).await.unwrap()), ["1"]);
+    }
+}
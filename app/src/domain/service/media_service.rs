@@ -0,0 +1,23 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct MediaService {
+    media_repo: Arc<dyn IMediaRepository + Send + Sync>,
+}
+
+impl MediaService {
+    pub fn new(media_repo: Arc<dyn IMediaRepository + Send + Sync>) -> Self {
+        Self { media_repo }
+    }
+
+    /// プレビュー用の画像 (動画と GIF はサムネイル)。画像が無ければ `None`
+    pub async fn preview_image(&self, media: &Media) -> Result<Option<Vec<u8>>> {
+        match media.image_url() {
+            Some(url) => self.media_repo.download(url).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
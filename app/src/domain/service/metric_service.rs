@@ -0,0 +1,103 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::domain::service::TweetService;
+use crate::error::*;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// `real` の裏で数字を取り直す間隔
+pub const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+/// 取り直すのは保存してからこの時間内のツイート
+pub const REFRESH_WINDOW_HOURS: i64 = 1;
+/// 1 回に取り直す最大件数
+const MAX_REFRESH: usize = 300;
+
+#[derive(Clone)]
+pub struct MetricService {
+    tweet: TweetService,
+    metric_repo: Arc<dyn IMetricRepository + Send + Sync>,
+}
+
+impl MetricService {
+    pub fn new(tweet: TweetService, metric_repo: Arc<dyn IMetricRepository + Send + Sync>) -> Self {
+        Self { tweet, metric_repo }
+    }
+
+    /// `window` 内に投稿された保存済みのツイートを取り直してスナップショットを残す
+    ///
+    /// 取り直した件数と、消されたツイートなどの部分エラーを返す。
+    pub async fn refresh(
+        &self,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<(usize, Vec<ServiceError>)> {
+        let recent = self
+            .tweet
+            .search_local(&LocalQuery {
+                since: Some(now - window),
+                limit: MAX_REFRESH,
+                ..Default::default()
+            })
+            .await?;
+        let ids = recent.into_iter().map(|tweet| tweet.id).collect::<Vec<_>>();
+        let (tweets, errors) = self.tweet.lookup(&ids).await?;
+        let count = tweets.len();
+        self.tweet.save_tweets(tweets).await?;
+        Ok((count, errors))
+    }
+
+    /// `window` 内に見たスナップショットで並べた上位 `limit` 件
+    pub async fn top(
+        &self,
+        window: Duration,
+        ranking: Ranking,
+        limit: usize,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(RankedTweet, Tweet)>> {
+        let snapshots = self.metric_repo.find_since(&(now - window)).await?;
+        let mut ids = snapshots
+            .iter()
+            .map(|it| it.tweet_id.clone())
+            .collect::<Vec<_>>();
+        ids.dedup();
+        let mut tweets = self
+            .tweet
+            .find_by_ids(&ids)
+            .await?
+            .into_iter()
+            .map(|tweet| (tweet.id.clone(), tweet))
+            .collect::<HashMap<_, _>>();
+        let created_at = |id: &str| {
+            let tweet = tweets.get(id)?;
+            DateTime::parse_from_rfc3339(&tweet.created_at)
+                .ok()
+                .map(|it| it.with_timezone(&Utc))
+        };
+        let ranked = rank(&snapshots, created_at, ranking, now);
+        Ok(ranked
+            .into_iter()
+            // 保存済みのツイートが消えていれば飛ばす
+            .filter_map(|it| {
+                let tweet = tweets.remove(&it.tweet_id)?;
+                Some((it, tweet))
+            })
+            .take(limit)
+            .collect())
+    }
+}
+
+/// `REFRESH_INTERVAL` ごとに `refresh` する。取り直しに失敗しても次を待つ
+pub fn spawn_refresher(metric: MetricService) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        // 1 回目はすぐに来るので飛ばす
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let _ = metric
+                .refresh(Duration::hours(REFRESH_WINDOW_HOURS), Utc::now())
+                .await;
+        }
+    })
+}
@@ -0,0 +1,649 @@
+use crate::domain::model::*;
+use crate::domain::service::{EventDetector, EventService, StreamService, TweetService};
+use crate::error::ServiceError;
+use chrono::{DateTime, Local, Utc};
+use futures::StreamExt;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 受信側が詰まったときにためておくイベント数
+const EVENT_BUFFER: usize = 64;
+/// 失敗したら 1 秒後から倍々で待ってやり直す
+const RETRY_BASE: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// つなぎ直したときに同じツイートを 2 度流さないよう覚えておく件数
+const SEEN_TWEETS: usize = 1000;
+
+#[derive(Debug)]
+pub enum PollEvent {
+    /// 新着ツイート (保存済み)。0 件でもポーリングのたびに送る
+    Tweets {
+        tweets: Vec<Tweet>,
+        polled_at: DateTime<Local>,
+        /// `search/recent` の残り回数
+        rate_limit: Option<RateLimit>,
+    },
+    /// 失敗したポーリング。`retry_in` 後にやり直す
+    Error {
+        message: String,
+        at: DateTime<Local>,
+        retry_in: Duration,
+    },
+    /// `spawn_detector` が見つけたイベント (保存済み)
+    Match(MatchEvent),
+}
+
+#[derive(Debug)]
+pub enum PollCommand {
+    Pause,
+    Resume,
+    SetQuery(SearchQuery),
+}
+
+/// バックグラウンドで `search/recent` をポーリングするタスクへのハンドル
+///
+/// `events` が drop されるとタスクも終わる。
+pub struct PollerHandle {
+    pub events: mpsc::Receiver<PollEvent>,
+    commands: mpsc::UnboundedSender<PollCommand>,
+    task: JoinHandle<()>,
+}
+
+impl PollerHandle {
+    pub fn send(&self, command: PollCommand) {
+        // タスクが終わっている場合は何もしない
+        let _ = self.commands.send(command);
+    }
+
+    /// 受信をやめてタスクの終わりを待つ。保存中のツイートは書き終わってから止まる
+    pub async fn shutdown(self) {
+        drop(self.events);
+        drop(self.commands);
+        // panic していても終わったことに変わりはない
+        let _ = self.task.await;
+    }
+}
+
+struct Poller {
+    service: TweetService,
+    query: SearchQuery,
+    since_id: Option<TweetID>,
+    /// false の間は DB の最新ツイートを since_id にする
+    resumed_from_db: bool,
+    paused: bool,
+}
+
+impl Poller {
+    /// 初回は DB の最新ツイート以降を取得する。部分エラーはツイートとは別に返す
+    async fn poll(&mut self) -> crate::error::Result<(Vec<Tweet>, Vec<ServiceError>)> {
+        if !self.resumed_from_db {
+            let latest = self.service.get_latest_tweets(1).await?;
+            self.since_id = latest.first().map(|tweet| TweetID(tweet.id.clone()));
+            self.resumed_from_db = true;
+        }
+
+        // 1 ページに収まらない分も取りこぼさないよう since_id までたどる
+        let (tweets, errors) = self
+            .service
+            .get_tweets_since(&self.query, self.since_id.as_ref())
+            .await?;
+        self.service.save_tweets(tweets.clone()).await?;
+
+        // search/recent は新しい順に返す
+        if let Some(newest) = tweets.first() {
+            self.since_id = Some(TweetID(newest.id.clone()));
+        }
+        Ok((tweets, errors))
+    }
+
+    fn handle(&mut self, command: PollCommand) {
+        match command {
+            PollCommand::Pause => self.paused = true,
+            PollCommand::Resume => self.paused = false,
+            // 新しいクエリでは直近のツイートから取り直す
+            PollCommand::SetQuery(query) => {
+                self.query = query;
+                self.since_id = None;
+            }
+        }
+    }
+}
+
+/// `PollEvent::Error` の表示用。やり直す場合は待ち時間を付ける
+pub fn describe_error(message: &str, retry_in: Duration) -> String {
+    if retry_in.is_zero() {
+        message.to_string()
+    } else {
+        format!("{} (retry in {}s)", message, retry_in.as_secs())
+    }
+}
+
+/// 待ってもすぐには直らない失敗か
+fn is_persistent(err: &ServiceError) -> bool {
+    err.is_error_of(TwitterApiError::Unauthorized)
+        || err.is_error_of(TwitterApiError::Forbidden)
+        || err.is_error_of(TwitterApiError::InvalidQuery)
+}
+
+/// 連続 `failures` 回失敗した後に待つ時間
+pub fn retry_delay(failures: u32) -> Duration {
+    RETRY_BASE
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// `interval` はレート制限に余裕があるときの間隔。残りが少なければ延ばし、429 ならリセットまで待つ
+pub fn spawn_poller(
+    service: TweetService,
+    query: SearchQuery,
+    interval: Duration,
+    rate_limits: RateLimits,
+) -> PollerHandle {
+    let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+    let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+
+    let task = tokio::spawn(async move {
+        let _registration = rate_limits.register(SEARCH_RECENT_ENDPOINT);
+        let mut poller = Poller {
+            service,
+            query,
+            since_id: None,
+            resumed_from_db: false,
+            paused: false,
+        };
+        let mut failures = 0;
+
+        loop {
+            let mut delay = interval;
+            if !poller.paused {
+                let result = poller.poll().await;
+                let pace = rate_limits.pace(SEARCH_RECENT_ENDPOINT, interval, Utc::now());
+                let event = match result {
+                    Ok((tweets, errors)) => {
+                        failures = 0;
+                        // 部分エラーはやり直さずに知らせるだけ
+                        for err in errors {
+                            let error = PollEvent::Error {
+                                message: err.to_string(),
+                                at: Local::now(),
+                                retry_in: Duration::ZERO,
+                            };
+                            if event_tx.send(error).await.is_err() {
+                                return;
+                            }
+                        }
+                        delay = pace;
+                        PollEvent::Tweets {
+                            tweets,
+                            polled_at: Local::now(),
+                            rate_limit: rate_limits.get(SEARCH_RECENT_ENDPOINT),
+                        }
+                    }
+                    Err(err) => {
+                        failures += 1;
+                        // 429 ならリセットまで待つ。認証やクエリの誤りはすぐには直らないので長めに待つ
+                        delay = if is_persistent(&err) {
+                            MAX_RETRY_DELAY
+                        } else {
+                            retry_delay(failures)
+                        }
+                        .max(pace);
+                        PollEvent::Error {
+                            message: err.to_string(),
+                            at: Local::now(),
+                            retry_in: delay,
+                        }
+                    }
+                };
+                if event_tx.send(event).await.is_err() {
+                    return;
+                }
+            }
+
+            // 待っている間に来たコマンドはすぐ反映する。クエリが変わったらすぐ取り直す
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    _ = event_tx.closed() => return,
+                    command = command_rx.recv() => match command {
+                        Some(command) => {
+                            let refetch = matches!(
+                                command,
+                                PollCommand::SetQuery(_) | PollCommand::Resume
+                            );
+                            poller.handle(command);
+                            if refetch {
+                                failures = 0;
+                                break;
+                            }
+                        }
+                        None => return,
+                    },
+                }
+            }
+        }
+    });
+
+    PollerHandle {
+        events: event_rx,
+        commands: command_tx,
+        task,
+    }
+}
+
+/// `search/stream` につないで届いたツイートを保存し、`interval` ごとにまとめて流す。切れたら待ってつなぎ直す
+///
+/// ポーリングと同じ間隔で区切るので、`EventDetector` はそのまま勢いを測れる。
+/// 対象はストリームのルールで決まるので `PollCommand::SetQuery` は使えない。
+pub fn spawn_streamer(
+    service: StreamService,
+    interval: Duration,
+    rate_limits: RateLimits,
+) -> PollerHandle {
+    let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+    let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+
+    let task = tokio::spawn(async move {
+        let _registration = rate_limits.register(SEARCH_STREAM_ENDPOINT);
+        let mut paused = false;
+        let mut failures = 0;
+        let mut seen = VecDeque::new();
+        let mut seen_ids = HashSet::new();
+        // 届いた順。流すときに search/recent と同じ新しい順にする
+        let mut batch: Vec<Tweet> = Vec::new();
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            let err = match service.connect().await {
+                Ok(mut stream) => {
+                    failures = 0;
+                    loop {
+                        let item = tokio::select! {
+                            item = stream.next() => item,
+                            _ = tick.tick() => {
+                                if paused {
+                                    continue;
+                                }
+                                let mut tweets = std::mem::take(&mut batch);
+                                tweets.reverse();
+                                let event = PollEvent::Tweets {
+                                    tweets,
+                                    polled_at: Local::now(),
+                                    rate_limit: None,
+                                };
+                                if event_tx.send(event).await.is_err() {
+                                    return;
+                                }
+                                continue;
+                            }
+                            _ = event_tx.closed() => return,
+                            command = command_rx.recv() => {
+                                match command {
+                                    Some(PollCommand::Pause) => paused = true,
+                                    Some(PollCommand::Resume) => paused = false,
+                                    Some(PollCommand::SetQuery(_)) => {
+                                        let error = PollEvent::Error {
+                                            message: "--stream ではクエリを変えられません (`stream rules` を使ってください)".to_string(),
+                                            at: Local::now(),
+                                            retry_in: Duration::ZERO,
+                                        };
+                                        if event_tx.send(error).await.is_err() {
+                                            return;
+                                        }
+                                    }
+                                    None => return,
+                                }
+                                continue;
+                            }
+                        };
+                        let tweet = match item {
+                            Some(Ok(StreamMessage::Tweet(tweet))) => *tweet,
+                            // 部分エラーや読めなかった行は知らせるだけで、接続はそのまま読み続ける
+                            // 切れたり止まったりしたときだけつなぎ直す
+                            Some(Ok(StreamMessage::Problem(err))) => {
+                                let error = PollEvent::Error {
+                                    message: err.to_string(),
+                                    at: Local::now(),
+                                    retry_in: Duration::ZERO,
+                                };
+                                if event_tx.send(error).await.is_err() {
+                                    return;
+                                }
+                                continue;
+                            }
+                            Some(Err(err)) => break err,
+                            None => break ServiceError::only(TwitterApiError::ServiceUnavailable),
+                        };
+                        if !seen_ids.insert(tweet.id.clone()) {
+                            continue;
+                        }
+                        seen.push_back(tweet.id.clone());
+                        if seen.len() > SEEN_TWEETS {
+                            if let Some(id) = seen.pop_front() {
+                                seen_ids.remove(&id);
+                            }
+                        }
+                        match service.save(tweet.clone()).await {
+                            Ok(()) if paused => {}
+                            Ok(()) => batch.push(tweet),
+                            Err(err) => {
+                                let error = PollEvent::Error {
+                                    message: err.to_string(),
+                                    at: Local::now(),
+                                    retry_in: Duration::ZERO,
+                                };
+                                if event_tx.send(error).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(err) => err,
+            };
+
+            // 切れるまでに届いた分はつなぎ直すのを待たずに流す
+            if !batch.is_empty() {
+                let mut tweets = std::mem::take(&mut batch);
+                tweets.reverse();
+                let event = PollEvent::Tweets {
+                    tweets,
+                    polled_at: Local::now(),
+                    rate_limit: None,
+                };
+                if event_tx.send(event).await.is_err() {
+                    return;
+                }
+            }
+            failures += 1;
+            // 429 は接続の回数の制限なのでリセットまで待つ
+            let delay = if err.is_error_of(TwitterApiError::RateLimited { reset: None }) {
+                rate_limits.pace(SEARCH_STREAM_ENDPOINT, MAX_RETRY_DELAY, Utc::now())
+            } else if is_persistent(&err) {
+                MAX_RETRY_DELAY
+            } else {
+                retry_delay(failures)
+            };
+            let error = PollEvent::Error {
+                message: err.to_string(),
+                at: Local::now(),
+                retry_in: delay,
+            };
+            if event_tx.send(error).await.is_err() {
+                return;
+            }
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    _ = event_tx.closed() => return,
+                    command = command_rx.recv() => match command {
+                        Some(PollCommand::Pause) => paused = true,
+                        Some(PollCommand::Resume) => paused = false,
+                        Some(PollCommand::SetQuery(_)) => {}
+                        None => return,
+                    },
+                }
+            }
+        }
+    });
+
+    PollerHandle {
+        events: event_rx,
+        commands: command_tx,
+        task,
+    }
+}
+
+/// `poller` のツイートの勢いからイベントを探し、保存して `PollEvent::Match` を挟む
+///
+/// `notify` は保存したイベントごとに呼ばれる。
+pub fn spawn_detector(
+    mut poller: PollerHandle,
+    mut detector: EventDetector,
+    events: EventService,
+    notify: impl Fn(&MatchEvent) -> crate::error::Result<()> + Send + Sync + 'static,
+) -> PollerHandle {
+    let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+    let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+
+    let task = tokio::spawn(async move {
+        let relay = async {
+            loop {
+                tokio::select! {
+                    event = poller.events.recv() => {
+                        let Some(event) = event else { return };
+                        let found = match &event {
+                            PollEvent::Tweets {
+                                tweets, polled_at, ..
+                            } => {
+                                detector.observe(tweets, polled_at.with_timezone(&Utc))
+                            }
+                            _ => None,
+                        };
+                        if event_tx.send(event).await.is_err() {
+                            return;
+                        }
+                        let Some(found) = found else { continue };
+                        // 保存や通知に失敗しても表示はする
+                        let saved = events.save(found.clone()).await;
+                        for err in [saved, notify(&found)].into_iter().filter_map(Result::err) {
+                            let error = PollEvent::Error {
+                                message: err.to_string(),
+                                at: Local::now(),
+                                retry_in: Duration::ZERO,
+                            };
+                            if event_tx.send(error).await.is_err() {
+                                return;
+                            }
+                        }
+                        if event_tx.send(PollEvent::Match(found)).await.is_err() {
+                            return;
+                        }
+                    }
+                    _ = event_tx.closed() => return,
+                    command = command_rx.recv() => match command {
+                        Some(command) => {
+                            if let PollCommand::SetQuery(query) = &command {
+                                detector.reset(query.build().unwrap_or_default());
+                            }
+                            poller.send(command);
+                        }
+                        None => return,
+                    },
+                }
+            }
+        };
+        relay.await;
+        poller.shutdown().await;
+    });
+
+    PollerHandle {
+        events: event_rx,
+        commands: command_tx,
+        task,
+    }
+}
+
+/// `shutdown` が終わるまで `poller` のイベントを流し、1 件ごとに `observe` を呼ぶ
+///
+/// `shutdown` が先に終わったら、保存中のツイートを書き終えてからイベントを閉じる。
+pub fn spawn_session(
+    mut poller: PollerHandle,
+    mut observe: impl FnMut(&PollEvent) + Send + 'static,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> PollerHandle {
+    let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+    let (command_tx, mut command_rx) = mpsc::unbounded_channel();
+
+    let task = tokio::spawn(async move {
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                event = poller.events.recv() => {
+                    let Some(event) = event else { break };
+                    observe(&event);
+                    if event_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                _ = event_tx.closed() => break,
+                _ = &mut shutdown => break,
+                command = command_rx.recv() => match command {
+                    Some(command) => poller.send(command),
+                    None => break,
+                },
+            }
+        }
+        poller.shutdown().await;
+    });
+
+    PollerHandle {
+        events: event_rx,
+        commands: command_tx,
+        task,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::interface::ITweetRepository;
+    use crate::domain::service::DetectorConfig;
+    use crate::infra::DBConnector;
+    use crate::infra::HttpClient;
+    use crate::repository::testing::{
+        auth_repository, memory_db, FakeStreamRepository, FakeTweetRepository,
+    };
+    use crate::repository::{EventRepository, MediaRepository, MetricRepository, UserRepository};
+    use std::sync::Arc;
+
+    fn tweet(id: u64) -> Tweet {
+        Tweet::new(
+            id.to_string(),
+            format!("ゴール {}", id),
+            "1".to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn tweet_service(
+        db: DBConnector,
+        repo: Arc<dyn ITweetRepository + Send + Sync>,
+    ) -> TweetService {
+        let http_client = Arc::new(HttpClient::new(RateLimits::default()));
+        TweetService::new(
+            repo,
+            Arc::new(UserRepository::new(
+                db.clone(),
+                http_client.clone(),
+                auth_repository(),
+            )),
+            Arc::new(MediaRepository::new(db.clone(), http_client)),
+            Arc::new(MetricRepository::new(db)),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_collect_burst_larger_than_one_page() {
+        // 前回の 1000 より後に 250 件。新しい順に 100 件ずつ返り、最後のページには既読の分も混じる
+        let pages = vec![
+            (1151..=1250).rev().map(tweet).collect(),
+            (1051..=1150).rev().map(tweet).collect(),
+            (991..=1050).rev().map(tweet).collect(),
+        ];
+        let db = memory_db();
+        let repo = Arc::new(FakeTweetRepository::new(db.clone(), pages));
+        let service = tweet_service(db, repo.clone());
+        let mut poller = Poller {
+            service,
+            query: SearchQuery::new().keyword("ゴール"),
+            since_id: Some(TweetID("1000".to_string())),
+            resumed_from_db: true,
+            paused: false,
+        };
+
+        let (tweets, errors) = poller.poll().await.unwrap();
+
+        assert_eq!(tweets.len(), 250);
+        assert!(errors.is_empty());
+        assert_eq!(poller.since_id, Some(TweetID("1250".to_string())));
+        assert_eq!(
+            *repo.requests.lock().unwrap(),
+            vec![
+                (100, None),
+                (100, Some("1".to_string())),
+                (100, Some("2".to_string()))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_detect_goal_in_stream_burst() {
+        let db = memory_db();
+        let (stream_repo, messages) = FakeStreamRepository::new();
+        let tweets = Arc::new(FakeTweetRepository::new(db.clone(), Vec::new()));
+        let service = StreamService::new(Arc::new(stream_repo), tweet_service(db.clone(), tweets));
+        let streamer = spawn_streamer(service, Duration::from_millis(200), RateLimits::default());
+        let mut detector = spawn_detector(
+            streamer,
+            EventDetector::new(DetectorConfig::default(), "#JPNCRO".to_string()),
+            EventService::new(Arc::new(EventRepository::new(db))),
+            |_| Ok(()),
+        );
+
+        // 何も来ない区切りで平均を作ってから、1 件ずつ届くゴールの盛り上がりを流す
+        let mut quiet = 0;
+        while quiet < 4 {
+            if let Some(PollEvent::Tweets { tweets, .. }) = detector.events.recv().await {
+                assert!(tweets.is_empty());
+                quiet += 1;
+            }
+        }
+        for id in 1..=30 {
+            messages
+                .send(StreamMessage::Tweet(Box::new(tweet(id))))
+                .unwrap();
+        }
+        let found = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match detector.events.recv().await {
+                    Some(PollEvent::Match(event)) => return event,
+                    Some(PollEvent::Error { message, .. }) => panic!("{}", message),
+                    Some(_) => {}
+                    None => panic!("closed before detecting"),
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(found.kind, MatchEventKind::Goal);
+        assert!(found.keyword_hits >= DetectorConfig::default().min_tweets);
+        detector.shutdown().await;
+    }
+
+    #[test]
+    fn it_should_back_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(4), Duration::from_secs(8));
+        assert_eq!(retry_delay(7), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(100), MAX_RETRY_DELAY);
+    }
+}
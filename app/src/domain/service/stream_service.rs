@@ -0,0 +1,40 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::domain::service::TweetService;
+use crate::error::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct StreamService {
+    stream_repo: Arc<dyn IStreamRepository + Send + Sync>,
+    tweet: TweetService,
+}
+
+impl StreamService {
+    pub fn new(stream_repo: Arc<dyn IStreamRepository + Send + Sync>, tweet: TweetService) -> Self {
+        Self { stream_repo, tweet }
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<StreamRule>> {
+        self.stream_repo.list_rules().await
+    }
+
+    pub async fn add_rule(&self, value: &str, tag: Option<&str>) -> Result<Vec<StreamRule>> {
+        self.stream_repo
+            .add_rules(vec![StreamRule::new(value, tag)?])
+            .await
+    }
+
+    pub async fn delete_rules(&self, ids: Vec<String>) -> Result<usize> {
+        self.stream_repo.delete_rules(ids).await
+    }
+
+    pub async fn connect(&self) -> Result<TweetStream> {
+        self.stream_repo.connect().await
+    }
+
+    /// 届いたツイートを著者や添付と一緒に保存する
+    pub async fn save(&self, tweet: Tweet) -> Result<()> {
+        self.tweet.save_tweets(vec![tweet]).await
+    }
+}
@@ -1,12 +1,28 @@
 use crate::domain::interface::*;
 use crate::domain::model::*;
 use crate::error::*;
+use chrono::Utc;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use std::sync::Arc;
 
+/// 1 リクエストで取得する最大件数 (`search/recent` の上限)
+const PAGE_SIZE: usize = 100;
+/// 1 リクエストで取り直す最大件数 (`/2/tweets?ids=` の上限)
+const LOOKUP_SIZE: usize = 100;
+/// 1 リクエストで取り直す最大ユーザー数 (`/2/users?ids=` の上限)
+const USER_LOOKUP_SIZE: usize = 100;
+/// ポーリング 1 回でたどる最大ページ数 (5 秒で 1000 件)
+const MAX_POLL_PAGES: usize = 10;
+/// `thread` で集める最大件数
+const MAX_THREAD: usize = 500;
+
 #[derive(Clone)]
 
 pub struct TweetService {
     tweet_repo: Arc<dyn ITweetRepository + Send + Sync>,
+    user_repo: Arc<dyn IUserRepository + Send + Sync>,
+    media_repo: Arc<dyn IMediaRepository + Send + Sync>,
+    metric_repo: Arc<dyn IMetricRepository + Send + Sync>,
 }
 
 // pub struct FindTweetOutput {
@@ -14,8 +30,18 @@ pub struct TweetService {
 // }
 
 impl TweetService {
-    pub fn new(tweet_repo: Arc<dyn ITweetRepository + Send + Sync>) -> Self {
-        Self { tweet_repo }
+    pub fn new(
+        tweet_repo: Arc<dyn ITweetRepository + Send + Sync>,
+        user_repo: Arc<dyn IUserRepository + Send + Sync>,
+        media_repo: Arc<dyn IMediaRepository + Send + Sync>,
+        metric_repo: Arc<dyn IMetricRepository + Send + Sync>,
+    ) -> Self {
+        Self {
+            tweet_repo,
+            user_repo,
+            media_repo,
+            metric_repo,
+        }
     }
 
     // pub async fn create(
@@ -56,29 +82,177 @@ impl TweetService {
     //     Ok(FindTweetOutput { data: vec![tweet] })
     // }
 
+    #[allow(dead_code)]
     pub async fn search(&self, query: &str) -> Result<Vec<Tweet>> {
         let tweets = self.tweet_repo.search(query).await?;
         Ok(tweets)
     }
 
-    pub async fn get_tweets(&self, query: &str) -> Result<Vec<Tweet>> {
+    /// 著者と添付は保存済みのものから埋める
+    pub async fn search_local(&self, query: &LocalQuery) -> Result<Vec<Tweet>> {
+        let mut tweets = self.tweet_repo.find_local(query).await?;
+        self.resolve_authors(&mut tweets).await?;
+        self.resolve_media(&mut tweets).await?;
+        Ok(tweets)
+    }
+
+    pub async fn resolve_media(&self, tweets: &mut [Tweet]) -> Result<()> {
+        let ids = tweets
+            .iter()
+            .map(|tweet| tweet.id.clone())
+            .collect::<Vec<_>>();
+        let media = self.media_repo.find_by_tweet_ids(&ids).await?;
+        for tweet in tweets {
+            // find_by_tweet_ids はツイートごとに添付の順で返す
+            tweet.media = media
+                .iter()
+                .filter(|(tweet_id, _)| tweet_id == &tweet.id)
+                .map(|(_, media)| media.clone())
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// 保存済みのユーザーを著者に入れる。無いか古いユーザーは取り直すが、取れなければ手元のものを使う
+    pub async fn resolve_authors(&self, tweets: &mut [Tweet]) -> Result<()> {
+        let mut ids = tweets
+            .iter()
+            .filter(|tweet| tweet.author.is_none())
+            .map(|tweet| tweet.author_id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut users = self.user_repo.find_by_ids(&ids).await?;
+        let now = Utc::now();
+        let refresh = ids
+            .into_iter()
+            .filter(|id| {
+                users
+                    .iter()
+                    .find(|user| &user.id == id)
+                    .is_none_or(|user| user.is_stale(now))
+            })
+            .collect::<Vec<_>>();
+        // オフラインでも表示はできるようにする。凍結や削除で取れなかった著者は手元のものか ID で表示する
+        for chunk in refresh.chunks(USER_LOOKUP_SIZE) {
+            let Ok((fetched, _)) = self.user_repo.lookup(chunk).await else {
+                break;
+            };
+            self.user_repo.save_users(fetched.clone()).await?;
+            users.retain(|user| !fetched.iter().any(|it| it.id == user.id));
+            users.extend(fetched);
+        }
+        attach_authors(tweets, &users);
+        Ok(())
+    }
+
+    pub async fn get_tweets(&self, query: &SearchQuery) -> Result<Vec<Tweet>> {
         let tweets = self.tweet_repo.get_tweets(query).await?;
         Ok(tweets)
     }
 
+    /// `next_token` をたどって `limit` 件まで集める
+    pub async fn search_paginated(&self, query: &SearchQuery, limit: usize) -> Result<Vec<Tweet>> {
+        self.search_stream(query.clone(), limit).try_collect().await
+    }
+
+    /// ページを取得しながら 1 件ずつ流す。ページが尽きるか `limit` 件に達したら終わる
+    pub fn search_stream(
+        &self,
+        query: SearchQuery,
+        limit: usize,
+    ) -> impl Stream<Item = Result<Tweet>> + Send + 'static {
+        enum Cursor {
+            First,
+            Next(String),
+            Done,
+        }
+
+        let service = self.clone();
+        stream::unfold((Cursor::First, limit), move |(cursor, remaining)| {
+            let service = service.clone();
+            let query = query.clone();
+            async move {
+                let token = match cursor {
+                    Cursor::Done => return None,
+                    _ if remaining == 0 => return None,
+                    Cursor::First => None,
+                    Cursor::Next(token) => Some(token),
+                };
+
+                // 検索で見たツイートも保存して、`top` で並べられるようにスナップショットを残す
+                let page = match service
+                    .tweet_repo
+                    .get_tweets_page(&query, remaining.min(PAGE_SIZE), None, token.as_deref())
+                    .await
+                {
+                    Ok(page) => service.save_tweets(page.tweets.clone()).await.map(|_| page),
+                    Err(err) => Err(err),
+                };
+                match page {
+                    Ok(page) => {
+                        let mut tweets = page.tweets;
+                        tweets.truncate(remaining);
+                        let remaining = remaining - tweets.len();
+                        let cursor = match page.next_token {
+                            Some(token) if !tweets.is_empty() => Cursor::Next(token),
+                            _ => Cursor::Done,
+                        };
+                        Some((Ok(tweets), (cursor, remaining)))
+                    }
+                    Err(err) => Some((Err(err), (Cursor::Done, 0))),
+                }
+            }
+        })
+        .flat_map(|page| {
+            let items = match page {
+                Ok(tweets) => tweets.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            };
+            stream::iter(items)
+        })
+    }
+
     pub async fn get_latest_tweets(&self, count: i32) -> Result<Vec<Tweet>> {
         let tweets = self.tweet_repo.get_latest_tweets(count).await?;
         Ok(tweets)
     }
 
+    #[allow(dead_code)]
     pub async fn get_tweets_by_hashtag(&self, hashtag: &str) -> Result<Vec<Tweet>> {
         let tweets = self.tweet_repo.get_tweets_by_hashtag(hashtag).await?;
         Ok(tweets)
     }
 
-    pub async fn get_tweets_after_id(&self, query: &str, id: &TweetID) -> Result<Vec<Tweet>> {
-        let tweets = self.tweet_repo.get_tweets_after_id(query, id).await?;
-        Ok(tweets)
+    /// `since_id` より新しいツイートを `next_token` をたどってすべて集める。新しい順
+    ///
+    /// `since_id` が無いときは最初のページだけ。多すぎるときは `MAX_POLL_PAGES` ページで諦める。
+    /// 部分エラーはツイートとは別に返す。
+    pub async fn get_tweets_since(
+        &self,
+        query: &SearchQuery,
+        since_id: Option<&TweetID>,
+    ) -> Result<(Vec<Tweet>, Vec<ServiceError>)> {
+        let mut tweets = Vec::new();
+        let mut errors = Vec::new();
+        let mut token = None;
+        for _ in 0..MAX_POLL_PAGES {
+            let page = self
+                .tweet_repo
+                .get_tweets_page(query, PAGE_SIZE, since_id, token.as_deref())
+                .await?;
+            tweets.extend(page.tweets);
+            errors.extend(page.errors);
+            token = match (since_id, page.next_token) {
+                (Some(_), Some(next)) => Some(next),
+                _ => break,
+            };
+        }
+        Ok((tweets, errors))
     }
 
     // pub async fn save(&self, tweet: Tweet) -> Result<()> {
@@ -86,8 +260,388 @@ impl TweetService {
     //     Ok(())
     // }
 
+    /// `/2/tweets?ids=` から取り直す。`LOOKUP_SIZE` 件ずつに分ける
+    ///
+    /// 消されたり見られなくなったりしたツイートは部分エラーとして返す。
+    pub async fn lookup(&self, ids: &[String]) -> Result<(Vec<Tweet>, Vec<ServiceError>)> {
+        let mut tweets = Vec::new();
+        let mut errors = Vec::new();
+        for chunk in ids.chunks(LOOKUP_SIZE) {
+            let (found, problems) = self.tweet_repo.lookup_tweets(chunk).await?;
+            tweets.extend(found);
+            errors.extend(problems);
+        }
+        Ok((tweets, errors))
+    }
+
+    /// 保存済みのツイート。無いものは含まない
+    pub async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<Tweet>> {
+        let mut tweets = self.tweet_repo.find_by_ids(ids).await?;
+        self.resolve_authors(&mut tweets).await?;
+        self.resolve_media(&mut tweets).await?;
+        Ok(tweets)
+    }
+
+    /// 保存済みか、無ければ `/2/tweets/:id` から取ってきて保存する。取ってきたときは参照しているツイートも返す
+    async fn find_or_lookup(&self, id: &str, remote: bool) -> Result<Option<(Tweet, Vec<Tweet>)>> {
+        let id = TweetID(id.to_string());
+        match self.tweet_repo.find_by_id(&id).await {
+            Ok(tweet) => return Ok(Some((tweet, Vec::new()))),
+            Err(err) if !err.is_error_of(RepositoryError::RecordNotFound) => return Err(err),
+            Err(_) if !remote => return Ok(None),
+            Err(_) => {}
+        }
+        let (tweet, referenced) = match self.tweet_repo.lookup_tweet(&id).await {
+            Ok(found) => found,
+            Err(err) if err.is_error_of(TwitterApiError::NotFound) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        self.save_tweets(vec![tweet.clone()]).await?;
+        Ok(Some((tweet, referenced)))
+    }
+
+    /// 著者と添付を埋めたツイートと、返信先・引用先など参照しているツイート (保存済みか `includes.tweets` にあったもの)
+    pub async fn show(&self, id: &TweetID) -> Result<(Tweet, Vec<Tweet>)> {
+        let (tweet, mut included) = self.find_or_lookup(&id.0, true).await?.ok_or_else(|| {
+            ServiceError::new(
+                TwitterApiError::NotFound,
+                anyhow::anyhow!("tweet {} not found", id.0),
+            )
+        })?;
+        let mut tweets = vec![tweet];
+        self.resolve_authors(&mut tweets).await?;
+        self.resolve_media(&mut tweets).await?;
+        let tweet = tweets.remove(0);
+        let mut referenced = Vec::new();
+        for (_, id) in tweet.references() {
+            match self.find_by_ids(std::slice::from_ref(&id)).await?.pop() {
+                Some(found) => referenced.push(found),
+                None => {
+                    if let Some(index) = included.iter().position(|it| it.id == id) {
+                        referenced.push(included.swap_remove(index));
+                    }
+                }
+            }
+        }
+        Ok((tweet, referenced))
+    }
+
+    /// `id` を含むスレッド。`remote` なら `conversation_id:` で検索したものを保存して手元のものと合わせる
+    pub async fn thread(&self, id: &str, remote: bool) -> Result<Vec<ThreadEntry>> {
+        let (tweet, _) = self.find_or_lookup(id, remote).await?.ok_or_else(|| {
+            ServiceError::new(
+                TwitterApiError::NotFound,
+                anyhow::anyhow!("tweet {} not found", id),
+            )
+        })?;
+        let conversation_id = tweet.conversation_id.clone().unwrap_or(tweet.id.clone());
+
+        let mut tweets = self
+            .tweet_repo
+            .find_local(&LocalQuery {
+                conversation_id: Some(conversation_id.clone()),
+                order: Order::OldestFirst,
+                limit: MAX_THREAD,
+                ..Default::default()
+            })
+            .await?;
+        tweets.push(tweet);
+        if remote {
+            let found = self
+                .search_paginated(
+                    &SearchQuery::new().conversation(&conversation_id),
+                    MAX_THREAD,
+                )
+                .await?;
+            self.save_tweets(found.clone()).await?;
+            tweets.extend(found);
+        }
+        // 最初のツイートは 7 日より前だと検索に出てこない
+        if !tweets.iter().any(|it| it.id == conversation_id) {
+            if let Some((first, _)) = self.find_or_lookup(&conversation_id, remote).await? {
+                tweets.push(first);
+            }
+        }
+        self.resolve_authors(&mut tweets).await?;
+        self.resolve_media(&mut tweets).await?;
+
+        let mut entries = build_thread(tweets);
+        for entry in entries.iter_mut().filter(|it| it.quoted.is_none()) {
+            if let Some(quoted) = entry.tweet.quoted() {
+                entry.quoted = self.find_by_ids(&[quoted]).await?.pop();
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 投稿して、投稿したツイートを保存する
+    pub async fn post(&self, draft: &Draft) -> Result<Tweet> {
+        let tweet = self.tweet_repo.post_tweet(draft).await?;
+        self.save_tweets(vec![tweet.clone()]).await?;
+        Ok(tweet)
+    }
+
+    /// 著者のプロフィールと添付、いいねなどの数も保存する
     pub async fn save_tweets(&self, tweets: Vec<Tweet>) -> Result<()> {
+        let now = Utc::now();
+        self.metric_repo
+            .save_snapshots(
+                tweets
+                    .iter()
+                    .filter_map(|tweet| tweet.metric_snapshot(now))
+                    .collect(),
+            )
+            .await?;
+        self.user_repo.save_users(authors(&tweets)).await?;
+        for tweet in tweets.iter().filter(|tweet| !tweet.media.is_empty()) {
+            self.media_repo
+                .save_media(&TweetID(tweet.id.clone()), tweet.media.clone())
+                .await?;
+        }
         self.tweet_repo.save_tweets(tweets).await?;
         Ok(())
     }
 }
+
+/// `includes.users` から入った著者 (重複なし)
+fn authors(tweets: &[Tweet]) -> Vec<User> {
+    let mut users: Vec<User> = Vec::new();
+    for author in tweets.iter().filter_map(|tweet| tweet.author.as_ref()) {
+        if !users.iter().any(|user| user.id == author.id) {
+            users.push(author.clone());
+        }
+    }
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::HttpClient;
+    use crate::repository::testing::{memory_db, FakeTweetRepository};
+    use crate::repository::{MediaRepository, MetricRepository};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    fn tweet(id: usize) -> Tweet {
+        Tweet::new(
+            id.to_string(),
+            format!("tweet {}", id),
+            "1".to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// 保存済みのユーザーと、`/2/users` の代わりに `remote` を返す
+    #[derive(Default)]
+    struct UserCache {
+        saved: Mutex<Vec<User>>,
+        remote: Vec<User>,
+        lookups: Mutex<Vec<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl IUserRepository for UserCache {
+        async fn save_users(&self, users: Vec<User>) -> Result<()> {
+            let mut saved = self.saved.lock().unwrap();
+            saved.retain(|user| !users.iter().any(|it| it.id == user.id));
+            saved.extend(users);
+            Ok(())
+        }
+        async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<User>> {
+            let saved = self.saved.lock().unwrap();
+            Ok(saved
+                .iter()
+                .filter(|user| ids.contains(&user.id))
+                .cloned()
+                .collect())
+        }
+        async fn lookup(&self, ids: &[String]) -> Result<(Vec<User>, Vec<ServiceError>)> {
+            self.lookups.lock().unwrap().push(ids.to_vec());
+            Ok((
+                self.remote
+                    .iter()
+                    .filter(|user| ids.contains(&user.id))
+                    .cloned()
+                    .collect(),
+                Vec::new(),
+            ))
+        }
+    }
+
+    fn user(id: &str, name: &str, fetched_at: chrono::DateTime<Utc>) -> User {
+        User {
+            id: id.to_string(),
+            username: name.to_lowercase(),
+            name: name.to_string(),
+            created_at: None,
+            description: None,
+            location: None,
+            profile_image_url: None,
+            url: None,
+            protected: None,
+            verified: None,
+            public_metrics: None,
+            fetched_at,
+        }
+    }
+
+    /// `search/recent` は 100 件ずつ 3 ページ返す
+    fn service() -> (TweetService, Arc<FakeTweetRepository>) {
+        let pages = (0..3)
+            .map(|page| (0..100).map(|i| tweet(page * 1000 + i)).collect())
+            .collect();
+        service_with(Arc::new(UserCache::default()), pages)
+    }
+
+    fn service_with(
+        users: Arc<UserCache>,
+        pages: Vec<Vec<Tweet>>,
+    ) -> (TweetService, Arc<FakeTweetRepository>) {
+        let db = memory_db();
+        let repo = Arc::new(FakeTweetRepository::new(db.clone(), pages));
+        let http_client = Arc::new(HttpClient::new(RateLimits::default()));
+        (
+            TweetService::new(
+                repo.clone(),
+                users,
+                Arc::new(MediaRepository::new(db.clone(), http_client)),
+                Arc::new(MetricRepository::new(db)),
+            ),
+            repo,
+        )
+    }
+
+    #[tokio::test]
+    async fn it_should_follow_next_token_until_limit() {
+        let (service, repo) = service();
+        let query = SearchQuery::new().keyword("ワールドカップ");
+
+        let tweets = service.search_paginated(&query, 250).await.unwrap();
+
+        assert_eq!(tweets.len(), 250);
+        assert_eq!(
+            *repo.requests.lock().unwrap(),
+            vec![
+                (100, None),
+                (100, Some("1".to_string())),
+                (50, Some("2".to_string()))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_when_pages_run_out() {
+        let (service, repo) = service();
+        let query = SearchQuery::new().keyword("ワールドカップ");
+
+        let tweets = service.search_paginated(&query, 1000).await.unwrap();
+
+        assert_eq!(tweets.len(), 300);
+        assert_eq!(repo.requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn it_should_save_searched_tweets_with_snapshots() {
+        let searched = Tweet {
+            public_metrics: Some(PublicMetrics {
+                like_count: 3,
+                ..PublicMetrics::default()
+            }),
+            ..tweet(1)
+        };
+        let (service, _) = service_with(Arc::new(UserCache::default()), vec![vec![searched]]);
+        let query = SearchQuery::new().keyword("ワールドカップ");
+
+        service.search_paginated(&query, 10).await.unwrap();
+
+        let saved = service.find_by_ids(&["1".to_string()]).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        let snapshots = service
+            .metric_repo
+            .find_since(&(Utc::now() - chrono::Duration::minutes(1)))
+            .await
+            .unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].metrics.like_count, 3);
+    }
+
+    #[tokio::test]
+    async fn it_should_refresh_missing_and_stale_authors() {
+        let now = Utc::now();
+        let users = Arc::new(UserCache {
+            saved: Mutex::new(vec![
+                user("1", "Keisuke", now),
+                user("2", "Old", now - chrono::Duration::days(2)),
+            ]),
+            remote: vec![user("2", "Kaoru", now), user("3", "Ritsu", now)],
+            lookups: Mutex::new(Vec::new()),
+        });
+        let (service, _) = service_with(users.clone(), Vec::new());
+
+        let mut tweets = ["1", "2", "3", "4", "1"]
+            .iter()
+            .map(|author_id| Tweet {
+                author_id: author_id.to_string(),
+                ..tweet(0)
+            })
+            .collect::<Vec<_>>();
+        service.resolve_authors(&mut tweets).await.unwrap();
+
+        assert_eq!(
+            tweets.iter().map(Tweet::author_label).collect::<Vec<_>>(),
+            vec![
+                "@keisuke (Keisuke)",
+                "@kaoru (Kaoru)",
+                "@ritsu (Ritsu)",
+                "4",
+                "@keisuke (Keisuke)"
+            ]
+        );
+        assert_eq!(
+            *users.lookups.lock().unwrap(),
+            vec![vec!["2".to_string(), "3".to_string(), "4".to_string()]]
+        );
+        assert_eq!(users.saved.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn it_should_refresh_authors_in_chunks() {
+        let now = Utc::now();
+        let ids = (1000..1150).map(|id| id.to_string()).collect::<Vec<_>>();
+        let users = Arc::new(UserCache {
+            remote: ids.iter().map(|id| user(id, "Fan", now)).collect(),
+            ..UserCache::default()
+        });
+        let (service, _) = service_with(users.clone(), Vec::new());
+
+        let mut tweets = ids
+            .iter()
+            .map(|author_id| Tweet {
+                author_id: author_id.clone(),
+                ..tweet(0)
+            })
+            .collect::<Vec<_>>();
+        service.resolve_authors(&mut tweets).await.unwrap();
+
+        assert!(tweets.iter().all(|tweet| tweet.author.is_some()));
+        assert_eq!(
+            users
+                .lookups
+                .lock()
+                .unwrap()
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>(),
+            vec![100, 50]
+        );
+    }
+}
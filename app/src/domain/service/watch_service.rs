@@ -0,0 +1,119 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct WatchService {
+    watch_repo: Arc<dyn IWatchRepository + Send + Sync>,
+}
+
+impl WatchService {
+    pub fn new(watch_repo: Arc<dyn IWatchRepository + Send + Sync>) -> Self {
+        Self { watch_repo }
+    }
+
+    /// 組み込みのエントリと保存したエントリ。同じ名前なら保存した方を使う
+    pub async fn list(&self) -> Result<Vec<WatchEntry>> {
+        let stored = self.watch_repo.list().await?;
+        let mut entries = WatchEntry::builtins()
+            .into_iter()
+            .filter(|builtin| stored.iter().all(|it| it.name != builtin.name))
+            .collect::<Vec<_>>();
+        entries.extend(stored);
+        Ok(entries)
+    }
+
+    pub async fn find(&self, name: &str) -> Result<WatchEntry> {
+        self.list()
+            .await?
+            .into_iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| unknown_entry(name))
+    }
+
+    pub async fn add(&self, entry: WatchEntry) -> Result<()> {
+        self.watch_repo.save(entry).await
+    }
+
+    /// 上書きした組み込みのエントリを消すと元に戻る
+    pub async fn remove(&self, name: &str) -> Result<()> {
+        if self.watch_repo.delete(name).await? {
+            return Ok(());
+        }
+        if WatchEntry::builtins().iter().any(|it| it.name == name) {
+            Err(ServiceError::new(
+                WatchError::Builtin,
+                anyhow::anyhow!("`{}` is built in and cannot be removed", name),
+            ))
+        } else {
+            Err(unknown_entry(name))
+        }
+    }
+}
+
+fn unknown_entry(name: &str) -> ServiceError {
+    ServiceError::new(
+        WatchError::NotFound,
+        anyhow::anyhow!("watch entry `{}` is not defined", name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository(Mutex<Vec<WatchEntry>>);
+
+    #[async_trait]
+    impl IWatchRepository for MemoryRepository {
+        async fn list(&self) -> Result<Vec<WatchEntry>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+        async fn save(&self, entry: WatchEntry) -> Result<()> {
+            let mut entries = self.0.lock().unwrap();
+            entries.retain(|it| it.name != entry.name);
+            entries.push(entry);
+            Ok(())
+        }
+        async fn delete(&self, name: &str) -> Result<bool> {
+            let mut entries = self.0.lock().unwrap();
+            let before = entries.len();
+            entries.retain(|it| it.name != name);
+            Ok(entries.len() < before)
+        }
+    }
+
+    fn names(entries: &[WatchEntry]) -> Vec<&str> {
+        entries.iter().map(|it| it.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn it_should_merge_builtin_entries() {
+        let service = WatchService::new(Arc::new(MemoryRepository::default()));
+        assert_eq!(names(&service.list().await.unwrap()), vec!["keisuke"]);
+
+        let mitoma = WatchEntry::new("mitoma", WatchKind::Player, "三笘", None).unwrap();
+        service.add(mitoma).await.unwrap();
+        let keisuke = WatchEntry::new("keisuke", WatchKind::Person, "本田圭佑 解説", None).unwrap();
+        service.add(keisuke).await.unwrap();
+        assert_eq!(
+            names(&service.list().await.unwrap()),
+            vec!["mitoma", "keisuke"]
+        );
+        assert_eq!(
+            service.find("keisuke").await.unwrap().query,
+            "本田圭佑 解説"
+        );
+
+        service.remove("keisuke").await.unwrap();
+        assert!(service.find("keisuke").await.unwrap().builtin);
+        let err = service.remove("keisuke").await.unwrap_err();
+        assert!(err.is_error_of(WatchError::Builtin));
+        let err = service.remove("kubo").await.unwrap_err();
+        assert!(err.is_error_of(WatchError::NotFound));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::config;
+use crate::domain::model::{FixtureError, Match, Stage, Team};
+use crate::error::*;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::*;
+use std::path::{Path, PathBuf};
+
+/// 同梱の日程
+pub const BUNDLED: &str = include_str!("../fixtures/worldcup2022.toml");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+/// fixture ファイルの中身。`home` / `away` は `teams` (または先に読んだファイル) の `code`
+///
+/// ```toml
+/// [[teams]]
+/// code = "JPN"
+/// name = "日本"
+/// hashtags = ["SAMURAIBLUE"]
+///
+/// [[matches]]
+/// id = "r16-jpn-cro"
+/// stage = "round-of-16"
+/// home = "JPN"
+/// away = "CRO"
+/// kickoff = "2022-12-05T15:00:00Z"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FixtureFile {
+    teams: Vec<Team>,
+    matches: Vec<FixtureEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureEntry {
+    id: String,
+    stage: Stage,
+    home: String,
+    away: String,
+    kickoff: DateTime<Utc>,
+    venue: Option<String>,
+}
+
+/// 読み込んだチームと試合。後から読んだものが同じ `code` / `id` を上書きする
+#[derive(Debug, Default)]
+pub struct Fixtures {
+    teams: IndexMap<String, Team>,
+    matches: IndexMap<String, Match>,
+}
+
+impl Fixtures {
+    /// 同梱の日程、設定ディレクトリの `fixtures/`、`extra` の順に読む
+    pub fn load(extra: &[PathBuf]) -> Result<Fixtures> {
+        let mut fixtures = Fixtures::default();
+        fixtures.add(BUNDLED, Format::Toml)?;
+
+        let mut paths = fixtures_dir()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|it| it.path()))
+            .filter(|path| format_of(path).is_some())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.extend(extra.iter().cloned());
+
+        for path in paths {
+            fixtures.add_file(&path)?;
+        }
+        Ok(fixtures)
+    }
+
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let format = format_of(path).ok_or_else(|| {
+            invalid_file(anyhow::anyhow!(
+                "{}: fixture files must be .json or .toml",
+                path.display()
+            ))
+        })?;
+        let content = std::fs::read_to_string(path).map_err(invalid_file)?;
+        self.add(&content, format)
+            .map_err(|err| invalid_file(anyhow::anyhow!("{}: {}", path.display(), err)))
+    }
+
+    pub fn add(&mut self, content: &str, format: Format) -> Result<()> {
+        let file: FixtureFile = match format {
+            Format::Json => serde_json::from_str(content).map_err(invalid_file)?,
+            Format::Toml => toml::from_str(content).map_err(invalid_file)?,
+        };
+        for team in file.teams {
+            self.teams.insert(team.code.clone(), team);
+        }
+        for entry in file.matches {
+            let game = Match {
+                home: self.team(&entry.home)?,
+                away: self.team(&entry.away)?,
+                id: entry.id,
+                stage: entry.stage,
+                kickoff: entry.kickoff,
+                venue: entry.venue,
+            };
+            self.matches.insert(game.id.clone(), game);
+        }
+        Ok(())
+    }
+
+    fn team(&self, code: &str) -> Result<Team> {
+        self.teams.get(code).cloned().ok_or_else(|| {
+            ServiceError::new(
+                FixtureError::UnknownTeam,
+                anyhow::anyhow!("team `{}` is not defined", code),
+            )
+        })
+    }
+
+    pub fn into_matches(self) -> Vec<Match> {
+        self.matches.into_values().collect()
+    }
+}
+
+/// `<config dir>/fixtures`
+pub fn fixtures_dir() -> Option<PathBuf> {
+    config::project_dirs().map(|dirs| dirs.config_dir().join("fixtures"))
+}
+
+fn format_of(path: &Path) -> Option<Format> {
+    match path.extension()?.to_str()? {
+        "json" => Some(Format::Json),
+        "toml" => Some(Format::Toml),
+        _ => None,
+    }
+}
+
+fn invalid_file<E>(err: E) -> ServiceError
+where
+    anyhow::Error: From<E>,
+{
+    ServiceError::new(FixtureError::InvalidFile, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_bundled_fixtures() {
+        let mut fixtures = Fixtures::default();
+        fixtures.add(BUNDLED, Format::Toml).unwrap();
+        let matches = fixtures.into_matches();
+        let croatia = matches.iter().find(|it| it.id == "r16-jpn-cro").unwrap();
+        assert_eq!(croatia.home.name, "日本");
+        assert_eq!(croatia.stage, Stage::RoundOf16);
+        assert_eq!(croatia.kickoff.to_rfc3339(), "2022-12-05T15:00:00+00:00");
+    }
+
+    #[test]
+    fn it_should_override_with_user_fixtures() {
+        let mut fixtures = Fixtures::default();
+        fixtures.add(BUNDLED, Format::Toml).unwrap();
+        fixtures
+            .add(
+                r#"{
+                    "teams": [{"code": "AUS", "name": "オーストラリア"}],
+                    "matches": [
+                        {"id": "r16-jpn-cro", "stage": "round-of-16", "home": "JPN", "away": "CRO",
+                         "kickoff": "2022-12-06T00:00:00+09:00"},
+                        {"id": "friendly-jpn-aus", "stage": "final", "home": "JPN", "away": "AUS",
+                         "kickoff": "2023-03-24T10:20:00Z", "venue": "国立競技場"}
+                    ]
+                }"#,
+                Format::Json,
+            )
+            .unwrap();
+        let matches = fixtures.into_matches();
+        let croatia = matches.iter().find(|it| it.id == "r16-jpn-cro").unwrap();
+        assert_eq!(croatia.venue, None);
+        assert_eq!(croatia.kickoff.to_rfc3339(), "2022-12-05T15:00:00+00:00");
+        assert_eq!(matches.last().unwrap().away.name, "オーストラリア");
+
+        let mut fixtures = Fixtures::default();
+        let err = fixtures
+            .add(
+                r#"{"matches": [{"id": "x", "stage": "final", "home": "JPN", "away": "BRA", "kickoff": "2022-12-05T15:00:00Z"}]}"#,
+                Format::Json,
+            )
+            .unwrap_err();
+        assert!(err.is_error_of(FixtureError::UnknownTeam));
+    }
+}
@@ -6,3 +6,6 @@ pub use db_executor::*;
 
 mod http_client;
 pub use http_client::*;
+
+mod loopback;
+pub use loopback::*;
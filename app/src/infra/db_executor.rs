@@ -67,8 +67,9 @@ impl DBConnector {
         .await?
     }
 
-    pub async fn execute<Q: Send + 'static>(&self, query: Q) -> Result<usize>
+    pub async fn execute<Q>(&self, query: Q) -> Result<usize>
     where
+        Q: Send + 'static,
         Q: diesel::RunQueryDsl<diesel::SqliteConnection>,
         Q: diesel::query_builder::QueryFragment<diesel::sqlite::Sqlite>,
         Q: diesel::query_builder::QueryId,
@@ -81,8 +82,10 @@ impl DBConnector {
         .await?
     }
 
-    pub async fn first<T: 'static + Send, Q: 'static + Send>(&self, query: Q) -> Result<T>
+    #[allow(dead_code)]
+    pub async fn first<T: 'static + Send, Q>(&self, query: Q) -> Result<T>
     where
+        Q: 'static + Send,
         Q: diesel::query_dsl::limit_dsl::LimitDsl,
         Q: diesel::RunQueryDsl<diesel::SqliteConnection>,
         diesel::helper_types::Limit<Q>:
@@ -97,8 +100,9 @@ impl DBConnector {
         .await?
     }
 
-    pub async fn load<T: 'static + Send, Q: 'static + Send>(&self, query: Q) -> Result<Vec<T>>
+    pub async fn load<T: 'static + Send, Q>(&self, query: Q) -> Result<Vec<T>>
     where
+        Q: 'static + Send,
         Q: diesel::RunQueryDsl<diesel::SqliteConnection>,
         Q: for<'a> diesel::query_dsl::LoadQuery<'a, diesel::SqliteConnection, T>,
     {
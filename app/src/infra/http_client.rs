@@ -1,10 +1,10 @@
 use crate::domain::interface::*;
+use crate::domain::model::{endpoint, RateLimits};
 use crate::error::*;
 use async_trait::async_trait;
 
 #[derive(Debug)]
 pub enum HttpClientError {
-    #[allow(dead_code)]
     InvalidBody,
     HttpError,
 }
@@ -39,14 +39,29 @@ impl From<reqwest::Error> for ServiceError {
 #[derive(Clone)]
 pub struct HttpClient {
     client: reqwest::Client,
+    rate_limits: RateLimits,
 }
 
 impl HttpClient {
-    pub fn new() -> HttpClient {
+    pub fn new(rate_limits: RateLimits) -> HttpClient {
         HttpClient {
             client: reqwest::Client::new(),
+            rate_limits,
         }
     }
+
+    /// レート制限のヘッダをエンドポイントごとに覚えておく
+    async fn send(
+        &self,
+        method: &str,
+        url: &str,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let resp = req.send().await?;
+        self.rate_limits
+            .record(endpoint(method, url), resp.status(), resp.headers());
+        Ok(resp)
+    }
 }
 
 #[async_trait]
@@ -60,7 +75,7 @@ impl IHttpClient for HttpClient {
         if let Some(h) = header {
             req = req.headers(h);
         }
-        let resp = req.send().await?;
+        let resp = self.send("GET", url, req).await?;
 
         Ok(resp)
     }
@@ -76,7 +91,7 @@ impl IHttpClient for HttpClient {
             req = req.headers(h);
         }
 
-        let resp = req.send().await?;
+        let resp = self.send("POST", url, req).await?;
 
         Ok(resp)
     }
@@ -92,7 +107,7 @@ impl IHttpClient for HttpClient {
             req = req.headers(h);
         }
 
-        let resp = req.send().await?;
+        let resp = self.send("PUT", url, req).await?;
 
         Ok(resp)
     }
@@ -107,7 +122,7 @@ impl IHttpClient for HttpClient {
             req = req.headers(h);
         }
 
-        let resp = req.send().await?;
+        let resp = self.send("DELETE", url, req).await?;
 
         Ok(resp)
     }
@@ -0,0 +1,172 @@
+use crate::domain::model::{AuthCallback, AuthError};
+use crate::error::*;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// リダイレクトを受けるパス
+pub const CALLBACK_PATH: &str = "/callback";
+
+/// 1 つの接続でリクエストヘッダーを読み終えるまで待つ時間
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DONE_PAGE: &str = "<!doctype html><meta charset=\"utf-8\"><title>samuraicli</title><p>ログインしました。このウィンドウを閉じて端末に戻ってください。</p>";
+const FAILED_PAGE: &str = "<!doctype html><meta charset=\"utf-8\"><title>samuraicli</title><p>ログインできませんでした。端末のエラーを確かめてください。</p>";
+
+/// `auth login` の認可画面から戻ってくるリダイレクトを localhost で受ける
+pub struct LoopbackListener {
+    listener: TcpListener,
+}
+
+impl LoopbackListener {
+    /// `0` なら空いているポートを使う
+    pub async fn bind(port: u16) -> Result<LoopbackListener> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .await
+            .map_err(|err| ServiceError::new(AuthError::InvalidCallback, err))?;
+        Ok(LoopbackListener { listener })
+    }
+
+    pub fn redirect_uri(&self) -> Result<String> {
+        let addr = self
+            .listener
+            .local_addr()
+            .map_err(|err| ServiceError::new(AuthError::InvalidCallback, err))?;
+        Ok(format!("http://{}{}", addr, CALLBACK_PATH))
+    }
+
+    /// `CALLBACK_PATH` へのリクエストが来るまで待ち、その対象 (`/callback?code=...`) を返す
+    ///
+    /// `state` が合わないなど認可コードを取り出せないときは、ブラウザにもエラーのページを返す。
+    /// favicon などほかのパスへのリクエストには 404 を返して待ち続ける。
+    /// ブラウザが先に開いておくだけの接続で止まらないように、接続ごとに別のタスクで読む。
+    pub async fn accept(&self, state: &str) -> Result<String> {
+        let (found, mut callback) = mpsc::channel(1);
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (socket, _) = accepted
+                        .map_err(|err| ServiceError::new(AuthError::InvalidCallback, err))?;
+                    tokio::spawn(respond(socket, state.to_string(), found.clone()));
+                }
+                Some(target) = callback.recv() => return Ok(target),
+            }
+        }
+    }
+}
+
+/// 1 つの接続のリクエストを読んで応答し、`CALLBACK_PATH` なら対象を `found` に送る
+async fn respond(socket: TcpStream, state: String, found: mpsc::Sender<String>) {
+    let mut reader = BufReader::new(socket);
+    let request_line = tokio::time::timeout(READ_TIMEOUT, async {
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await.ok()?;
+        // ヘッダーは使わないので読み捨てる
+        let mut line = String::new();
+        while matches!(reader.read_line(&mut line).await, Ok(n) if n > 2) {
+            line.clear();
+        }
+        Some(request_line)
+    })
+    .await;
+    let Ok(Some(request_line)) = request_line else {
+        return;
+    };
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let mut socket = reader.into_inner();
+    if target.split('?').next() != Some(CALLBACK_PATH) {
+        let _ = socket
+            .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .await;
+        return;
+    }
+    let (status, page) = match AuthCallback::parse(&target).and_then(|it| it.code_for(&state)) {
+        Ok(_) => ("200 OK", DONE_PAGE),
+        Err(_) => ("400 Bad Request", FAILED_PAGE),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        status,
+        page.len()
+    );
+    let _ = socket.write_all(head.as_bytes()).await;
+    let _ = socket.write_all(page.as_bytes()).await;
+    let _ = found.send(target).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_should_return_callback_target() {
+        let listener = LoopbackListener::bind(0).await.unwrap();
+        let redirect_uri = listener.redirect_uri().unwrap();
+        let base = redirect_uri.trim_end_matches(CALLBACK_PATH).to_string();
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let favicon = client
+                .get(format!("{}/favicon.ico", base))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(favicon.status(), 404);
+            let callback = client
+                .get(format!("{}?state=s&code=c", redirect_uri))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(callback.status(), 200);
+        });
+        assert_eq!(
+            listener.accept("s").await.unwrap(),
+            "/callback?state=s&code=c"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_serve_error_page_on_state_mismatch() {
+        let listener = LoopbackListener::bind(0).await.unwrap();
+        let redirect_uri = listener.redirect_uri().unwrap();
+        let response = tokio::spawn(async move {
+            reqwest::get(format!("{}?state=other&code=c", redirect_uri))
+                .await
+                .unwrap()
+        });
+        // ログインを失敗させるのは呼び出し側。対象はそのまま返す
+        assert_eq!(
+            listener.accept("s").await.unwrap(),
+            "/callback?state=other&code=c"
+        );
+        let response = response.await.unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(response.text().await.unwrap(), FAILED_PAGE);
+    }
+
+    #[tokio::test]
+    async fn it_should_not_wait_for_idle_connections() {
+        let listener = LoopbackListener::bind(0).await.unwrap();
+        let redirect_uri = listener.redirect_uri().unwrap();
+        let addr = redirect_uri
+            .trim_start_matches("http://")
+            .trim_end_matches(CALLBACK_PATH)
+            .to_string();
+        // 先読みの接続は何も送らずに開いたままにする
+        let _idle = TcpStream::connect(&addr).await.unwrap();
+        tokio::spawn(async move {
+            reqwest::get(format!("{}?state=s&code=c", redirect_uri))
+                .await
+                .unwrap();
+        });
+        let target = tokio::time::timeout(Duration::from_secs(5), listener.accept("s"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(target, "/callback?state=s&code=c");
+    }
+}
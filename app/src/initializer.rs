@@ -1,14 +1,137 @@
+use crate::config::{self, ConfigError, ConfigFile, Profile};
+use crate::domain::model::RateLimits;
 use crate::domain::service;
+use crate::error::*;
 use crate::infra;
 use crate::repository;
+use clap::ColorChoice;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_QUERY: &str = "ワールドカップ";
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+pub const DEFAULT_THEME: &str = "samurai-blue";
+pub const DEFAULT_DB_POOL_SIZE: u32 = 5;
+pub const DEFAULT_REDIRECT_PORT: u16 = 8976;
 
 #[derive(Clone, Debug)]
 pub struct Config {
+    pub profile: String,
     pub db_url: String,
     pub db_pool_size: u32,
     // pub tweets_table_name: String,
     pub bearer_token: String,
+    pub queries: Vec<String>,
+    pub poll_interval: Duration,
+    pub theme: String,
+    /// `--color` で上書きする
+    pub color: ColorChoice,
+    pub detector: service::DetectorConfig,
+    pub event_bell: bool,
+    pub event_hook: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub redirect_port: u16,
+    /// `user_auth = "oauth1"` のときだけ。ユーザーとしての呼び出しを OAuth 1.0a で署名する
+    pub oauth1: Option<repository::OAuth1Credentials>,
+    pub cheer_templates: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// 設定ディレクトリの `config.toml` を読み、環境変数で上書きする
+    pub fn load(profile: Option<&str>) -> Result<Config> {
+        let file = match config::config_path() {
+            Some(path) => ConfigFile::load(&path)?,
+            None => ConfigFile::default(),
+        };
+        let (name, profile) = file.profile(profile)?;
+        Config::from_profile(name, profile, |key| std::env::var(key).ok())
+    }
+
+    /// 環境変数 (`DATABASE_URL`, `DATABASE_POOL_SIZE`, `BEARER_TOKEN`, `POLL_INTERVAL`, `CLIENT_ID`, `CLIENT_SECRET`, `USER_AUTH`, `CONSUMER_KEY`, `CONSUMER_SECRET`, `ACCESS_TOKEN`, `ACCESS_TOKEN_SECRET`) はファイルの値より優先される
+    pub fn from_profile(
+        name: String,
+        profile: Profile,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config> {
+        let db_url = env("DATABASE_URL")
+            .or(profile.db_path)
+            .ok_or_else(|| missing_value("DATABASE_URL or db_path must be set"))?;
+        let db_pool_size = env("DATABASE_POOL_SIZE")
+            .and_then(|it| it.parse().ok())
+            .or(profile.db_pool_size)
+            .unwrap_or(DEFAULT_DB_POOL_SIZE);
+        let bearer_token = env("BEARER_TOKEN")
+            .or(profile.bearer_token)
+            .ok_or_else(|| missing_value("BEARER_TOKEN or bearer_token must be set"))?;
+        let poll_interval = env("POLL_INTERVAL")
+            .and_then(|it| it.parse().ok())
+            .or(profile.poll_interval)
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+        let mut detector = service::DetectorConfig::default();
+        if let Some(threshold) = profile.event_threshold {
+            detector.threshold = threshold;
+        }
+        if let Some(window) = profile.event_window {
+            detector.window = chrono::Duration::seconds(window as i64);
+        }
+        let oauth1 = match env("USER_AUTH").or(profile.user_auth).as_deref() {
+            None | Some("oauth2") => None,
+            Some("oauth1") => Some(repository::OAuth1Credentials {
+                consumer_key: env("CONSUMER_KEY")
+                    .or(profile.consumer_key)
+                    .ok_or_else(|| missing_value("CONSUMER_KEY or consumer_key must be set"))?,
+                consumer_secret: env("CONSUMER_SECRET")
+                    .or(profile.consumer_secret)
+                    .ok_or_else(|| {
+                        missing_value("CONSUMER_SECRET or consumer_secret must be set")
+                    })?,
+                access_token: env("ACCESS_TOKEN")
+                    .or(profile.access_token)
+                    .ok_or_else(|| missing_value("ACCESS_TOKEN or access_token must be set"))?,
+                access_token_secret: env("ACCESS_TOKEN_SECRET")
+                    .or(profile.access_token_secret)
+                    .ok_or_else(|| {
+                        missing_value("ACCESS_TOKEN_SECRET or access_token_secret must be set")
+                    })?,
+            }),
+            Some(other) => {
+                return Err(ServiceError::new(
+                    ConfigError::InvalidFile,
+                    anyhow::anyhow!("user_auth must be `oauth2` or `oauth1`, not `{}`", other),
+                ))
+            }
+        };
+        let queries = if profile.queries.is_empty() {
+            vec![DEFAULT_QUERY.to_string()]
+        } else {
+            profile.queries
+        };
+
+        Ok(Config {
+            profile: name,
+            db_url,
+            db_pool_size,
+            bearer_token,
+            queries,
+            poll_interval: Duration::from_secs(poll_interval),
+            theme: profile.theme.unwrap_or_else(|| DEFAULT_THEME.to_string()),
+            color: ColorChoice::Auto,
+            detector,
+            event_bell: profile.event_bell.unwrap_or(true),
+            event_hook: profile.event_hook,
+            client_id: env("CLIENT_ID").or(profile.client_id),
+            client_secret: env("CLIENT_SECRET").or(profile.client_secret),
+            redirect_port: profile.redirect_port.unwrap_or(DEFAULT_REDIRECT_PORT),
+            oauth1,
+            cheer_templates: profile.cheer_templates,
+        })
+    }
+}
+
+fn missing_value(message: &'static str) -> ServiceError {
+    ServiceError::new(ConfigError::MissingValue, anyhow::anyhow!(message))
 }
 
 #[derive(Clone)]
@@ -16,6 +139,8 @@ pub struct Infras {
     pub db: infra::DBConnector,
     pub http_client: Arc<infra::HttpClient>,
     pub bearer_token: String,
+    /// `HttpClient` が更新し、ポーリングの間隔に使う
+    pub rate_limits: RateLimits,
 }
 impl Infras {
     pub async fn ensure_initialized(&self) -> Option<()> {
@@ -30,50 +155,212 @@ impl Infras {
 pub async fn infras(config: &Config) -> Infras {
     let db_executor = infra::DBExecutor::new(config.db_url.clone(), config.db_pool_size);
     let db_connector = infra::DBConnector::new(db_executor);
-    let http_client = Arc::new(infra::HttpClient::new());
+    let rate_limits = RateLimits::default();
+    let http_client = Arc::new(infra::HttpClient::new(rate_limits.clone()));
     Infras {
         db: db_connector,
         http_client: http_client.clone(),
         bearer_token: config.bearer_token.clone(),
+        rate_limits,
     }
 }
 
 #[derive(Clone)]
-
 pub struct Repository {
+    pub action: Arc<repository::ActionRepository>,
+    pub auth: Arc<repository::AuthRepository>,
     pub tweet: Arc<repository::TweetRepository>,
+    pub user: Arc<repository::UserRepository>,
+    pub media: Arc<repository::MediaRepository>,
+    pub metric: Arc<repository::MetricRepository>,
+    pub stream: Arc<repository::StreamRepository>,
+    pub watch: Arc<repository::WatchRepository>,
+    pub fixture: Arc<repository::FixtureRepository>,
+    pub event: Arc<repository::EventRepository>,
 }
 
-pub fn repository(infras: &Infras) -> Repository {
+pub fn repository(config: &Config, infras: &Infras) -> Repository {
+    let client = config
+        .client_id
+        .clone()
+        .map(|client_id| repository::OAuthClient {
+            client_id,
+            client_secret: config.client_secret.clone(),
+        });
+    let auth = Arc::new(repository::AuthRepository::new(
+        infras.http_client.clone(),
+        infras.bearer_token.clone(),
+        client,
+        config.oauth1.clone().map(repository::OAuth1Signer::new),
+        config::token_path(&config.profile),
+    ));
     let tweet = Arc::new(repository::TweetRepository::new(
         infras.db.clone(),
         infras.http_client.clone(),
-        infras.bearer_token.clone(),
+        auth.clone(),
+    ));
+    let user = Arc::new(repository::UserRepository::new(
+        infras.db.clone(),
+        infras.http_client.clone(),
+        auth.clone(),
     ));
-    Repository { tweet }
+    let media = Arc::new(repository::MediaRepository::new(
+        infras.db.clone(),
+        infras.http_client.clone(),
+    ));
+    let metric = Arc::new(repository::MetricRepository::new(infras.db.clone()));
+    let stream = Arc::new(repository::StreamRepository::new(
+        infras.http_client.clone(),
+        auth.clone(),
+    ));
+    let watch = Arc::new(repository::WatchRepository::new(infras.db.clone()));
+    let fixture = Arc::new(repository::FixtureRepository::new(infras.db.clone()));
+    let event = Arc::new(repository::EventRepository::new(infras.db.clone()));
+    let action = Arc::new(repository::ActionRepository::new(infras.db.clone()));
+    Repository {
+        action,
+        auth,
+        tweet,
+        user,
+        media,
+        metric,
+        stream,
+        watch,
+        fixture,
+        event,
+    }
 }
 
 #[derive(Clone)]
 pub struct Services {
+    pub action: service::ActionService,
+    pub auth: service::AuthService,
     pub tweet: service::TweetService,
+    pub media: service::MediaService,
+    pub metric: service::MetricService,
+    pub stream: service::StreamService,
+    pub watch: service::WatchService,
+    pub fixture: service::FixtureService,
+    pub event: service::EventService,
 }
 
 #[derive(Clone)]
 pub struct AppContext {
+    pub config: Config,
     pub infras: Infras,
+    #[allow(dead_code)]
     pub repository: Repository,
     pub services: Services,
 }
 
 pub async fn new(config: Config) -> AppContext {
     let infras = infras(&config).await;
-    let repository = repository(&infras);
+    let repository = repository(&config, &infras);
+    let tweet = service::TweetService::new(
+        repository.tweet.clone(),
+        repository.user.clone(),
+        repository.media.clone(),
+        repository.metric.clone(),
+    );
     let services = Services {
-        tweet: service::TweetService::new(repository.tweet.clone()),
+        action: service::ActionService::new(
+            repository.tweet.clone(),
+            repository.action.clone(),
+            tweet.clone(),
+        ),
+        auth: service::AuthService::new(repository.auth.clone()),
+        metric: service::MetricService::new(tweet.clone(), repository.metric.clone()),
+        stream: service::StreamService::new(repository.stream.clone(), tweet.clone()),
+        tweet,
+        media: service::MediaService::new(repository.media.clone()),
+        watch: service::WatchService::new(repository.watch.clone()),
+        fixture: service::FixtureService::new(repository.fixture.clone()),
+        event: service::EventService::new(repository.event.clone()),
     };
     AppContext {
+        config,
         infras,
         repository,
         services,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn profile() -> Profile {
+        Profile {
+            queries: vec!["本田圭佑".to_string()],
+            poll_interval: Some(30),
+            theme: Some("plain".to_string()),
+            db_path: Some("file.db".to_string()),
+            db_pool_size: None,
+            bearer_token: Some("file-token".to_string()),
+            event_threshold: Some(2.5),
+            ..Profile::default()
+        }
+    }
+
+    #[test]
+    fn it_should_build_config_from_profile() {
+        let config = Config::from_profile("p".to_string(), profile(), |_| None).unwrap();
+        assert_eq!(config.db_url, "file.db");
+        assert_eq!(config.bearer_token, "file-token");
+        assert_eq!(config.db_pool_size, DEFAULT_DB_POOL_SIZE);
+        assert_eq!(config.poll_interval, Duration::from_secs(30));
+        assert_eq!(config.queries, vec!["本田圭佑".to_string()]);
+        assert_eq!(config.theme, "plain");
+        assert_eq!(config.detector.threshold, 2.5);
+        assert!(config.event_bell);
+    }
+
+    #[test]
+    fn it_should_prefer_env_over_profile() {
+        let env = HashMap::from([
+            ("DATABASE_URL", "env.db"),
+            ("BEARER_TOKEN", "env-token"),
+            ("DATABASE_POOL_SIZE", "2"),
+        ]);
+        let config = Config::from_profile("p".to_string(), profile(), |key| {
+            env.get(key).map(|it| it.to_string())
+        })
+        .unwrap();
+        assert_eq!(config.db_url, "env.db");
+        assert_eq!(config.bearer_token, "env-token");
+        assert_eq!(config.db_pool_size, 2);
+    }
+
+    #[test]
+    fn it_should_select_oauth1() {
+        let oauth1 = Profile {
+            user_auth: Some("oauth1".to_string()),
+            consumer_key: Some("key".to_string()),
+            consumer_secret: Some("secret".to_string()),
+            access_token: Some("42-token".to_string()),
+            ..profile()
+        };
+        let err = Config::from_profile("p".to_string(), oauth1.clone(), |_| None).unwrap_err();
+        assert!(err.is_error_of(ConfigError::MissingValue));
+
+        let config = Config::from_profile("p".to_string(), oauth1, |key| {
+            (key == "ACCESS_TOKEN_SECRET").then(|| "token-secret".to_string())
+        })
+        .unwrap();
+        assert_eq!(
+            config.oauth1.unwrap().access_token_secret,
+            "token-secret".to_string()
+        );
+        assert!(Config::from_profile("p".to_string(), profile(), |_| None)
+            .unwrap()
+            .oauth1
+            .is_none());
+    }
+
+    #[test]
+    fn it_should_require_token() {
+        let err = Config::from_profile("p".to_string(), Profile::default(), |_| None).unwrap_err();
+        assert!(err.is_error_of(ConfigError::MissingValue));
+    }
+}
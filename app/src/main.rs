@@ -6,19 +6,19 @@ mod wrapper;
 pub use wrapper::*;
 
 use dotenv::dotenv;
-use owo_colors::{AnsiColors, OwoColorize};
-use rand::Rng;
 use std::ffi::OsString;
 
-use clap::{ColorChoice, Command};
-
-use crate::domain::model::TweetID;
+use clap::{Arg, ColorChoice, Command};
 
+mod command;
+mod config;
 mod domain;
+mod fixtures;
 mod infra;
 mod initializer;
 mod repository;
 mod schema;
+mod view;
 
 fn cli() -> Command {
     Command::new("samuraicup")
@@ -26,10 +26,39 @@ fn cli() -> Command {
         .subcommand_required(true)
         .arg_required_else_help(true)
         .allow_external_subcommands(true)
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .help("config.toml のプロファイル名"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .global(true)
+                .value_parser(clap::value_parser!(ColorChoice))
+                .default_value("auto")
+                .help("色をつけるか (auto は NO_COLOR と端末かどうかで決める)"),
+        )
         // real: color red
-        .subcommand(Command::new("real").about("⚽ワールドカップをリアルタイムで確認する"))
-        .subcommand(Command::new("search").about("🥅ワールドカップのツイートを取得する"))
-        .subcommand(Command::new("keisuke").about("📣本田圭佑の動向を取得する"))
+        .subcommand(command::real::command())
+        .subcommand(command::search::command())
+        .subcommand(command::keisuke::command())
+        .subcommand(command::history::command())
+        .subcommand(command::top::command())
+        .subcommand(command::thread::command())
+        .subcommand(command::show::command())
+        .subcommand(command::stream::command())
+        .subcommand(command::cheer::command())
+        .subcommand(command::reply::command())
+        .subcommands(command::action::commands())
+        .subcommand(command::likes::command())
+        .subcommand(command::auth::command())
+        .subcommand(command::events::command())
+        .subcommand(command::watch::command())
+        .subcommand(command::schedule::command())
+        .subcommand(command::game::command())
+        .subcommand(command::plugins::command())
 }
 
 #[tokio::main]
@@ -38,21 +67,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_BACKTRACE", "1");
     dotenv().ok();
 
-    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db_pool_size = std::env::var("DATABASE_POOL_SIZE")
-        .ok()
-        .and_then(|it| it.parse().ok())
-        .unwrap_or(5);
-    let bearer_token = std::env::var("BEARER_TOKEN").expect("BEARER_TOKEN not set");
-    // let tweets_table_name = std::env::var("TWEETS_TABLE_NAME").expect("TWEETS_TABLE_NAME not set");
-
-    let app = initializer::new(initializer::Config {
-        db_url: db_url,
-        db_pool_size: db_pool_size,
-        // tweets_table_name: tweets_table_name,
-        bearer_token: bearer_token,
-    })
-    .await;
+    let matches = cli().get_matches();
+
+    // プラグインは DB や BEARER_TOKEN が無くても動かせるよう、初期化より前に実行する
+    if let Some((ext, sub_matches)) = matches.subcommand() {
+        if cli().find_subcommand(ext).is_none() {
+            let config = initializer::Config::load(
+                matches.get_one::<String>("profile").map(|it| it.as_str()),
+            )
+            .ok();
+            let args = sub_matches
+                .get_many::<OsString>("")
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            // プラグインの終了コードをそのまま返す
+            match command::plugins::dispatch(config.as_ref(), ext, &args).await {
+                Ok(0) => return Ok(()),
+                Ok(code) => std::process::exit(code),
+                Err(err) => return Err(err.to_string().into()),
+            }
+        }
+    }
+
+    let mut config =
+        initializer::Config::load(matches.get_one::<String>("profile").map(|it| it.as_str()))
+            .map_err(|err| err.to_string())?;
+    if let Some(color) = matches.get_one::<ColorChoice>("color") {
+        config.color = *color;
+    }
+
+    let app = initializer::new(config).await;
 
     app.infras
         .ensure_initialized()
@@ -73,112 +118,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // get tweets by every 1 minute and save to db
     // tweet view
 
-    let matches = cli().get_matches();
-
-    match matches.subcommand() {
-        Some(("real", sub_matches)) => {
-            let mut rng = rand::thread_rng();
-            let color = owo_colors::Rgb(
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-            );
-
-            let text_color = owo_colors::Rgb(
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-            );
-            loop {
-                let latest_tweet = app.services.tweet.get_latest_tweets(1).await.unwrap();
-
-                let latest_tweet_id = if latest_tweet.len() == 0 {
-                    TweetID("0".to_string())
-                } else {
-                    TweetID(latest_tweet[0].id.clone())
-                };
-
-                let tweets = if latest_tweet.len() == 0 {
-                    app.services
-                        .tweet
-                        .get_tweets("ワールドカップ")
-                        .await
-                        .unwrap()
-                } else {
-                    app.services
-                        .tweet
-                        .get_tweets_after_id("ワールドカップ", &latest_tweet_id)
-                        .await
-                        .unwrap()
-                };
-
-                app.services
-                    .tweet
-                    .save_tweets(tweets.clone())
-                    .await
-                    .unwrap();
-
-                for tweet in tweets {
-                    println!(
-                        "{} {}",
-                        format!("{}", tweet.author_id).color(color).bold(),
-                        format!("{}", tweet.text).color(text_color),
-                    );
-                }
-
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-        }
-        Some(("search", sub_matches)) => {
-            let tweets = app
-                .services
-                .tweet
-                .get_tweets("ワールドカップ")
-                .await
-                .unwrap();
-
-            for tweet in tweets {
-                println!("{}", tweet.text);
-            }
-        }
-        Some(("keisuke", sub_matches)) => {
-            let mut rng = rand::thread_rng();
-
-            let color = owo_colors::Rgb(
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-            );
-
-            let text_color = owo_colors::Rgb(
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-                rng.gen_range(0..255),
-            );
-            let tweets = app.services.tweet.get_tweets("本田圭佑").await.unwrap();
-            let debug_str = "    ";
-            for tweet in tweets {
-                println!(
-                    "{}",
-                    format!(
-                        "{}{}{}",
-                        debug_str,
-                        format!("{}: ", tweet.author_id).color(color),
-                        tweet.text.color(text_color)
-                    )
-                );
-            }
-        }
-        Some((ext, sub_matches)) => {
-            let args = sub_matches
-                .get_many::<OsString>("")
-                .into_iter()
-                .flatten()
-                .collect::<Vec<_>>();
-            println!("Calling out to {:?} with {:?}", ext, args);
+    let result = match matches.subcommand() {
+        Some(("real", sub_matches)) => command::real::run(&app, sub_matches).await,
+        Some(("search", sub_matches)) => command::search::run(&app, sub_matches).await,
+        Some(("keisuke", sub_matches)) => command::keisuke::run(&app, sub_matches).await,
+        Some(("watch", sub_matches)) => command::watch::run(&app, sub_matches).await,
+        Some(("schedule", sub_matches)) => command::schedule::run(&app, sub_matches).await,
+        Some(("match", sub_matches)) => command::game::run(&app, sub_matches).await,
+        Some(("events", sub_matches)) => command::events::run(&app, sub_matches).await,
+        Some(("history", sub_matches)) => command::history::run(&app, sub_matches).await,
+        Some(("top", sub_matches)) => command::top::run(&app, sub_matches).await,
+        Some(("thread", sub_matches)) => command::thread::run(&app, sub_matches).await,
+        Some(("show", sub_matches)) => command::show::run(&app, sub_matches).await,
+        Some(("stream", sub_matches)) => command::stream::run(&app, sub_matches).await,
+        Some(("cheer", sub_matches)) => command::cheer::run(&app, sub_matches).await,
+        Some(("reply", sub_matches)) => command::reply::run(&app, sub_matches).await,
+        Some((name @ ("like" | "unlike" | "retweet" | "unretweet" | "delete"), sub_matches)) => {
+            command::action::run(&app, name, sub_matches).await
         }
+        Some(("likes", sub_matches)) => command::likes::run(&app, sub_matches).await,
+        Some(("auth", sub_matches)) => command::auth::run(&app, sub_matches).await,
+        Some(("plugins", sub_matches)) => command::plugins::run(&app, sub_matches).await,
         _ => unreachable!(),
-    }
-
+    };
+
+    // ユーザーとして呼んだときの 401 は `AuthError::TokenRejected` になり、見直すものはメッセージに入っている
+    result.map_err(|err| {
+        if err.is_error_of(domain::model::TwitterApiError::Unauthorized) {
+            format!("{}\nBEARER_TOKEN を確認してください", err)
+        } else {
+            err.to_string()
+        }
+    })?;
     Ok(())
 }
@@ -1,5 +1,329 @@
 mod tweet_repo;
 pub use tweet_repo::*;
 
+mod action_repo;
+pub use action_repo::*;
+
+mod auth_repo;
+pub use auth_repo::*;
+
+mod fixture_repo;
+pub use fixture_repo::*;
+
+mod event_repo;
+pub use event_repo::*;
+
+mod media_repo;
+pub use media_repo::*;
+
+mod metric_repo;
+pub use metric_repo::*;
+
+mod stream_repo;
+pub use stream_repo::*;
+
+mod user_repo;
+pub use user_repo::*;
+
+mod watch_repo;
+pub use watch_repo::*;
+
+mod twitter_api;
+pub use twitter_api::*;
+
+mod oauth1;
+pub use oauth1::*;
+
 mod repository_error;
 pub use repository_error::*;
+
+#[cfg(test)]
+pub mod testing {
+    use crate::domain::interface::{IStreamRepository, ITweetRepository, TweetStream};
+    use crate::domain::model::*;
+    use crate::error::*;
+    use crate::infra::{DBConnector, DBExecutor, HttpClient};
+    use crate::repository::{AuthRepository, TweetRepository};
+    use async_trait::async_trait;
+    use diesel::connection::SimpleConnection;
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// `migrations/` をすべて流したインメモリの DB
+    pub fn memory_db() -> DBConnector {
+        // 接続ごとに別の DB になるので 1 本だけにする
+        let executor = DBExecutor::new(":memory:".to_string(), 1);
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let mut migrations = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().join("up.sql"))
+            .filter(|path| path.exists())
+            .collect::<Vec<_>>();
+        migrations.sort();
+        let mut conn = executor.get_connection();
+        for migration in migrations {
+            conn.batch_execute(&std::fs::read_to_string(migration).unwrap())
+                .unwrap();
+        }
+        DBConnector::new(executor)
+    }
+
+    /// 何を聞かれても `status` と `body` を返す HTTP サーバーを立てて、その URL を返す
+    pub async fn serve(status: u16, content_type: &'static str, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    // リクエストはヘッダーの終わりまで読み捨てる
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|it| it == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let head = format!(
+                        "HTTP/1.1 {} X\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        status,
+                        content_type,
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                });
+            }
+        });
+        format!("http://{}/media", addr)
+    }
+
+    /// `responses[n]` の各チャンクを n 回目の接続に chunked で少しずつ返して切る。最後の応答はその後も繰り返す
+    pub async fn serve_chunks(responses: Vec<Vec<Vec<u8>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let chunks = responses[connections.min(responses.len() - 1)].clone();
+                connections += 1;
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|it| it == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let head = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n";
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    for chunk in chunks {
+                        let _ = socket
+                            .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                            .await;
+                        let _ = socket.write_all(&chunk).await;
+                        let _ = socket.write_all(b"\r\n").await;
+                        let _ = socket.flush().await;
+                        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    }
+                    let _ = socket.write_all(b"0\r\n\r\n").await;
+                });
+            }
+        });
+        format!("http://{}/2/tweets/search/stream", addr)
+    }
+
+    /// 空の Bearer トークンの `AuthRepository`。ユーザーとしては呼べない
+    pub fn auth_repository() -> Arc<AuthRepository> {
+        let http_client = Arc::new(HttpClient::new(RateLimits::default()));
+        Arc::new(AuthRepository::new(
+            http_client,
+            String::new(),
+            None,
+            None,
+            None,
+        ))
+    }
+
+    /// `memory_db` に保存する `TweetRepository`。API は呼べないので空の Bearer トークンで作る
+    pub fn tweet_repository(db: DBConnector) -> TweetRepository {
+        let http_client = Arc::new(HttpClient::new(RateLimits::default()));
+        TweetRepository::new(db, http_client, auth_repository())
+    }
+
+    /// サービスのテスト用の `ITweetRepository`
+    ///
+    /// 保存や DB の検索は `memory_db` の `TweetRepository` に任せ、`search/recent` は `pages` を 1 ページずつ
+    /// (`since_id` 以前のものは除いて) 返す。
+    /// ほかの API を呼ぶメソッドはエラーになる。
+    pub struct FakeTweetRepository {
+        local: TweetRepository,
+        pages: Vec<Vec<Tweet>>,
+        /// `get_tweets_page` に渡された `(max_results, pagination_token)`
+        pub requests: Mutex<Vec<(usize, Option<String>)>>,
+    }
+
+    impl FakeTweetRepository {
+        pub fn new(db: DBConnector, pages: Vec<Vec<Tweet>>) -> Self {
+            Self {
+                local: tweet_repository(db),
+                pages,
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    /// ツイートの ID は新しいほど大きい
+    fn newer_than(id: &str, since_id: &str) -> bool {
+        id.parse::<u64>().unwrap() > since_id.parse::<u64>().unwrap()
+    }
+
+    fn unsupported(fake: &str, method: &str) -> ServiceError {
+        ServiceError::new(
+            TwitterApiError::ServiceUnavailable,
+            anyhow::anyhow!("{} does not support `{}`", fake, method),
+        )
+    }
+
+    #[async_trait]
+    impl ITweetRepository for FakeTweetRepository {
+        async fn find_by_id(&self, id: &TweetID) -> Result<Tweet> {
+            self.local.find_by_id(id).await
+        }
+        async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<Tweet>> {
+            self.local.find_by_ids(ids).await
+        }
+        async fn save(&self, tweet: Tweet) -> Result<()> {
+            self.local.save(tweet).await
+        }
+        async fn save_tweets(&self, tweets: Vec<Tweet>) -> Result<()> {
+            self.local.save_tweets(tweets).await
+        }
+        async fn search(&self, query: &str) -> Result<Vec<Tweet>> {
+            self.local.search(query).await
+        }
+        async fn get_tweets(&self, _query: &SearchQuery) -> Result<Vec<Tweet>> {
+            Err(unsupported("FakeTweetRepository", "get_tweets"))
+        }
+        async fn get_tweets_page(
+            &self,
+            _query: &SearchQuery,
+            max_results: usize,
+            since_id: Option<&TweetID>,
+            pagination_token: Option<&str>,
+        ) -> Result<TweetPage> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((max_results, pagination_token.map(String::from)));
+            let page = pagination_token.map_or(0, |token| token.parse().unwrap());
+            Ok(TweetPage {
+                tweets: self
+                    .pages
+                    .get(page)
+                    .into_iter()
+                    .flatten()
+                    .filter(|tweet| since_id.is_none_or(|id| newer_than(&tweet.id, &id.0)))
+                    .take(max_results)
+                    .cloned()
+                    .collect(),
+                next_token: (page + 1 < self.pages.len()).then(|| (page + 1).to_string()),
+                errors: Vec::new(),
+            })
+        }
+        async fn get_latest_tweets(&self, count: i32) -> Result<Vec<Tweet>> {
+            self.local.get_latest_tweets(count).await
+        }
+        async fn lookup_tweet(&self, _id: &TweetID) -> Result<(Tweet, Vec<Tweet>)> {
+            Err(unsupported("FakeTweetRepository", "lookup_tweet"))
+        }
+        async fn lookup_tweets(&self, _ids: &[String]) -> Result<(Vec<Tweet>, Vec<ServiceError>)> {
+            Err(unsupported("FakeTweetRepository", "lookup_tweets"))
+        }
+        async fn find_local(&self, query: &LocalQuery) -> Result<Vec<Tweet>> {
+            self.local.find_local(query).await
+        }
+        async fn get_tweets_by_hashtag(&self, _hashtag: &str) -> Result<Vec<Tweet>> {
+            Err(unsupported("FakeTweetRepository", "get_tweets_by_hashtag"))
+        }
+        async fn get_tweets_after_id(
+            &self,
+            _query: &SearchQuery,
+            _id: &TweetID,
+        ) -> Result<Vec<Tweet>> {
+            Err(unsupported("FakeTweetRepository", "get_tweets_after_id"))
+        }
+        async fn delete(&self, id: &TweetID) -> Result<()> {
+            self.local.delete(id).await
+        }
+        async fn delete_tweet(&self, _id: &TweetID) -> Result<()> {
+            Err(unsupported("FakeTweetRepository", "delete_tweet"))
+        }
+        async fn favorite_tweet(&self, _id: &TweetID) -> Result<()> {
+            Err(unsupported("FakeTweetRepository", "favorite_tweet"))
+        }
+        async fn unfavorite_tweet(&self, _id: &TweetID) -> Result<()> {
+            Err(unsupported("FakeTweetRepository", "unfavorite_tweet"))
+        }
+        async fn retweet(&self, _id: &TweetID) -> Result<()> {
+            Err(unsupported("FakeTweetRepository", "retweet"))
+        }
+        async fn unretweet(&self, _id: &TweetID) -> Result<()> {
+            Err(unsupported("FakeTweetRepository", "unretweet"))
+        }
+        async fn liked_tweets(&self, _max_results: usize) -> Result<Vec<Tweet>> {
+            Err(unsupported("FakeTweetRepository", "liked_tweets"))
+        }
+        async fn post_tweet(&self, _draft: &Draft) -> Result<Tweet> {
+            Err(unsupported("FakeTweetRepository", "post_tweet"))
+        }
+    }
+
+    /// サービスのテスト用の `IStreamRepository`。送り手に渡したものを 1 回目の `connect` のストリームで流す
+    pub struct FakeStreamRepository {
+        messages: Mutex<Option<mpsc::UnboundedReceiver<StreamMessage>>>,
+    }
+
+    impl FakeStreamRepository {
+        pub fn new() -> (Self, mpsc::UnboundedSender<StreamMessage>) {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let repo = Self {
+                messages: Mutex::new(Some(receiver)),
+            };
+            (repo, sender)
+        }
+    }
+
+    #[async_trait]
+    impl IStreamRepository for FakeStreamRepository {
+        async fn list_rules(&self) -> Result<Vec<StreamRule>> {
+            Err(unsupported("FakeStreamRepository", "list_rules"))
+        }
+        async fn add_rules(&self, _rules: Vec<StreamRule>) -> Result<Vec<StreamRule>> {
+            Err(unsupported("FakeStreamRepository", "add_rules"))
+        }
+        async fn delete_rules(&self, _ids: Vec<String>) -> Result<usize> {
+            Err(unsupported("FakeStreamRepository", "delete_rules"))
+        }
+        async fn connect(&self) -> Result<TweetStream> {
+            let messages = self
+                .messages
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| unsupported("FakeStreamRepository", "connect twice"))?;
+            Ok(
+                futures::stream::unfold(messages, |mut messages| async move {
+                    let message = messages.recv().await?;
+                    Some((Ok(message), messages))
+                })
+                .boxed(),
+            )
+        }
+    }
+}
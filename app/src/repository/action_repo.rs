@@ -0,0 +1,121 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::RepositoryError;
+use crate::schema::actions;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = actions)]
+pub struct ActionRecord {
+    id: String,
+    kind: String,
+    tweet_id: String,
+    performed_at: String,
+    succeeded: bool,
+    error: Option<String>,
+}
+
+impl ActionRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<Action> {
+        let invalid = |err: anyhow::Error| ServiceError::new(RepositoryError::InvalidRecord, err);
+        Ok(Action {
+            kind: ActionKind::parse(&self.kind).map_err(|err| invalid(err.into_inner()))?,
+            performed_at: DateTime::parse_from_rfc3339(&self.performed_at)
+                .map_err(|err| invalid(err.into()))?
+                .with_timezone(&Utc),
+            id: self.id,
+            tweet_id: self.tweet_id,
+            // 失敗したのにエラーが無い記録は作らないが、念のため成否は列の方を信じる
+            error: match self.succeeded {
+                true => None,
+                false => Some(self.error.unwrap_or_default()),
+            },
+        })
+    }
+
+    pub fn from_model(action: Action) -> Self {
+        Self {
+            kind: action.kind.as_str().to_string(),
+            performed_at: to_created_at(&action.performed_at),
+            succeeded: action.succeeded(),
+            id: action.id,
+            tweet_id: action.tweet_id,
+            error: action.error,
+        }
+    }
+}
+
+pub struct ActionRepository {
+    db: DBConnector,
+}
+
+impl ActionRepository {
+    pub fn new(db: DBConnector) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl IActionRepository for ActionRepository {
+    async fn save(&self, action: Action) -> Result<()> {
+        self.db
+            .execute(
+                insert_into(actions::table)
+                    .values::<ActionRecord>(ActionRecord::from_model(action)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list(&self, kinds: &[ActionKind]) -> Result<Vec<Action>> {
+        let kinds = kinds.iter().map(|kind| kind.as_str()).collect::<Vec<_>>();
+        let records = self
+            .db
+            .load::<ActionRecord, _>(
+                actions::table
+                    .filter(actions::kind.eq_any(kinds))
+                    .order(actions::performed_at.asc()),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(ActionRecord::to_model)
+            .collect::<Result<Vec<Action>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::testing::memory_db;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn it_should_keep_failed_actions() {
+        let repo = ActionRepository::new(memory_db());
+        let at = |minute| Utc.with_ymd_and_hms(2022, 12, 5, 16, minute, 0).unwrap();
+        let liked = Action::new(ActionKind::Like, "1", at(1), &Ok(()));
+        let failed = Action::new(
+            ActionKind::Delete,
+            "2",
+            at(0),
+            &Err(ServiceError::only(TwitterApiError::Forbidden)),
+        );
+        repo.save(liked.clone()).await.unwrap();
+        repo.save(failed.clone()).await.unwrap();
+
+        assert_eq!(
+            repo.list(&[ActionKind::Like, ActionKind::Delete])
+                .await
+                .unwrap(),
+            vec![failed, liked.clone()]
+        );
+        assert_eq!(repo.list(&[ActionKind::Like]).await.unwrap(), vec![liked]);
+    }
+}
@@ -0,0 +1,513 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::repository::{read_body, status_error, ApiResponse, OAuth1Signer, RepositoryError};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use serde::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const AUTHORIZE_URL: &str = "https://twitter.com/i/oauth2/authorize";
+const TOKEN_URL: &str = "https://api.twitter.com/2/oauth2/token";
+const ME_URL: &str = "https://api.twitter.com/2/users/me";
+/// `offline.access` でリフレッシュトークンをもらう
+const SCOPES: &str = "tweet.read tweet.write users.read like.read like.write offline.access";
+
+/// `auth login` に使うアプリ
+#[derive(Clone, Debug)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+/// `/2/oauth2/token` の応答
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: i64,
+    #[serde(default)]
+    scope: String,
+}
+
+/// `/2/oauth2/token` の失敗。v2 の problem ではなく OAuth の形で返ってくる
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TokenErrorBody {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Me {
+    id: String,
+    username: String,
+}
+
+/// アプリの Bearer トークンと `auth login` で取ったユーザーのトークンを持ち、エンドポイントに合わせて使い分ける
+pub struct AuthRepository {
+    http_client: Arc<dyn IHttpClient + Sync + Send>,
+    bearer_token: String,
+    client: Option<OAuthClient>,
+    /// 設定されていればユーザーとしての呼び出しは OAuth 2.0 の代わりにこちらで署名する
+    oauth1: Option<OAuth1Signer>,
+    token_path: Option<PathBuf>,
+    token_url: String,
+    me_url: String,
+    /// 読み込んだトークン。リフレッシュトークンは一度しか使えないので、更新が重ならないようロックする
+    token: tokio::sync::Mutex<Option<OAuthToken>>,
+}
+
+impl AuthRepository {
+    pub fn new(
+        http_client: Arc<dyn IHttpClient + Sync + Send>,
+        bearer_token: String,
+        client: Option<OAuthClient>,
+        oauth1: Option<OAuth1Signer>,
+        token_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            http_client,
+            bearer_token,
+            client,
+            oauth1,
+            token_path,
+            token_url: TOKEN_URL.to_string(),
+            me_url: ME_URL.to_string(),
+            token: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// `method` と `url` のエンドポイントに合った `Authorization` 入りのヘッダー
+    pub async fn headers(&self, method: &str, url: &str) -> Result<reqwest::header::HeaderMap> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            self.authorization(method, url)
+                .await?
+                .parse()
+                .map_err(GeneralError::invalid_authority)?,
+        );
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+        Ok(headers)
+    }
+
+    /// ユーザーのトークンは期限が近ければリフレッシュしてから使う。OAuth 1.0a ならリクエストごとに署名する
+    pub async fn authorization(&self, method: &str, url: &str) -> Result<String> {
+        match (Auth::for_endpoint(&endpoint(method, url)), &self.oauth1) {
+            (Auth::AppOnly, _) => Ok(format!("Bearer {}", self.bearer_token)),
+            (Auth::User, Some(signer)) => signer.authorization(method, url, &[]),
+            (Auth::User, None) => Ok(format!("Bearer {}", self.user_token().await?.access_token)),
+        }
+    }
+
+    /// ユーザーとして呼んだリクエストの 401 は BEARER_TOKEN ではなくユーザーの資格情報の問題なので、
+    /// 見直すものを添えて `AuthError::TokenRejected` にする
+    pub fn check_rejected(&self, method: &str, url: &str, err: ServiceError) -> ServiceError {
+        let user = Auth::for_endpoint(&endpoint(method, url)) == Auth::User;
+        if !user || !err.is_error_of(TwitterApiError::Unauthorized) {
+            return err;
+        }
+        let hint = match &self.oauth1 {
+            Some(_) => "CONSUMER_KEY / CONSUMER_SECRET / ACCESS_TOKEN / ACCESS_TOKEN_SECRET を確認してください",
+            None => "`samuraicli auth login` でログインし直してください",
+        };
+        ServiceError::new(AuthError::TokenRejected, err.into_inner().context(hint))
+    }
+
+    /// ログインしたユーザーの ID
+    pub async fn user_id(&self) -> Result<String> {
+        match &self.oauth1 {
+            Some(signer) => signer
+                .credentials()
+                .user_id()
+                .map(String::from)
+                .ok_or_else(|| {
+                    ServiceError::new(
+                        AuthError::NotLoggedIn,
+                        anyhow::anyhow!("access_token からユーザー ID が分かりません"),
+                    )
+                }),
+            None => self.user_token().await?.user_id.ok_or_else(not_logged_in),
+        }
+    }
+
+    async fn user_token(&self) -> Result<OAuthToken> {
+        let mut cached = self.token.lock().await;
+        if cached.is_none() {
+            *cached = self.read_token()?;
+        }
+        let token = cached.clone().ok_or_else(not_logged_in)?;
+        if !token.is_expired(Utc::now()) {
+            return Ok(token);
+        }
+        let refresh_token = token.refresh_token.clone().ok_or_else(|| {
+            ServiceError::new(
+                AuthError::NotLoggedIn,
+                anyhow::anyhow!("トークンの期限が切れました。`samuraicli auth login` でログインし直してください"),
+            )
+        })?;
+        let mut refreshed = self
+            .request_token(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ])
+            .await?;
+        // 新しいリフレッシュトークンが来なければ前のものを使い続ける
+        if refreshed.refresh_token.is_none() {
+            refreshed.refresh_token = Some(refresh_token);
+        }
+        refreshed.user_id = token.user_id;
+        refreshed.username = token.username;
+        self.write_token(&refreshed)?;
+        *cached = Some(refreshed.clone());
+        Ok(refreshed)
+    }
+
+    fn client(&self) -> Result<&OAuthClient> {
+        self.client.as_ref().ok_or_else(|| {
+            ServiceError::new(
+                AuthError::MissingClientId,
+                anyhow::anyhow!("config.toml の client_id か CLIENT_ID を設定してください"),
+            )
+        })
+    }
+
+    /// confidential client は Basic 認証、public client は `client_id` をボディに入れる
+    fn token_request(
+        &self,
+        params: &[(&str, &str)],
+    ) -> Result<(reqwest::header::HeaderMap, String)> {
+        let client = self.client()?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.extend_pairs(params);
+        match &client.client_secret {
+            Some(secret) => {
+                let basic = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", client.client_id, secret));
+                headers.insert(
+                    reqwest::header::AUTHORIZATION,
+                    format!("Basic {}", basic)
+                        .parse()
+                        .map_err(GeneralError::invalid_authority)?,
+                );
+            }
+            None => {
+                form.append_pair("client_id", &client.client_id);
+            }
+        }
+        Ok((headers, form.finish()))
+    }
+
+    async fn request_token(&self, params: &[(&str, &str)]) -> Result<OAuthToken> {
+        let (headers, body) = self.token_request(params)?;
+        let response = self
+            .http_client
+            .post(&self.token_url, Some(headers), Some(body))
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(token_error(status, &body));
+        }
+        let token: TokenResponse = serde_json::from_str(&body)
+            .map_err(|err| ServiceError::new(RepositoryError::SerializationError, err))?;
+        Ok(OAuthToken {
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: Utc::now() + Duration::seconds(token.expires_in),
+            scope: token.scope,
+            user_id: None,
+            username: None,
+        })
+    }
+
+    fn read_token(&self) -> Result<Option<OAuthToken>> {
+        let Some(path) = self.token_path.as_ref().filter(|path| path.exists()) else {
+            return Ok(None);
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err))
+    }
+
+    /// 自分だけが読めるように書く
+    fn write_token(&self, token: &OAuthToken) -> Result<()> {
+        use std::io::Write;
+
+        let path = self.token_path.as_ref().ok_or_else(|| {
+            ServiceError::new(
+                RepositoryError::InvalidRecord,
+                anyhow::anyhow!("no data directory to store the token"),
+            )
+        })?;
+        let content = serde_json::to_string_pretty(token)
+            .map_err(|err| ServiceError::new(RepositoryError::SerializationError, err))?;
+        let io_error = |err| ServiceError::new(RepositoryError::InvalidRecord, err);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path).map_err(io_error)?;
+        file.write_all(content.as_bytes()).map_err(io_error)?;
+        Ok(())
+    }
+}
+
+fn not_logged_in() -> ServiceError {
+    ServiceError::new(
+        AuthError::NotLoggedIn,
+        anyhow::anyhow!("`samuraicli auth login` でログインしてください"),
+    )
+}
+
+/// `invalid_grant` などはトークンが使えなくなったとき。それ以外は API のエラーとして扱う
+fn token_error(status: http::StatusCode, body: &str) -> ServiceError {
+    let error = serde_json::from_str::<TokenErrorBody>(body).unwrap_or_default();
+    if error.error.is_empty() {
+        return status_error(status, None, body);
+    }
+    let message = match error.error_description {
+        Some(description) => format!("{}: {}", error.error, description),
+        None => error.error,
+    };
+    ServiceError::new(
+        AuthError::TokenRejected,
+        anyhow::anyhow!(
+            "{}. `samuraicli auth login` でログインし直してください",
+            message
+        ),
+    )
+}
+
+#[async_trait]
+impl IAuthRepository for AuthRepository {
+    fn authorize_url(&self, redirect_uri: &str, state: &str, pkce: &Pkce) -> Result<String> {
+        let url = url::Url::parse_with_params(
+            AUTHORIZE_URL,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client()?.client_id),
+                ("redirect_uri", redirect_uri),
+                ("scope", SCOPES),
+                ("state", state),
+                ("code_challenge", &pkce.challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(GeneralError::serialization_error)?;
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        pkce: &Pkce,
+    ) -> Result<OAuthToken> {
+        let mut token = self
+            .request_token(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", &pkce.verifier),
+            ])
+            .await?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", token.access_token)
+                .parse()
+                .map_err(GeneralError::invalid_authority)?,
+        );
+        let response = self.http_client.get(&self.me_url, Some(headers)).await?;
+        let body = read_body(response).await?;
+        let (me, _, _) = ApiResponse::<Me>::parse(&body)?.into_data()?;
+        if let Some(me) = me {
+            token.user_id = Some(me.id);
+            token.username = Some(me.username);
+        }
+        Ok(token)
+    }
+
+    async fn load_token(&self) -> Result<Option<OAuthToken>> {
+        let token = self.read_token()?;
+        *self.token.lock().await = token.clone();
+        Ok(token)
+    }
+
+    async fn save_token(&self, token: &OAuthToken) -> Result<()> {
+        self.write_token(token)?;
+        *self.token.lock().await = Some(token.clone());
+        Ok(())
+    }
+
+    async fn delete_token(&self) -> Result<()> {
+        if let Some(path) = self.token_path.as_ref().filter(|path| path.exists()) {
+            std::fs::remove_file(path)
+                .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err))?;
+        }
+        *self.token.lock().await = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::HttpClient;
+    use crate::repository::testing::serve;
+    use crate::repository::OAuth1Credentials;
+
+    const LIKE_URL: &str = "https://api.twitter.com/2/users/42/likes";
+    const SEARCH_URL: &str = "https://api.twitter.com/2/tweets/search/recent?query=JPN";
+
+    fn repo(token_url: String, token_path: PathBuf) -> AuthRepository {
+        let mut repo = AuthRepository::new(
+            Arc::new(HttpClient::new(RateLimits::default())),
+            "app-token".to_string(),
+            Some(OAuthClient {
+                client_id: "client".to_string(),
+                client_secret: None,
+            }),
+            None,
+            Some(token_path),
+        );
+        repo.token_url = token_url;
+        repo
+    }
+
+    fn token_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("samuraicup-test-{}", uuid::Uuid::new_v4()))
+            .join("token.json")
+    }
+
+    #[tokio::test]
+    async fn it_should_rotate_expired_token() {
+        let url = serve(
+            200,
+            "application/json",
+            br#"{"token_type":"bearer","expires_in":7200,"access_token":"new-access","scope":"tweet.read","refresh_token":"new-refresh"}"#.to_vec(),
+        )
+        .await;
+        let path = token_path();
+        let repo = repo(url, path.clone());
+        assert!(repo
+            .authorization("POST", LIKE_URL)
+            .await
+            .unwrap_err()
+            .is_error_of(AuthError::NotLoggedIn));
+
+        repo.save_token(&OAuthToken {
+            access_token: "old-access".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: Utc::now(),
+            scope: "tweet.read".to_string(),
+            user_id: Some("42".to_string()),
+            username: Some("samurai".to_string()),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            repo.authorization("GET", SEARCH_URL).await.unwrap(),
+            "Bearer app-token"
+        );
+        assert_eq!(
+            repo.authorization("POST", LIKE_URL).await.unwrap(),
+            "Bearer new-access"
+        );
+        // 新しいリフレッシュトークンが保存され、ユーザーは引き継ぐ
+        let saved = repo.load_token().await.unwrap().unwrap();
+        assert_eq!(saved.refresh_token.as_deref(), Some("new-refresh"));
+        assert_eq!(saved.user_id.as_deref(), Some("42"));
+        assert!(!saved.is_expired(Utc::now()));
+
+        repo.delete_token().await.unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn it_should_reject_revoked_refresh_token() {
+        let url = serve(
+            400,
+            "application/json",
+            br#"{"error":"invalid_request","error_description":"Value passed for the token was invalid."}"#.to_vec(),
+        )
+        .await;
+        let repo = repo(url, token_path());
+        repo.save_token(&OAuthToken {
+            access_token: "old-access".to_string(),
+            refresh_token: Some("old-refresh".to_string()),
+            expires_at: Utc::now(),
+            scope: String::new(),
+            user_id: None,
+            username: None,
+        })
+        .await
+        .unwrap();
+        let err = repo.authorization("POST", LIKE_URL).await.unwrap_err();
+        assert!(err.is_error_of(AuthError::TokenRejected));
+    }
+
+    #[tokio::test]
+    async fn it_should_sign_user_requests_with_oauth1() {
+        let repo = AuthRepository::new(
+            Arc::new(HttpClient::new(RateLimits::default())),
+            "app-token".to_string(),
+            None,
+            Some(OAuth1Signer::new(OAuth1Credentials {
+                consumer_key: "key".to_string(),
+                consumer_secret: "secret".to_string(),
+                access_token: "42-token".to_string(),
+                access_token_secret: "token-secret".to_string(),
+            })),
+            None,
+        );
+        assert!(repo
+            .authorization("DELETE", "https://api.twitter.com/2/tweets/1")
+            .await
+            .unwrap()
+            .starts_with("OAuth oauth_consumer_key=\"key\""));
+        assert_eq!(
+            repo.authorization("GET", SEARCH_URL).await.unwrap(),
+            "Bearer app-token"
+        );
+        assert_eq!(repo.user_id().await.unwrap(), "42");
+    }
+
+    #[test]
+    fn it_should_blame_user_credentials_for_user_context_401() {
+        let repo = AuthRepository::new(
+            Arc::new(HttpClient::new(RateLimits::default())),
+            "app-token".to_string(),
+            None,
+            None,
+            None,
+        );
+        let unauthorized = || status_error(http::StatusCode::UNAUTHORIZED, None, "");
+
+        let err = repo.check_rejected("GET", SEARCH_URL, unauthorized());
+        assert!(err.is_error_of(TwitterApiError::Unauthorized));
+        let err = repo.check_rejected("POST", LIKE_URL, unauthorized());
+        assert!(err.is_error_of(AuthError::TokenRejected));
+        assert!(err.to_string().contains("`samuraicli auth login`"));
+    }
+}
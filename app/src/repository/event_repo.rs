@@ -0,0 +1,130 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::RepositoryError;
+use crate::schema::match_events;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = match_events)]
+pub struct MatchEventRecord {
+    id: String,
+    kind: String,
+    detected_at: String,
+    query: String,
+    tweets_per_minute: f64,
+    baseline: f64,
+    score: f64,
+    keyword_hits: i32,
+    sample_tweet_id: Option<String>,
+}
+
+impl MatchEventRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<MatchEvent> {
+        let invalid = |err: anyhow::Error| ServiceError::new(RepositoryError::InvalidRecord, err);
+        Ok(MatchEvent {
+            kind: MatchEventKind::parse(&self.kind).map_err(|err| invalid(err.into_inner()))?,
+            detected_at: DateTime::parse_from_rfc3339(&self.detected_at)
+                .map_err(|err| invalid(err.into()))?
+                .with_timezone(&Utc),
+            id: self.id,
+            query: self.query,
+            tweets_per_minute: self.tweets_per_minute,
+            baseline: self.baseline,
+            score: self.score,
+            keyword_hits: self.keyword_hits as usize,
+            sample_tweet_id: self.sample_tweet_id,
+        })
+    }
+
+    pub fn from_model(event: MatchEvent) -> Self {
+        Self {
+            kind: event.kind.as_str().to_string(),
+            detected_at: to_created_at(&event.detected_at),
+            id: event.id,
+            query: event.query,
+            tweets_per_minute: event.tweets_per_minute,
+            baseline: event.baseline,
+            score: event.score,
+            keyword_hits: event.keyword_hits as i32,
+            sample_tweet_id: event.sample_tweet_id,
+        }
+    }
+}
+
+pub struct EventRepository {
+    db: DBConnector,
+}
+
+impl EventRepository {
+    pub fn new(db: DBConnector) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl IEventRepository for EventRepository {
+    async fn save(&self, event: MatchEvent) -> Result<()> {
+        self.db
+            .execute(
+                replace_into(match_events::table)
+                    .values::<MatchEventRecord>(MatchEventRecord::from_model(event)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list(&self, limit: i64) -> Result<Vec<MatchEvent>> {
+        let records = self
+            .db
+            .load::<MatchEventRecord, _>(
+                match_events::table
+                    .order(match_events::detected_at.desc())
+                    .limit(limit),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<MatchEvent>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::testing::memory_db;
+    use chrono::TimeZone;
+
+    fn event(id: &str, minute: u32) -> MatchEvent {
+        MatchEvent {
+            id: id.to_string(),
+            kind: MatchEventKind::Equalizer,
+            detected_at: Utc.with_ymd_and_hms(2022, 12, 5, 15, minute, 0).unwrap(),
+            query: "#JPNCRO".to_string(),
+            tweets_per_minute: 120.0,
+            baseline: 15.5,
+            score: 6.25,
+            keyword_hits: 30,
+            sample_tweet_id: Some("1599771355208675328".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_list_newest_first() {
+        let repo = EventRepository::new(memory_db());
+        repo.save(event("a", 43)).await.unwrap();
+        repo.save(event("b", 55)).await.unwrap();
+
+        assert_eq!(
+            repo.list(10).await.unwrap(),
+            vec![event("b", 55), event("a", 43)]
+        );
+        assert_eq!(repo.list(1).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::RepositoryError;
+use crate::schema::fixtures;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = fixtures)]
+pub struct FixtureRecord {
+    id: String,
+    stage: String,
+    home_code: String,
+    home_name: String,
+    home_hashtags: String,
+    away_code: String,
+    away_name: String,
+    away_hashtags: String,
+    kickoff: String,
+    venue: Option<String>,
+}
+
+impl FixtureRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<Match> {
+        let invalid = |err: anyhow::Error| ServiceError::new(RepositoryError::InvalidRecord, err);
+        let team = |code: String, name: String, hashtags: &str| -> Result<Team> {
+            Ok(Team {
+                code,
+                name,
+                hashtags: serde_json::from_str(hashtags).map_err(|err| invalid(err.into()))?,
+            })
+        };
+        Ok(Match {
+            stage: Stage::parse(&self.stage).map_err(|err| invalid(err.into_inner()))?,
+            home: team(self.home_code, self.home_name, &self.home_hashtags)?,
+            away: team(self.away_code, self.away_name, &self.away_hashtags)?,
+            kickoff: DateTime::parse_from_rfc3339(&self.kickoff)
+                .map_err(|err| invalid(err.into()))?
+                .with_timezone(&Utc),
+            id: self.id,
+            venue: self.venue,
+        })
+    }
+
+    pub fn from_model(game: Match) -> Result<Self> {
+        let hashtags = |team: &Team| {
+            serde_json::to_string(&team.hashtags)
+                .map_err(|err| ServiceError::new(RepositoryError::SerializationError, err))
+        };
+        Ok(Self {
+            stage: game.stage.as_str(),
+            home_hashtags: hashtags(&game.home)?,
+            away_hashtags: hashtags(&game.away)?,
+            home_code: game.home.code,
+            home_name: game.home.name,
+            away_code: game.away.code,
+            away_name: game.away.name,
+            // 文字列のまま並べ替えられるように UTC で揃える
+            kickoff: to_created_at(&game.kickoff),
+            id: game.id,
+            venue: game.venue,
+        })
+    }
+}
+
+pub struct FixtureRepository {
+    db: DBConnector,
+}
+
+impl FixtureRepository {
+    pub fn new(db: DBConnector) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl IFixtureRepository for FixtureRepository {
+    async fn save_matches(&self, matches: Vec<Match>) -> Result<()> {
+        let records = matches
+            .into_iter()
+            .map(FixtureRecord::from_model)
+            .collect::<Result<Vec<FixtureRecord>>>()?;
+        for record in records {
+            self.db
+                .execute(replace_into(fixtures::table).values::<FixtureRecord>(record))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<Match>> {
+        let records = self
+            .db
+            .load::<FixtureRecord, _>(
+                fixtures::table.order((fixtures::kickoff.asc(), fixtures::id.asc())),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<Match>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{Fixtures, Format, BUNDLED};
+    use crate::repository::testing::memory_db;
+
+    #[tokio::test]
+    async fn it_should_round_trip_matches() {
+        let mut fixtures = Fixtures::default();
+        fixtures.add(BUNDLED, Format::Toml).unwrap();
+        let mut matches = fixtures.into_matches();
+        matches.reverse();
+
+        let repo = FixtureRepository::new(memory_db());
+        repo.save_matches(matches.clone()).await.unwrap();
+        repo.save_matches(matches[..1].to_vec()).await.unwrap();
+
+        matches.reverse();
+        assert_eq!(repo.list().await.unwrap(), matches);
+    }
+}
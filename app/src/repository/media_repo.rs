@@ -0,0 +1,166 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::read_bytes;
+use crate::schema::media_records;
+use async_trait::async_trait;
+use diesel::dsl::*;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = media_records, primary_key(media_key))]
+pub struct MediaRecord {
+    media_key: String,
+    tweet_id: String,
+    /// `attachments.media_keys` での順番
+    position: i32,
+    media_type: String,
+    url: Option<String>,
+    preview_image_url: Option<String>,
+    duration_ms: Option<i64>,
+    width: Option<i32>,
+    height: Option<i32>,
+    alt_text: Option<String>,
+}
+
+impl MediaRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> (String, Media) {
+        let media = Media {
+            media_key: self.media_key,
+            media_type: self.media_type,
+            url: self.url,
+            preview_image_url: self.preview_image_url,
+            duration_ms: self.duration_ms.map(|it| it as u64),
+            width: self.width.map(|it| it as u32),
+            height: self.height.map(|it| it as u32),
+            alt_text: self.alt_text,
+        };
+        (self.tweet_id, media)
+    }
+
+    pub fn from_model(tweet_id: &str, position: usize, media: Media) -> Self {
+        Self {
+            media_key: media.media_key,
+            tweet_id: tweet_id.to_string(),
+            position: position as i32,
+            media_type: media.media_type,
+            url: media.url,
+            preview_image_url: media.preview_image_url,
+            duration_ms: media.duration_ms.map(|it| it as i64),
+            width: media.width.map(|it| it as i32),
+            height: media.height.map(|it| it as i32),
+            alt_text: media.alt_text,
+        }
+    }
+}
+
+pub struct MediaRepository {
+    db: DBConnector,
+    http_client: Arc<dyn IHttpClient + Sync + Send>,
+}
+
+impl MediaRepository {
+    pub fn new(db: DBConnector, http_client: Arc<dyn IHttpClient + Sync + Send>) -> Self {
+        Self { db, http_client }
+    }
+}
+
+#[async_trait]
+impl IMediaRepository for MediaRepository {
+    async fn save_media(&self, tweet_id: &TweetID, media: Vec<Media>) -> Result<()> {
+        for (position, media) in media.into_iter().enumerate() {
+            self.db
+                .execute(
+                    replace_into(media_records::table).values::<MediaRecord>(
+                        MediaRecord::from_model(&tweet_id.0, position, media),
+                    ),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn find_by_tweet_ids(&self, ids: &[String]) -> Result<Vec<(String, Media)>> {
+        let records = self
+            .db
+            .load::<MediaRecord, _>(
+                media_records::table
+                    .filter(media_records::tweet_id.eq_any(ids.to_vec()))
+                    .order((media_records::tweet_id, media_records::position)),
+            )
+            .await?;
+        Ok(records.into_iter().map(MediaRecord::to_model).collect())
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.http_client.get(url, None).await?;
+        read_bytes(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::HttpClient;
+    use crate::repository::testing::{memory_db, serve};
+
+    fn repo() -> MediaRepository {
+        MediaRepository::new(
+            memory_db(),
+            Arc::new(HttpClient::new(RateLimits::default())),
+        )
+    }
+
+    fn photo(key: &str) -> Media {
+        Media {
+            media_key: key.to_string(),
+            media_type: "photo".to_string(),
+            url: Some(format!("https://pbs.twimg.com/media/{}.jpg", key)),
+            preview_image_url: None,
+            duration_ms: None,
+            width: Some(1200),
+            height: Some(675),
+            alt_text: Some("三笘の1ミリ".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_attachment_order() {
+        let repo = repo();
+        repo.save_media(&TweetID("1".to_string()), vec![photo("b"), photo("a")])
+            .await
+            .unwrap();
+        repo.save_media(&TweetID("2".to_string()), vec![photo("c")])
+            .await
+            .unwrap();
+
+        let media = repo.find_by_tweet_ids(&["1".to_string()]).await.unwrap();
+        assert_eq!(
+            media,
+            vec![("1".to_string(), photo("b")), ("1".to_string(), photo("a"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_should_download_from_server() {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(4, 2, image::Rgb([188, 0, 45]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let url = serve(200, "image/png", png.clone()).await;
+
+        let downloaded = repo().download(&url).await.unwrap();
+        assert_eq!(downloaded, png);
+        let image = image::load_from_memory(&downloaded).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+
+        let url = serve(404, "text/plain", b"Not Found".to_vec()).await;
+        assert!(repo().download(&url).await.is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::RepositoryError;
+use crate::schema::tweet_metric_snapshots;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = tweet_metric_snapshots, primary_key(tweet_id, observed_at))]
+pub struct MetricSnapshotRecord {
+    tweet_id: String,
+    observed_at: String,
+    like_count: i64,
+    retweet_count: i64,
+    reply_count: i64,
+    quote_count: i64,
+}
+
+impl MetricSnapshotRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<MetricSnapshot> {
+        Ok(MetricSnapshot {
+            observed_at: DateTime::parse_from_rfc3339(&self.observed_at)
+                .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err))?
+                .with_timezone(&Utc),
+            tweet_id: self.tweet_id,
+            metrics: PublicMetrics {
+                like_count: self.like_count as u64,
+                retweet_count: self.retweet_count as u64,
+                reply_count: self.reply_count as u64,
+                quote_count: self.quote_count as u64,
+            },
+        })
+    }
+
+    pub fn from_model(snapshot: MetricSnapshot) -> Self {
+        Self {
+            tweet_id: snapshot.tweet_id,
+            observed_at: to_created_at(&snapshot.observed_at),
+            like_count: snapshot.metrics.like_count as i64,
+            retweet_count: snapshot.metrics.retweet_count as i64,
+            reply_count: snapshot.metrics.reply_count as i64,
+            quote_count: snapshot.metrics.quote_count as i64,
+        }
+    }
+}
+
+pub struct MetricRepository {
+    db: DBConnector,
+}
+
+impl MetricRepository {
+    pub fn new(db: DBConnector) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl IMetricRepository for MetricRepository {
+    async fn save_snapshots(&self, snapshots: Vec<MetricSnapshot>) -> Result<()> {
+        for snapshot in snapshots {
+            self.db
+                .execute(
+                    replace_into(tweet_metric_snapshots::table)
+                        .values::<MetricSnapshotRecord>(MetricSnapshotRecord::from_model(snapshot)),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn find_since(&self, since: &DateTime<Utc>) -> Result<Vec<MetricSnapshot>> {
+        let records = self
+            .db
+            .load::<MetricSnapshotRecord, _>(
+                tweet_metric_snapshots::table
+                    .filter(tweet_metric_snapshots::observed_at.ge(to_created_at(since)))
+                    .order((
+                        tweet_metric_snapshots::tweet_id,
+                        tweet_metric_snapshots::observed_at,
+                    )),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(MetricSnapshotRecord::to_model)
+            .collect::<Result<Vec<MetricSnapshot>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::testing::memory_db;
+    use chrono::TimeZone;
+
+    fn snapshot(tweet_id: &str, minute: u32, likes: u64) -> MetricSnapshot {
+        MetricSnapshot {
+            tweet_id: tweet_id.to_string(),
+            observed_at: Utc.with_ymd_and_hms(2022, 12, 5, 16, minute, 0).unwrap(),
+            metrics: PublicMetrics {
+                like_count: likes,
+                retweet_count: 2,
+                reply_count: 1,
+                quote_count: 0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_find_snapshots_in_window() {
+        let repo = MetricRepository::new(memory_db());
+        repo.save_snapshots(vec![
+            snapshot("1", 0, 10),
+            snapshot("1", 20, 30),
+            snapshot("2", 10, 5),
+        ])
+        .await
+        .unwrap();
+        // 同じ時刻に見たものは置き換える
+        repo.save_snapshots(vec![snapshot("1", 20, 40)])
+            .await
+            .unwrap();
+
+        let since = Utc.with_ymd_and_hms(2022, 12, 5, 16, 10, 0).unwrap();
+        assert_eq!(
+            repo.find_since(&since).await.unwrap(),
+            vec![snapshot("1", 20, 40), snapshot("2", 10, 5)]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::*;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha1::Sha1;
+
+/// RFC 3986 の unreserved 以外はすべてエンコードする
+const OAUTH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// OAuth 1.0a のコンシューマーキーとアクセストークンの組
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OAuth1Credentials {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub access_token: String,
+    pub access_token_secret: String,
+}
+
+impl OAuth1Credentials {
+    /// Twitter のアクセストークンは `ユーザーID-...` の形
+    pub fn user_id(&self) -> Option<&str> {
+        self.access_token
+            .split_once('-')
+            .map(|(id, _)| id)
+            .filter(|id| !id.is_empty() && id.bytes().all(|it| it.is_ascii_digit()))
+    }
+}
+
+/// HMAC-SHA1 で署名して `Authorization: OAuth ...` を作る
+#[derive(Clone, Debug)]
+pub struct OAuth1Signer {
+    credentials: OAuth1Credentials,
+}
+
+impl OAuth1Signer {
+    pub fn new(credentials: OAuth1Credentials) -> Self {
+        Self { credentials }
+    }
+
+    pub fn credentials(&self) -> &OAuth1Credentials {
+        &self.credentials
+    }
+
+    /// `form` は `application/x-www-form-urlencoded` のボディの値。JSON のボディは署名に含めない
+    pub fn authorization(&self, method: &str, url: &str, form: &[(&str, &str)]) -> Result<String> {
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        self.authorization_with(method, url, form, &nonce, &timestamp)
+    }
+
+    fn authorization_with(
+        &self,
+        method: &str,
+        url: &str,
+        form: &[(&str, &str)],
+        nonce: &str,
+        timestamp: &str,
+    ) -> Result<String> {
+        let mut oauth = vec![
+            ("oauth_consumer_key", self.credentials.consumer_key.as_str()),
+            ("oauth_nonce", nonce),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_timestamp", timestamp),
+            ("oauth_token", self.credentials.access_token.as_str()),
+            ("oauth_version", "1.0"),
+        ];
+        let signature = self.signature(method, url, form, &oauth)?;
+        oauth.push(("oauth_signature", &signature));
+        oauth.sort();
+        let params = oauth
+            .into_iter()
+            .map(|(key, value)| format!("{}=\"{}\"", encode(key), encode(value)))
+            .collect::<Vec<_>>();
+        Ok(format!("OAuth {}", params.join(", ")))
+    }
+
+    fn signature(
+        &self,
+        method: &str,
+        url: &str,
+        form: &[(&str, &str)],
+        oauth: &[(&str, &str)],
+    ) -> Result<String> {
+        let url = url::Url::parse(url).map_err(GeneralError::serialization_error)?;
+        // クエリとボディと oauth_* をエンコードしてから並べる
+        let mut params = url
+            .query_pairs()
+            .map(|(key, value)| (encode(&key), encode(&value)))
+            .chain(
+                form.iter()
+                    .chain(oauth)
+                    .map(|(key, value)| (encode(key), encode(value))),
+            )
+            .collect::<Vec<_>>();
+        params.sort();
+        let params = params
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut base_url = url.clone();
+        base_url.set_query(None);
+        base_url.set_fragment(None);
+        let base = format!(
+            "{}&{}&{}",
+            method.to_uppercase(),
+            encode(base_url.as_str()),
+            encode(&params)
+        );
+        let key = format!(
+            "{}&{}",
+            encode(&self.credentials.consumer_secret),
+            encode(&self.credentials.access_token_secret)
+        );
+        let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes())
+            .map_err(GeneralError::invalid_authority)?;
+        mac.update(base.as_bytes());
+        Ok(base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+    }
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, OAUTH_ENCODE_SET).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_match_twitter_signature_example() {
+        // https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
+        let signer = OAuth1Signer::new(OAuth1Credentials {
+            consumer_key: "xvz1evFS4wEEPTGEFPHBog".to_string(),
+            consumer_secret: "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw".to_string(),
+            access_token: "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb".to_string(),
+            access_token_secret: "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE".to_string(),
+        });
+        let header = signer
+            .authorization_with(
+                "post",
+                "https://api.twitter.com/1.1/statuses/update.json?include_entities=true",
+                &[(
+                    "status",
+                    "Hello Ladies + Gentlemen, a signed OAuth request!",
+                )],
+                "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+                "1318622958",
+            )
+            .unwrap();
+        assert_eq!(
+            header,
+            "OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", \
+             oauth_nonce=\"kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg\", \
+             oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\", \
+             oauth_signature_method=\"HMAC-SHA1\", \
+             oauth_timestamp=\"1318622958\", \
+             oauth_token=\"370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb\", \
+             oauth_version=\"1.0\""
+        );
+        assert_eq!(signer.credentials().user_id(), Some("370773112"));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::repository::{
+    check_status, fields_param, partial_errors, read_body, with_includes, ApiResponse,
+    AuthRepository, RepositoryError,
+};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::*;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SEARCH_STREAM_URL: &str = "https://api.twitter.com/2/tweets/search/stream";
+/// keep-alive の改行は 20 秒ごとに届く。これだけ何も来なければ止まったとみなす
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// チャンクの区切りに関係なく、改行までそろった行を取り出す
+#[derive(Default)]
+pub struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// 空行 (keep-alive) は含まない
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let Some(end) = self.pending.iter().rposition(|it| *it == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.pending.split_off(end + 1);
+        let lines = std::mem::replace(&mut self.pending, rest);
+        String::from_utf8_lossy(&lines)
+            .split('\n')
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct StreamEnvelope {
+    matching_rules: Vec<MatchingRule>,
+}
+
+/// 1 行分のツイートと、その後に部分エラー。読めない行も接続は切らずに `Problem` にする
+fn parse_line(line: &str) -> Vec<Result<StreamMessage>> {
+    match parse_tweet(line) {
+        Ok((tweet, errors)) => std::iter::once(StreamMessage::Tweet(Box::new(tweet)))
+            .chain(errors.into_iter().map(StreamMessage::Problem))
+            .map(Ok)
+            .collect(),
+        Err(err) => vec![Ok(StreamMessage::Problem(err))],
+    }
+}
+
+fn parse_tweet(line: &str) -> Result<(Tweet, Vec<ServiceError>)> {
+    let mut response = ApiResponse::<Tweet>::parse(line)?;
+    let includes = std::mem::take(&mut response.includes);
+    // 接続を切られる前は errors だけが届く
+    let (tweet, _, problems) = response.into_data()?;
+    let mut tweet = with_includes(tweet.into_iter().collect(), &includes)
+        .pop()
+        .ok_or_else(|| {
+            ServiceError::new(
+                RepositoryError::InvalidRecord,
+                anyhow::anyhow!("unexpected stream message: {}", line),
+            )
+        })?;
+    tweet.matching_rules = serde_json::from_str::<StreamEnvelope>(line)
+        .unwrap_or_default()
+        .matching_rules;
+    Ok((tweet, partial_errors(problems)))
+}
+
+fn disconnected(message: &str) -> ServiceError {
+    ServiceError::new(
+        TwitterApiError::ServiceUnavailable,
+        anyhow::anyhow!("stream {}", message),
+    )
+}
+
+#[derive(Deserialize)]
+struct RuleMeta {
+    summary: Option<RuleSummary>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RuleSummary {
+    deleted: usize,
+}
+
+pub struct StreamRepository {
+    http_client: Arc<dyn IHttpClient + Sync + Send>,
+    auth: Arc<AuthRepository>,
+    stream_url: String,
+}
+
+impl StreamRepository {
+    pub fn new(http_client: Arc<dyn IHttpClient + Sync + Send>, auth: Arc<AuthRepository>) -> Self {
+        Self {
+            http_client,
+            auth,
+            stream_url: SEARCH_STREAM_URL.to_string(),
+        }
+    }
+
+    async fn post_rules(&self, body: serde_json::Value) -> Result<String> {
+        let uri = format!("{}/rules", self.stream_url);
+        let headers = self.auth.headers("POST", &uri).await?;
+        let response = self
+            .http_client
+            .post(&uri, Some(headers), Some(body.to_string()))
+            .await?;
+        read_body(response).await
+    }
+}
+
+#[async_trait]
+impl IStreamRepository for StreamRepository {
+    async fn list_rules(&self) -> Result<Vec<StreamRule>> {
+        let uri = format!("{}/rules", self.stream_url);
+        let headers = self.auth.headers("GET", &uri).await?;
+        let response = self.http_client.get(&uri, Some(headers)).await?;
+        let body = read_body(response).await?;
+        // ルールが無いときは data が無い
+        let (rules, _, _) = ApiResponse::<Vec<StreamRule>>::parse(&body)?.into_data()?;
+        Ok(rules.unwrap_or_default())
+    }
+
+    async fn add_rules(&self, rules: Vec<StreamRule>) -> Result<Vec<StreamRule>> {
+        let body = self.post_rules(serde_json::json!({ "add": rules })).await?;
+        let (rules, _, _) = ApiResponse::<Vec<StreamRule>>::parse(&body)?.into_data()?;
+        Ok(rules.unwrap_or_default())
+    }
+
+    async fn delete_rules(&self, ids: Vec<String>) -> Result<usize> {
+        let body = self
+            .post_rules(serde_json::json!({ "delete": { "ids": ids } }))
+            .await?;
+        let (_, meta, _) = ApiResponse::<serde_json::Value, RuleMeta>::parse(&body)?.into_data()?;
+        Ok(meta
+            .and_then(|meta| meta.summary)
+            .unwrap_or_default()
+            .deleted)
+    }
+
+    async fn connect(&self) -> Result<TweetStream> {
+        let uri = format!("{}?{}", self.stream_url, fields_param());
+        let headers = self.auth.headers("GET", &uri).await?;
+        let response = self.http_client.get(&uri, Some(headers)).await?;
+        let response = check_status(response).await?;
+
+        struct State {
+            response: Option<reqwest::Response>,
+            lines: LineBuffer,
+            ready: VecDeque<Result<StreamMessage>>,
+        }
+        let state = State {
+            response: Some(response),
+            lines: LineBuffer::default(),
+            ready: VecDeque::new(),
+        };
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.ready.pop_front() {
+                    return Some((item, state));
+                }
+                let response = state.response.as_mut()?;
+                match tokio::time::timeout(STALL_TIMEOUT, response.chunk()).await {
+                    Ok(Ok(Some(chunk))) => {
+                        let lines = state.lines.push(&chunk);
+                        state
+                            .ready
+                            .extend(lines.iter().flat_map(|line| parse_line(line)));
+                    }
+                    Ok(Ok(None)) => {
+                        state.response = None;
+                        state.ready.push_back(Err(disconnected("closed")));
+                    }
+                    Ok(Err(err)) => {
+                        state.response = None;
+                        state.ready.push_back(Err(err.into()));
+                    }
+                    Err(_) => {
+                        state.response = None;
+                        state.ready.push_back(Err(disconnected("stalled")));
+                    }
+                }
+            }
+        })
+        .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::{HttpClient, HttpClientError};
+    use crate::repository::testing::{auth_repository, serve_chunks};
+
+    #[test]
+    fn it_should_split_lines_across_chunks() {
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(b"\r\n").is_empty());
+        let goal = "{\"data\":{\"text\":\"ゴール\"}}".as_bytes();
+        // マルチバイト文字の途中で切れても行がそろってから読む
+        assert!(lines.push(&goal[..20]).is_empty());
+        assert_eq!(
+            lines.push(&[&goal[20..], b"\r\n\r\n{\"a\"".as_slice()].concat()),
+            vec!["{\"data\":{\"text\":\"ゴール\"}}".to_string()]
+        );
+        assert_eq!(lines.push(b":1}\n"), vec!["{\"a\":1}".to_string()]);
+    }
+
+    #[test]
+    fn it_should_keep_partial_errors_after_tweet() {
+        let line = r#"{"data":{"id":"1","text":"ゴール!","author_id":"10","created_at":"2022-12-05T15:43:00.000Z"},"errors":[{"title":"Not Found Error","detail":"Could not find user with id: [10].","type":"https://api.twitter.com/2/problems/resource-not-found"}]}"#;
+        let mut messages = parse_line(line).into_iter();
+        assert!(matches!(messages.next(), Some(Ok(StreamMessage::Tweet(_)))));
+        let Some(Ok(StreamMessage::Problem(err))) = messages.next() else {
+            panic!("expected a partial error");
+        };
+        assert!(err.is_error_of(TwitterApiError::NotFound));
+        assert!(messages.next().is_none());
+    }
+
+    #[test]
+    fn it_should_report_unreadable_line_as_problem() {
+        let mut messages = parse_line("{\"data\":").into_iter();
+        let Some(Ok(StreamMessage::Problem(err))) = messages.next() else {
+            panic!("expected a problem");
+        };
+        assert!(err.is_error_of(HttpClientError::InvalidBody));
+        assert!(messages.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn it_should_read_tweets_until_disconnected() {
+        let tweet = r#"{"data":{"id":"1","text":"ゴール!","author_id":"10","created_at":"2022-12-05T15:43:00.000Z"},"includes":{"users":[{"id":"10","username":"jfa","name":"JFA"}]},"matching_rules":[{"id":"7","tag":"goal"}]}"#;
+        let url = serve_chunks(vec![vec![
+            b"\r\n{\"data\":\r\n".to_vec(),
+            tweet.as_bytes()[..40].to_vec(),
+            [&tweet.as_bytes()[40..], b"\r\n"].concat(),
+            b"\r\n".to_vec(),
+        ]])
+        .await;
+        let repo = StreamRepository {
+            http_client: Arc::new(HttpClient::new(RateLimits::default())),
+            auth: auth_repository(),
+            stream_url: url,
+        };
+
+        let mut stream = repo.connect().await.unwrap();
+        // 読めない行があっても切らずに次の行を読む
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(StreamMessage::Problem(_)))
+        ));
+        let Some(Ok(StreamMessage::Tweet(tweet))) = stream.next().await else {
+            panic!("expected a tweet");
+        };
+        assert_eq!(tweet.text, "ゴール!");
+        assert_eq!(tweet.author_label(), "@jfa (JFA)");
+        assert_eq!(tweet.rule_labels(), "[goal] ");
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.is_error_of(TwitterApiError::ServiceUnavailable));
+        assert!(stream.next().await.is_none());
+    }
+}
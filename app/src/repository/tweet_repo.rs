@@ -1,7 +1,12 @@
 use crate::domain::interface::*;
+use crate::domain::model::Order;
 use crate::domain::model::*;
 use crate::error::*;
 use crate::infra::DBConnector;
+use crate::repository::{
+    partial_errors, read_body, ApiResponse, AuthRepository, Includes, RepositoryError,
+    MEDIA_FIELDS, USER_FIELDS,
+};
 use crate::schema::tweet_records;
 use async_trait::async_trait;
 use diesel::dsl::*;
@@ -24,24 +29,23 @@ pub struct TweetRecord {
     source: String,
     withheld: Option<String>,
     bigquery: bool,
+    conversation_id: Option<String>,
 }
 
 impl TweetRecord {
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_model(self) -> Result<Tweet> {
-        let entities: Option<serde_json::Value> = serde_json::from_str(&self.entities).unwrap();
-        let geo: Option<serde_json::Value> = match self.geo {
-            Some(geo) => Some(serde_json::from_str(&geo).unwrap()),
-            None => None,
-        };
-        let referenced_tweets: Option<Vec<serde_json::Value>> = match self.referenced_tweets {
-            Some(referenced_tweets) => Some(serde_json::from_str(&referenced_tweets).unwrap()),
-            None => None,
-        };
-        let withheld: Option<serde_json::Value> = match self.withheld {
-            Some(withheld) => Some(serde_json::from_str(&withheld).unwrap()),
-            None => None,
-        };
-        Ok(Tweet::new(
+        fn parse<T: de::DeserializeOwned>(json: &str) -> Result<T> {
+            serde_json::from_str(json)
+                .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err))
+        }
+        let entities: Option<serde_json::Value> = parse(&self.entities)?;
+        let geo: Option<serde_json::Value> = self.geo.as_deref().map(parse).transpose()?;
+        let referenced_tweets: Option<Vec<serde_json::Value>> =
+            self.referenced_tweets.as_deref().map(parse).transpose()?;
+        let withheld: Option<serde_json::Value> =
+            self.withheld.as_deref().map(parse).transpose()?;
+        let mut tweet = Tweet::new(
             self.id,
             self.text,
             self.author_id,
@@ -54,23 +58,20 @@ impl TweetRecord {
             referenced_tweets,
             Some(self.source),
             withheld,
-        ))
+        );
+        tweet.conversation_id = self.conversation_id;
+        Ok(tweet)
     }
 
     pub fn from_model(tweet: Tweet) -> Result<Self> {
-        let entities = serde_json::to_string(&tweet.entities).unwrap();
-        let geo = match tweet.geo {
-            Some(geo) => Some(serde_json::to_string(&geo).unwrap()),
-            None => None,
-        };
-        let referenced_tweets = match tweet.referenced_tweets {
-            Some(referenced_tweets) => Some(serde_json::to_string(&referenced_tweets).unwrap()),
-            None => None,
-        };
-        let withheld = match tweet.withheld {
-            Some(withheld) => Some(serde_json::to_string(&withheld).unwrap()),
-            None => None,
-        };
+        fn to_json<T: Serialize>(value: &T) -> Result<String> {
+            serde_json::to_string(value)
+                .map_err(|err| ServiceError::new(RepositoryError::SerializationError, err))
+        }
+        let entities = to_json(&tweet.entities)?;
+        let geo = tweet.geo.as_ref().map(to_json).transpose()?;
+        let referenced_tweets = tweet.referenced_tweets.as_ref().map(to_json).transpose()?;
+        let withheld = tweet.withheld.as_ref().map(to_json).transpose()?;
         Ok(TweetRecord {
             id: tweet.id,
             text: tweet.text,
@@ -79,43 +80,164 @@ impl TweetRecord {
             entities,
             geo,
             in_reply_to_user_id: tweet.in_reply_to_user_id,
-            lang: tweet.lang.unwrap(),
+            // lang / source は返ってこないことがあるので空文字で保存する
+            lang: tweet.lang.unwrap_or_default(),
             possibly_sensitive: tweet.possibly_sensitive,
             referenced_tweets,
-            source: tweet.source.unwrap(),
+            source: tweet.source.unwrap_or_default(),
             withheld,
             bigquery: false,
+            conversation_id: tweet.conversation_id,
         })
     }
 }
 
+/// `POST /2/tweets` の `data`
+#[derive(Deserialize)]
+struct PostedTweet {
+    id: String,
+    text: String,
+}
+
 pub struct TweetRepository {
     db: DBConnector,
     http_client: Arc<dyn IHttpClient + Sync + Send>,
-    bearer_token: String,
+    auth: Arc<AuthRepository>,
 }
 
 impl TweetRepository {
     pub fn new(
         db: DBConnector,
         http_client: Arc<dyn IHttpClient + Sync + Send>,
-        bearer_token: String,
+        auth: Arc<AuthRepository>,
     ) -> Self {
         Self {
             db,
             http_client,
-            bearer_token,
+            auth,
         }
     }
 }
 
-#[derive(Clone, Deserialize, Serialize, Default)]
+const TWEETS_URL: &str = "https://api.twitter.com/2/tweets";
+const USERS_URL: &str = "https://api.twitter.com/2/users";
+const SEARCH_RECENT_URL: &str = "https://api.twitter.com/2/tweets/search/recent";
+const TWEET_FIELDS: &str = "attachments,author_id,conversation_id,created_at,entities,geo,in_reply_to_user_id,lang,possibly_sensitive,public_metrics,referenced_tweets,source,text,withheld";
+/// `/2/tweets?ids=` で一度に引ける件数
+pub const MAX_TWEETS_PER_LOOKUP: usize = 100;
+/// `search/recent` の `max_results` は 10 から 100 まで
+pub const MIN_RESULTS_PER_PAGE: usize = 10;
+pub const MAX_RESULTS_PER_PAGE: usize = 100;
+
+impl TweetRepository {
+    async fn search_recent(
+        &self,
+        query: &SearchQuery,
+        max_results: usize,
+        since_id: Option<&TweetID>,
+        pagination_token: Option<&str>,
+    ) -> Result<TweetPage> {
+        let mut uri = format!(
+            "{}?{}&max_results={}&{}",
+            SEARCH_RECENT_URL,
+            query.to_query_param()?,
+            max_results.clamp(MIN_RESULTS_PER_PAGE, MAX_RESULTS_PER_PAGE),
+            fields_param()
+        );
+        if let Some(id) = since_id {
+            uri.push_str(&format!("&since_id={}", id.0));
+        }
+        if let Some(token) = pagination_token {
+            uri.push_str(&format!("&{}", next_token_param(token)));
+        }
+        // 失敗してもポーリングは続けるので panic しない
+        let (tweets, meta, errors) = self.get_tweets_response(&uri).await?;
+        Ok(TweetPage {
+            tweets,
+            next_token: meta.and_then(|meta| meta.next_token),
+            errors,
+        })
+    }
+
+    /// 著者と添付を入れたツイートと、1 件ずつの部分エラー
+    async fn get_tweets_response(
+        &self,
+        uri: &str,
+    ) -> Result<(Vec<Tweet>, Option<TweetResponseMeta>, Vec<ServiceError>)> {
+        let mut response = TweetResponse::parse(&self.get_body(uri).await?)?;
+        let includes = std::mem::take(&mut response.includes);
+        let (tweets, meta, problems) = response.into_data()?;
+        Ok((
+            with_includes(tweets.unwrap_or_default(), &includes),
+            meta,
+            partial_errors(problems),
+        ))
+    }
+
+    /// `auth` がユーザーのトークンか署名を付ける
+    async fn send_as_user(&self, method: &str, uri: &str, body: Option<String>) -> Result<String> {
+        let headers = self.auth.headers(method, uri).await?;
+        let response = match method {
+            "DELETE" => self.http_client.delete(uri, Some(headers)).await?,
+            _ => self.http_client.post(uri, Some(headers), body).await?,
+        };
+        read_body(response)
+            .await
+            .map_err(|err| self.auth.check_rejected(method, uri, err))
+    }
+
+    async fn get_body(&self, uri: &str) -> Result<String> {
+        let headers = self.auth.headers("GET", uri).await?;
+        let response = self.http_client.get(uri, Some(headers)).await?;
+        read_body(response)
+            .await
+            .map_err(|err| self.auth.check_rejected("GET", uri, err))
+    }
+}
+
+/// `{"data": {"liked": true}}` のような応答の `field` が `expected` か確かめる
+fn confirm(body: &str, field: &str, expected: bool) -> Result<()> {
+    let (data, _, _) = ApiResponse::<serde_json::Value>::parse(body)?.into_data()?;
+    match data.and_then(|data| data.get(field)?.as_bool()) {
+        Some(value) if value == expected => Ok(()),
+        _ => Err(ServiceError::new(
+            ActionError::Unconfirmed,
+            anyhow::anyhow!("unexpected response: {}", body),
+        )),
+    }
+}
 
-pub struct TweetResponse {
-    data: Option<Vec<Tweet>>,
-    meta: Option<TweetResponseMeta>,
+/// `includes` の著者と添付をツイートに入れる
+pub fn with_includes(mut tweets: Vec<Tweet>, includes: &Includes) -> Vec<Tweet> {
+    attach_authors(&mut tweets, &includes.users);
+    attach_media(&mut tweets, &includes.media);
+    tweets
 }
 
+/// `search/recent` と `/2/tweets` で共通の `tweet.fields` と `expansions`。参照しているツイートとその著者も `includes` に入れてもらう
+pub fn fields_param() -> String {
+    format!(
+        "tweet.fields={}&expansions=author_id,attachments.media_keys,referenced_tweets.id,referenced_tweets.id.author_id&user.fields={}&media.fields={}",
+        TWEET_FIELDS, USER_FIELDS, MEDIA_FIELDS
+    )
+}
+
+/// 前の応答の `meta.next_token`。中身は決まっていないのでエンコードする
+fn next_token_param(token: &str) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("next_token", token)
+        .finish()
+}
+
+/// `LIKE` のワイルドカードをそのままの文字として扱う
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub type TweetResponse = ApiResponse<Vec<Tweet>, TweetResponseMeta>;
+
 #[derive(Clone, Deserialize, Serialize, Default)]
 pub struct TweetResponseMeta {
     pub newest_id: Option<String>,
@@ -136,6 +258,19 @@ impl ITweetRepository for TweetRepository {
         record.to_model()
     }
 
+    async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<Tweet>> {
+        let records = self
+            .db
+            .load::<TweetRecord, _>(
+                tweet_records::table.filter(tweet_records::id.eq_any(ids.to_vec())),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<Tweet>>>()
+    }
+
     async fn search(&self, query: &str) -> Result<Vec<Tweet>> {
         let records = self
             .db
@@ -149,40 +284,22 @@ impl ITweetRepository for TweetRepository {
             .collect::<Result<Vec<Tweet>>>()
     }
 
-    async fn get_tweets(&self, query: &str) -> Result<Vec<Tweet>> {
-        // remove retweets
-        let tweet_fileds = "tweet.fields=author_id,created_at,entities,geo,in_reply_to_user_id,lang,possibly_sensitive,referenced_tweets,source,text,withheld&max_results=10&expansions=author_id&user.fields=created_at,description,entities,id,location,name,pinned_tweet_id,profile_image_url,protected,public_metrics,url,username,verified,withheld";
-        let uri = "https://api.twitter.com/2/tweets/search/recent?query=".to_string()
-            + query
-            + "%20-is:retweet&"
-            + tweet_fileds;
-        let mut headers = reqwest::header::HeaderMap::new();
-        // add bearer_token
-        let bearer_token = format!("Bearer {}", self.bearer_token);
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            bearer_token.parse().unwrap(),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
-        );
-        let response = self.http_client.get(&uri, Some(headers)).await.unwrap();
-
-        let body = response.text().await.unwrap();
-
-        let tweets = serde_json::from_str::<TweetResponse>(&body).unwrap();
-
-        if tweets.data.is_none() {
-            return Ok(Vec::new());
-        }
-
-        let mut result = Vec::new();
-        for tweet in tweets.data.unwrap() {
-            result.push(tweet);
-        }
+    async fn get_tweets(&self, query: &SearchQuery) -> Result<Vec<Tweet>> {
+        let page = self
+            .search_recent(query, MIN_RESULTS_PER_PAGE, None, None)
+            .await?;
+        Ok(page.tweets)
+    }
 
-        Ok(result)
+    async fn get_tweets_page(
+        &self,
+        query: &SearchQuery,
+        max_results: usize,
+        since_id: Option<&TweetID>,
+        pagination_token: Option<&str>,
+    ) -> Result<TweetPage> {
+        self.search_recent(query, max_results, since_id, pagination_token)
+            .await
     }
 
     async fn get_latest_tweets(&self, count: i32) -> Result<Vec<Tweet>> {
@@ -200,79 +317,110 @@ impl ITweetRepository for TweetRepository {
             .collect::<Result<Vec<Tweet>>>()
     }
 
-    async fn get_tweets_by_hashtag(&self, hashtag: &str) -> Result<Vec<Tweet>> {
-        // remove retweets
-        let tweet_fileds = "tweet.fields=author_id,created_at,entities,geo,in_reply_to_user_id,lang,possibly_sensitive,referenced_tweets,source,text,withheld&max_results=10&expansions=author_id&user.fields=created_at,description,entities,id,location,name,pinned_tweet_id,profile_image_url,protected,public_metrics,url,username,verified,withheld";
-        let uri = "https://api.twitter.com/2/tweets/search/recent?query=%23".to_string()
-            + hashtag
-            + "%20-is:retweet&"
-            + tweet_fileds;
-        let mut headers = reqwest::header::HeaderMap::new();
-        // add bearer_token
-        let bearer_token = format!("Bearer {}", self.bearer_token);
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            bearer_token.parse().unwrap(),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
+    async fn lookup_tweets(&self, ids: &[String]) -> Result<(Vec<Tweet>, Vec<ServiceError>)> {
+        if ids.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let uri = format!(
+            "{}?ids={}&{}",
+            TWEETS_URL,
+            ids[..ids.len().min(MAX_TWEETS_PER_LOOKUP)].join(","),
+            fields_param()
         );
-        let response = self.http_client.get(&uri, Some(headers)).await.unwrap();
-
-        let body = response.text().await.unwrap();
+        let (tweets, _, errors) = self.get_tweets_response(&uri).await?;
+        Ok((tweets, errors))
+    }
 
-        let tweets = serde_json::from_str::<TweetResponse>(&body).unwrap();
+    async fn lookup_tweet(&self, id: &TweetID) -> Result<(Tweet, Vec<Tweet>)> {
+        let uri = format!("{}/{}?{}", TWEETS_URL, id.0, fields_param());
+        let mut response = ApiResponse::<Tweet>::parse(&self.get_body(&uri).await?)?;
+        let mut includes = std::mem::take(&mut response.includes);
+        // 見つからなければ errors だけが返ってきて NotFound になる
+        let (tweet, _, _) = response.into_data()?;
+        let tweet = with_includes(tweet.into_iter().collect(), &includes)
+            .pop()
+            .ok_or_else(|| {
+                ServiceError::new(
+                    TwitterApiError::NotFound,
+                    anyhow::anyhow!("tweet {} not found", id.0),
+                )
+            })?;
+        let referenced = with_includes(std::mem::take(&mut includes.tweets), &includes);
+        Ok((tweet, referenced))
+    }
 
-        if tweets.data.is_none() {
-            return Ok(Vec::new());
+    async fn find_local(&self, query: &LocalQuery) -> Result<Vec<Tweet>> {
+        let mut statement = tweet_records::table.into_boxed();
+        for text in &query.texts {
+            statement = statement.filter(
+                tweet_records::text
+                    .like(format!("%{}%", escape_like(text)))
+                    .escape('\\'),
+            );
         }
-
-        let mut result = Vec::new();
-        for tweet in tweets.data.unwrap() {
-            result.push(tweet);
+        if !query.authors.is_empty() {
+            statement = statement.filter(tweet_records::author_id.eq_any(query.authors.clone()));
         }
-
-        Ok(result)
-    }
-
-    async fn get_tweets_after_id(&self, query: &str, id: &TweetID) -> Result<Vec<Tweet>> {
-        // get tweets' author name
-        // remove retweets
-        let tweet_fileds = "tweet.fields=author_id,created_at,entities,geo,in_reply_to_user_id,lang,possibly_sensitive,referenced_tweets,source,text,withheld&max_results=10&expansions=author_id&user.fields=created_at,description,entities,id,location,name,pinned_tweet_id,profile_image_url,protected,public_metrics,url,username,verified,withheld";
-        let uri = "https://api.twitter.com/2/tweets/search/recent?query=".to_string()
-            + query
-            + "%20-is:retweet&since_id="
-            + &id.0
-            + "&"
-            + tweet_fileds;
-        let mut headers = reqwest::header::HeaderMap::new();
-        // add bearer_token
-        let bearer_token = format!("Bearer {}", self.bearer_token);
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            bearer_token.parse().unwrap(),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
-        );
-        let response = self.http_client.get(&uri, Some(headers)).await.unwrap();
-
-        let body = response.text().await.unwrap();
-
-        let tweets = serde_json::from_str::<TweetResponse>(&body).unwrap();
-
-        if tweets.data.is_none() {
-            return Ok(Vec::new());
+        if let Some(lang) = &query.lang {
+            statement = statement.filter(tweet_records::lang.eq(lang.clone()));
         }
-
-        let mut result = Vec::new();
-        for tweet in tweets.data.unwrap() {
-            result.push(tweet);
+        if let Some(id) = &query.conversation_id {
+            statement = statement.filter(tweet_records::conversation_id.eq(id.clone()));
+        }
+        if let Some(since) = &query.since {
+            statement = statement.filter(tweet_records::created_at.ge(to_created_at(since)));
+        }
+        if let Some(until) = &query.until {
+            statement = statement.filter(tweet_records::created_at.lt(to_created_at(until)));
         }
+        statement = match query.sensitive {
+            Some(true) => statement.filter(tweet_records::possibly_sensitive.eq(true)),
+            Some(false) => statement.filter(
+                tweet_records::possibly_sensitive
+                    .is_null()
+                    .or(tweet_records::possibly_sensitive.eq(false)),
+            ),
+            None => statement,
+        };
+        statement = match query.order {
+            Order::NewestFirst => {
+                statement.order((tweet_records::created_at.desc(), tweet_records::id.desc()))
+            }
+            Order::OldestFirst => {
+                statement.order((tweet_records::created_at.asc(), tweet_records::id.asc()))
+            }
+        };
+        let records = self
+            .db
+            .load::<TweetRecord, _>(
+                statement
+                    .limit(query.limit as i64)
+                    .offset(query.offset as i64),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<Tweet>>>()
+    }
+
+    async fn get_tweets_by_hashtag(&self, hashtag: &str) -> Result<Vec<Tweet>> {
+        let page = self
+            .search_recent(
+                &SearchQuery::new().hashtag(hashtag),
+                MIN_RESULTS_PER_PAGE,
+                None,
+                None,
+            )
+            .await?;
+        Ok(page.tweets)
+    }
 
-        Ok(result)
+    async fn get_tweets_after_id(&self, query: &SearchQuery, id: &TweetID) -> Result<Vec<Tweet>> {
+        let page = self
+            .search_recent(query, MIN_RESULTS_PER_PAGE, Some(id), None)
+            .await?;
+        Ok(page.tweets)
     }
 
     async fn save(&self, tweet: Tweet) -> Result<()> {
@@ -286,7 +434,7 @@ impl ITweetRepository for TweetRepository {
     async fn save_tweets(&self, tweets: Vec<Tweet>) -> Result<()> {
         let records = tweets
             .into_iter()
-            .map(|tweet| TweetRecord::from_model(tweet))
+            .map(TweetRecord::from_model)
             .collect::<Result<Vec<TweetRecord>>>()?;
         for record in records {
             self.db
@@ -304,59 +452,218 @@ impl ITweetRepository for TweetRepository {
     }
 
     async fn delete_tweet(&self, id: &TweetID) -> Result<()> {
-        // delete tweet from twitter
-        let uri = format!("https://api.twitter.com/2/tweets/{}", id.0);
-        let mut headers = reqwest::header::HeaderMap::new();
-        // add bearer_token
-
-        let bearer_token = format!(
-            "
-        Bearer {}",
-            self.bearer_token
-        );
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            bearer_token.parse().unwrap(),
-        );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
-        );
-        let response = self.http_client.delete(&uri, Some(headers)).await.unwrap();
+        let uri = format!("{}/{}", TWEETS_URL, id.0);
+        let body = self.send_as_user("DELETE", &uri, None).await?;
+        confirm(&body, "deleted", true)
+    }
+
+    async fn favorite_tweet(&self, id: &TweetID) -> Result<()> {
+        // いいねはログインしたユーザーの `/2/users/:id/likes` に送る
+        let uri = format!("{}/{}/likes", USERS_URL, self.auth.user_id().await?);
+        let body = serde_json::json!({ "tweet_id": id.0 }).to_string();
+        let body = self.send_as_user("POST", &uri, Some(body)).await?;
+        confirm(&body, "liked", true)
+    }
 
-        let body = response.text().await.unwrap();
+    async fn unfavorite_tweet(&self, id: &TweetID) -> Result<()> {
+        let uri = format!(
+            "{}/{}/likes/{}",
+            USERS_URL,
+            self.auth.user_id().await?,
+            id.0
+        );
+        let body = self.send_as_user("DELETE", &uri, None).await?;
+        confirm(&body, "liked", false)
+    }
 
-        Ok(())
+    async fn retweet(&self, id: &TweetID) -> Result<()> {
+        let uri = format!("{}/{}/retweets", USERS_URL, self.auth.user_id().await?);
+        let body = serde_json::json!({ "tweet_id": id.0 }).to_string();
+        let body = self.send_as_user("POST", &uri, Some(body)).await?;
+        confirm(&body, "retweeted", true)
     }
 
-    async fn favorite_tweet(&self, id: &TweetID) -> Result<()> {
-        // favorite tweet from twitter
-        let uri = format!("https://api.twitter.com/2/tweets/{}/like", id.0);
-        let mut headers = reqwest::header::HeaderMap::new();
-        // add bearer_token
-
-        let bearer_token = format!(
-            "
-        Bearer {}",
-            self.bearer_token
+    async fn unretweet(&self, id: &TweetID) -> Result<()> {
+        let uri = format!(
+            "{}/{}/retweets/{}",
+            USERS_URL,
+            self.auth.user_id().await?,
+            id.0
         );
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            bearer_token.parse().unwrap(),
+        let body = self.send_as_user("DELETE", &uri, None).await?;
+        confirm(&body, "retweeted", false)
+    }
+
+    async fn liked_tweets(&self, max_results: usize) -> Result<Vec<Tweet>> {
+        let uri = format!(
+            "{}/{}/liked_tweets?max_results={}&{}",
+            USERS_URL,
+            self.auth.user_id().await?,
+            max_results.clamp(MIN_RESULTS_PER_PAGE, MAX_RESULTS_PER_PAGE),
+            fields_param()
         );
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
+        // 消されたツイートの部分エラーは一覧に出ないだけなので捨てる
+        let (tweets, _, _) = self.get_tweets_response(&uri).await?;
+        Ok(tweets)
+    }
+
+    async fn post_tweet(&self, draft: &Draft) -> Result<Tweet> {
+        // 投稿してから失敗しないように、ユーザーは先に確かめておく
+        let author_id = self.auth.user_id().await?;
+        // 返信は返信先と同じ会話。返信先が手元に無ければ返信先から始まる会話とみなす
+        let conversation_id = match &draft.in_reply_to {
+            Some(id) => match self.find_by_id(id).await {
+                Ok(parent) => Some(parent.conversation_id.unwrap_or(parent.id)),
+                Err(err) if err.is_error_of(RepositoryError::RecordNotFound) => Some(id.0.clone()),
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+
+        let mut body = serde_json::json!({ "text": draft.text });
+        if let Some(id) = &draft.in_reply_to {
+            body["reply"] = serde_json::json!({ "in_reply_to_tweet_id": id.0 });
+        }
+        let body = self
+            .send_as_user("POST", TWEETS_URL, Some(body.to_string()))
+            .await?;
+        let (posted, _, _) = ApiResponse::<PostedTweet>::parse(&body)?.into_data()?;
+        let posted = posted.ok_or_else(|| {
+            ServiceError::new(
+                RepositoryError::SerializationError,
+                anyhow::anyhow!("no data in response: {}", body),
+            )
+        })?;
+
+        // 応答は ID と本文だけなので、残りは手元で埋める
+        let referenced_tweets = draft
+            .in_reply_to
+            .as_ref()
+            .map(|id| vec![serde_json::json!({ "type": "replied_to", "id": id.0 })]);
+        let mut tweet = Tweet::new(
+            posted.id,
+            posted.text,
+            author_id,
+            to_created_at(&chrono::Utc::now()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            referenced_tweets,
+            None,
+            None,
         );
+        tweet.conversation_id = Some(conversation_id.unwrap_or(tweet.id.clone()));
+        Ok(tweet)
+    }
+}
 
-        let response = self
-            .http_client
-            .post(&uri, Some(headers), None)
-            .await
-            .unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::testing::{memory_db, tweet_repository};
+    use chrono::{TimeZone, Utc};
+
+    fn tweet(id: &str, text: &str, author_id: &str, created_at: &str) -> Tweet {
+        Tweet::new(
+            id.to_string(),
+            text.to_string(),
+            author_id.to_string(),
+            created_at.to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            if id == "3" { Some(true) } else { None },
+            None,
+            Some("Twitter for iPhone".to_string()),
+            None,
+        )
+    }
 
-        let body = response.text().await.unwrap();
+    fn ids(tweets: &[Tweet]) -> Vec<&str> {
+        tweets.iter().map(|it| it.id.as_str()).collect()
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn it_should_find_local_tweets() {
+        let repo = tweet_repository(memory_db());
+        repo.save_tweets(vec![
+            tweet("1", "前半 0-1", "10", "2022-12-05T15:00:00.000Z"),
+            tweet("2", "100%同点ゴール!", "20", "2022-12-05T16:00:00.000Z"),
+            tweet("3", "同点ゴール!!", "10", "2022-12-05T17:00:00.000Z"),
+            tweet("4", "PK 戦へ", "30", "2022-12-05T18:00:00.000Z"),
+        ])
+        .await
+        .unwrap();
+
+        let all = LocalQuery {
+            limit: 10,
+            ..Default::default()
+        };
+        let find = |query: LocalQuery| {
+            let repo = &repo;
+            async move { repo.find_local(&query).await.unwrap() }
+        };
+
+        assert_eq!(ids(&find(all.clone()).await), vec!["4", "3", "2", "1"]);
+        let goals = LocalQuery {
+            texts: vec!["同点".to_string()],
+            ..all.clone()
+        };
+        assert_eq!(ids(&find(goals.clone()).await), vec!["3", "2"]);
+        let percent = LocalQuery {
+            texts: vec!["0%".to_string()],
+            ..all.clone()
+        };
+        assert_eq!(ids(&find(percent).await), vec!["2"]);
+        let safe = LocalQuery {
+            sensitive: Some(false),
+            ..goals
+        };
+        assert_eq!(ids(&find(safe).await), vec!["2"]);
+        let by_author = LocalQuery {
+            authors: vec!["10".to_string(), "30".to_string()],
+            order: Order::OldestFirst,
+            offset: 1,
+            limit: 1,
+            ..all.clone()
+        };
+        assert_eq!(ids(&find(by_author).await), vec!["3"]);
+        let window = LocalQuery {
+            since: Some(Utc.with_ymd_and_hms(2022, 12, 5, 16, 0, 0).unwrap()),
+            until: Some(Utc.with_ymd_and_hms(2022, 12, 5, 18, 0, 0).unwrap()),
+            ..all
+        };
+        assert_eq!(ids(&find(window).await), vec!["3", "2"]);
+    }
+
+    #[test]
+    fn it_should_encode_next_token() {
+        assert_eq!(
+            next_token_param("b26v89c19zqg8o3f"),
+            "next_token=b26v89c19zqg8o3f"
+        );
+        assert_eq!(next_token_param("a+b/c=&d"), "next_token=a%2Bb%2Fc%3D%26d");
+    }
+
+    #[test]
+    fn it_should_confirm_action_result() {
+        assert!(confirm(r#"{"data":{"liked":true}}"#, "liked", true).is_ok());
+        let err = confirm(r#"{"data":{"liked":true}}"#, "liked", false).unwrap_err();
+        assert!(err.is_error_of(ActionError::Unconfirmed));
+        let err = confirm(r#"{"data":{}}"#, "deleted", true).unwrap_err();
+        assert!(err.is_error_of(ActionError::Unconfirmed));
+    }
+
+    #[test]
+    fn it_should_attach_authors_to_included_tweets() {
+        let body = r#"{"data":{"id":"2","text":"@jfa おめでとう","author_id":"20","created_at":"2022-12-05T15:44:00.000Z","referenced_tweets":[{"type":"replied_to","id":"1"}]},"includes":{"users":[{"id":"10","username":"jfa","name":"JFA"},{"id":"20","username":"fan","name":"Fan"}],"tweets":[{"id":"1","text":"ゴール!","author_id":"10","created_at":"2022-12-05T15:43:00.000Z"}]}}"#;
+        let mut response = ApiResponse::<Tweet>::parse(body).unwrap();
+        let mut includes = std::mem::take(&mut response.includes);
+        let referenced = with_includes(std::mem::take(&mut includes.tweets), &includes);
+        assert_eq!(ids(&referenced), vec!["1"]);
+        assert_eq!(referenced[0].author.as_ref().unwrap().username, "jfa");
     }
 }
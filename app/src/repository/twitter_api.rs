@@ -0,0 +1,287 @@
+use crate::domain::model::{Media, RateLimit, Tweet, TwitterApiError, User};
+use crate::error::*;
+use crate::infra::HttpClientError;
+use chrono::{DateTime, Local, Utc};
+use serde::*;
+
+/// `includes.users` や `/2/users` で取るユーザーの項目
+pub const USER_FIELDS: &str = "created_at,description,entities,id,location,name,pinned_tweet_id,profile_image_url,protected,public_metrics,url,username,verified,withheld";
+
+/// `includes.media` で取る添付の項目
+pub const MEDIA_FIELDS: &str =
+    "alt_text,duration_ms,height,media_key,preview_image_url,type,url,width";
+
+/// Twitter のエラーオブジェクト。v2 の problem details と v1.1 形式 (`message` / `code`) のどちらも読む
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApiProblem {
+    #[serde(rename = "type")]
+    pub problem_type: Option<String>,
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    pub status: Option<u16>,
+    pub message: Option<String>,
+    pub code: Option<i64>,
+    /// 部分エラーのときに対象のパラメータと値
+    pub parameter: Option<String>,
+    pub value: Option<String>,
+}
+
+impl ApiProblem {
+    /// `https://api.twitter.com/2/problems/resource-not-found` → `resource-not-found`
+    pub fn kind(&self) -> Option<&str> {
+        self.problem_type.as_deref()?.rsplit('/').next()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.detail.is_none() && self.message.is_none()
+    }
+}
+
+impl std::fmt::Display for ApiProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = [&self.detail, &self.message, &self.title]
+            .into_iter()
+            .flatten()
+            .next()
+            .map(String::as_str)
+            .unwrap_or("unknown error");
+        match (&self.title, &self.detail) {
+            (Some(title), Some(_)) if title != text => write!(f, "{}: {}", title, text)?,
+            _ => write!(f, "{}", text)?,
+        }
+        if let Some(code) = self.code {
+            write!(f, " (code {})", code)?;
+        }
+        Ok(())
+    }
+}
+
+/// 1 つのレスポンスに入っていたエラー
+#[derive(Debug)]
+pub struct ApiProblems(pub Vec<ApiProblem>);
+
+impl std::fmt::Display for ApiProblems {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let problems = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+        write!(f, "{}", problems.join("; "))
+    }
+}
+
+impl std::error::Error for ApiProblems {}
+
+/// `expansions` で一緒に返ってくるオブジェクト
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Includes {
+    pub users: Vec<User>,
+    pub media: Vec<Media>,
+    /// 返信先・引用先などの参照しているツイート
+    pub tweets: Vec<Tweet>,
+}
+
+/// v2 の `{"data": ..., "includes": ..., "errors": [...], "meta": ...}`
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse<T, M = serde_json::Value> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub includes: Includes,
+    pub meta: Option<M>,
+    #[serde(default)]
+    pub errors: Vec<ApiProblem>,
+}
+
+impl<T, M> ApiResponse<T, M>
+where
+    T: de::DeserializeOwned,
+    M: de::DeserializeOwned,
+{
+    pub fn parse(body: &str) -> Result<Self> {
+        serde_json::from_str(body).map_err(|err| {
+            ServiceError::new(
+                HttpClientError::InvalidBody,
+                anyhow::anyhow!("unexpected response: {}", err),
+            )
+        })
+    }
+
+    /// `data` が無くて `errors` だけならエラー。部分エラーは `partial_errors` で取り出す
+    pub fn into_data(self) -> Result<(Option<T>, Option<M>, Vec<ApiProblem>)> {
+        if self.data.is_none() && !self.errors.is_empty() {
+            return Err(problem_error(None, self.errors));
+        }
+        Ok((self.data, self.meta, self.errors))
+    }
+}
+
+/// 部分エラー (見つからない・見られないツイートなど) を 1 件ずつ `ServiceError` にする
+pub fn partial_errors(problems: Vec<ApiProblem>) -> Vec<ServiceError> {
+    problems
+        .into_iter()
+        .map(|problem| problem_error(None, vec![problem]))
+        .collect()
+}
+
+/// エラーのステータスなら `TwitterApiError` にし、成功ならボディを返す
+pub async fn read_body(response: reqwest::Response) -> Result<String> {
+    String::from_utf8(read_bytes(response).await?)
+        .map_err(|err| ServiceError::new(HttpClientError::InvalidBody, err))
+}
+
+/// `read_body` の画像など用
+pub async fn read_bytes(response: reqwest::Response) -> Result<Vec<u8>> {
+    Ok(check_status(response).await?.bytes().await?.to_vec())
+}
+
+/// 成功ならボディを読まずにそのまま返す。ストリームなど少しずつ読むとき用
+pub async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let reset = RateLimit::from_headers(response.headers()).map(|limit| limit.reset);
+    let body = response.bytes().await?;
+    Err(status_error(status, reset, &String::from_utf8_lossy(&body)))
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ErrorBody {
+    errors: Vec<ApiProblem>,
+    #[serde(flatten)]
+    problem: ApiProblem,
+}
+
+/// 失敗したレスポンスのエラー
+pub fn status_error(
+    status: http::StatusCode,
+    reset: Option<DateTime<Utc>>,
+    body: &str,
+) -> ServiceError {
+    let body = serde_json::from_str::<ErrorBody>(body).unwrap_or_default();
+    let mut problems = Vec::new();
+    if !body.problem.is_empty() {
+        problems.push(body.problem);
+    }
+    problems.extend(body.errors);
+    if problems.is_empty() {
+        problems.push(ApiProblem {
+            title: Some(status.to_string()),
+            status: Some(status.as_u16()),
+            ..ApiProblem::default()
+        });
+    }
+
+    use http::StatusCode;
+    let kind = match status {
+        StatusCode::UNAUTHORIZED => Some(TwitterApiError::Unauthorized),
+        StatusCode::FORBIDDEN => Some(TwitterApiError::Forbidden),
+        StatusCode::TOO_MANY_REQUESTS => Some(TwitterApiError::RateLimited { reset }),
+        StatusCode::BAD_REQUEST => Some(TwitterApiError::InvalidQuery),
+        StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => Some(TwitterApiError::ServiceUnavailable),
+        _ => None,
+    };
+    match kind {
+        Some(kind) => problems_into(kind, problems),
+        None => problem_error(reset, problems),
+    }
+}
+
+/// problem の `type` から種類を決める
+fn problem_error(reset: Option<DateTime<Utc>>, problems: Vec<ApiProblem>) -> ServiceError {
+    let kind = match problems.first().and_then(ApiProblem::kind) {
+        Some("invalid-request") => TwitterApiError::InvalidQuery,
+        Some("resource-not-found") => TwitterApiError::NotFound,
+        Some("not-authorized-for-resource") | Some("client-forbidden") => {
+            TwitterApiError::Forbidden
+        }
+        Some("usage-capped") => TwitterApiError::RateLimited { reset },
+        _ => TwitterApiError::Problem,
+    };
+    problems_into(kind, problems)
+}
+
+fn problems_into(kind: TwitterApiError, problems: Vec<ApiProblem>) -> ServiceError {
+    match &kind {
+        TwitterApiError::RateLimited { reset: Some(reset) } => {
+            let reset = reset.with_timezone(&Local).format("%H:%M:%S");
+            ServiceError::new(
+                kind,
+                anyhow::Error::new(ApiProblems(problems)).context(format!("reset {}", reset)),
+            )
+        }
+        _ => ServiceError::new(kind, ApiProblems(problems)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_map_statuses() {
+        let err = status_error(
+            http::StatusCode::UNAUTHORIZED,
+            None,
+            r#"{"title":"Unauthorized","type":"about:blank","status":401,"detail":"Unauthorized"}"#,
+        );
+        assert!(err.is_error_of(TwitterApiError::Unauthorized));
+        assert_eq!(err.to_string(), "unauthorized: Unauthorized");
+
+        let err = status_error(
+            http::StatusCode::TOO_MANY_REQUESTS,
+            Some(Utc::now()),
+            "Too Many Requests",
+        );
+        assert!(err.is_error_of(TwitterApiError::RateLimited { reset: None }));
+
+        let err = status_error(http::StatusCode::BAD_GATEWAY, None, "");
+        assert!(err.is_error_of(TwitterApiError::ServiceUnavailable));
+        assert_eq!(err.to_string(), "service_unavailable: 502 Bad Gateway");
+    }
+
+    #[test]
+    fn it_should_parse_invalid_query() {
+        let body = r#"{
+            "errors": [{"parameters": {"query": ["(("]}, "message": "There were errors processing your request: missing right paren (at position 2)"}],
+            "title": "Invalid Request",
+            "detail": "One or more parameters to your request was invalid.",
+            "type": "https://api.twitter.com/2/problems/invalid-request"
+        }"#;
+        let err = status_error(http::StatusCode::BAD_REQUEST, None, body);
+        assert!(err.is_error_of(TwitterApiError::InvalidQuery));
+        assert_eq!(
+            err.to_string(),
+            "invalid_query: Invalid Request: One or more parameters to your request was invalid.; There were errors processing your request: missing right paren (at position 2)"
+        );
+    }
+
+    #[test]
+    fn it_should_split_partial_errors() {
+        let body = r#"{
+            "data": [{"id": "1"}],
+            "errors": [{
+                "value": "2", "detail": "Could not find tweet with ids: [2].", "title": "Not Found Error",
+                "resource_type": "tweet", "parameter": "ids", "resource_id": "2",
+                "type": "https://api.twitter.com/2/problems/resource-not-found"
+            }]
+        }"#;
+        let (data, _, problems) = ApiResponse::<Vec<serde_json::Value>>::parse(body)
+            .unwrap()
+            .into_data()
+            .unwrap();
+        assert_eq!(data.unwrap().len(), 1);
+        assert_eq!(problems[0].kind(), Some("resource-not-found"));
+        assert!(partial_errors(problems)[0].is_error_of(TwitterApiError::NotFound));
+
+        let body = r#"{"errors": [{"detail": "Sorry, you are not authorized to see the Tweet with id: [3].", "title": "Authorization Error", "type": "https://api.twitter.com/2/problems/not-authorized-for-resource"}]}"#;
+        let err = ApiResponse::<Vec<serde_json::Value>>::parse(body)
+            .unwrap()
+            .into_data()
+            .unwrap_err();
+        assert!(err.is_error_of(TwitterApiError::Forbidden));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::{
+    partial_errors, read_body, ApiResponse, AuthRepository, RepositoryError, USER_FIELDS,
+};
+use crate::schema::user_records;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::*;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+const USERS_URL: &str = "https://api.twitter.com/2/users";
+/// `/2/users?ids=` で一度に引ける件数
+pub const MAX_USERS_PER_LOOKUP: usize = 100;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = user_records)]
+pub struct UserRecord {
+    id: String,
+    username: String,
+    name: String,
+    created_at: Option<String>,
+    description: Option<String>,
+    location: Option<String>,
+    profile_image_url: Option<String>,
+    url: Option<String>,
+    protected: Option<bool>,
+    verified: Option<bool>,
+    public_metrics: Option<String>,
+    fetched_at: String,
+}
+
+impl UserRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<User> {
+        let invalid = |err: anyhow::Error| ServiceError::new(RepositoryError::InvalidRecord, err);
+        Ok(User {
+            public_metrics: self
+                .public_metrics
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()
+                .map_err(|err| invalid(err.into()))?,
+            fetched_at: DateTime::parse_from_rfc3339(&self.fetched_at)
+                .map_err(|err| invalid(err.into()))?
+                .with_timezone(&Utc),
+            id: self.id,
+            username: self.username,
+            name: self.name,
+            created_at: self.created_at,
+            description: self.description,
+            location: self.location,
+            profile_image_url: self.profile_image_url,
+            url: self.url,
+            protected: self.protected,
+            verified: self.verified,
+        })
+    }
+
+    pub fn from_model(user: User) -> Result<Self> {
+        Ok(Self {
+            public_metrics: user
+                .public_metrics
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|err| ServiceError::new(RepositoryError::SerializationError, err))?,
+            fetched_at: to_created_at(&user.fetched_at),
+            id: user.id,
+            username: user.username,
+            name: user.name,
+            created_at: user.created_at,
+            description: user.description,
+            location: user.location,
+            profile_image_url: user.profile_image_url,
+            url: user.url,
+            protected: user.protected,
+            verified: user.verified,
+        })
+    }
+}
+
+pub struct UserRepository {
+    db: DBConnector,
+    http_client: Arc<dyn IHttpClient + Sync + Send>,
+    auth: Arc<AuthRepository>,
+}
+
+impl UserRepository {
+    pub fn new(
+        db: DBConnector,
+        http_client: Arc<dyn IHttpClient + Sync + Send>,
+        auth: Arc<AuthRepository>,
+    ) -> Self {
+        Self {
+            db,
+            http_client,
+            auth,
+        }
+    }
+}
+
+#[async_trait]
+impl IUserRepository for UserRepository {
+    async fn save_users(&self, users: Vec<User>) -> Result<()> {
+        for user in users {
+            self.db
+                .execute(
+                    replace_into(user_records::table)
+                        .values::<UserRecord>(UserRecord::from_model(user)?),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn find_by_ids(&self, ids: &[String]) -> Result<Vec<User>> {
+        let records = self
+            .db
+            .load::<UserRecord, _>(
+                user_records::table.filter(user_records::id.eq_any(ids.to_vec())),
+            )
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<User>>>()
+    }
+
+    async fn lookup(&self, ids: &[String]) -> Result<(Vec<User>, Vec<ServiceError>)> {
+        if ids.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let uri = format!(
+            "{}?ids={}&user.fields={}",
+            USERS_URL,
+            ids[..ids.len().min(MAX_USERS_PER_LOOKUP)].join(","),
+            USER_FIELDS
+        );
+        let headers = self.auth.headers("GET", &uri).await?;
+        let response = self.http_client.get(&uri, Some(headers)).await?;
+        let body = read_body(response).await?;
+        // 凍結や削除で見つからないユーザーは部分エラーになる
+        let (users, _, problems) = ApiResponse::<Vec<User>>::parse(&body)?.into_data()?;
+        Ok((users.unwrap_or_default(), partial_errors(problems)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::HttpClient;
+    use crate::repository::testing::{auth_repository, memory_db};
+    use chrono::TimeZone;
+
+    fn user(id: &str, name: &str) -> User {
+        User {
+            id: id.to_string(),
+            username: format!("user{}", id),
+            name: name.to_string(),
+            created_at: None,
+            description: Some("サッカー好き".to_string()),
+            location: None,
+            profile_image_url: None,
+            url: None,
+            protected: Some(false),
+            verified: None,
+            public_metrics: Some(serde_json::json!({ "followers_count": 10 })),
+            fetched_at: Utc.with_ymd_and_hms(2022, 12, 5, 15, 0, 0).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_replace_cached_profiles() {
+        let repo = UserRepository::new(
+            memory_db(),
+            Arc::new(HttpClient::new(RateLimits::default())),
+            auth_repository(),
+        );
+        repo.save_users(vec![user("1", "圭佑"), user("2", "三笘")])
+            .await
+            .unwrap();
+        repo.save_users(vec![user("1", "本田圭佑")]).await.unwrap();
+
+        let mut users = repo
+            .find_by_ids(&["1".to_string(), "3".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users.remove(0), user("1", "本田圭佑"));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::domain::interface::*;
+use crate::domain::model::*;
+use crate::error::*;
+use crate::infra::DBConnector;
+use crate::repository::RepositoryError;
+use crate::schema::watch_entries;
+use async_trait::async_trait;
+use diesel::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable, Identifiable)]
+#[diesel(table_name = watch_entries, primary_key(name))]
+pub struct WatchEntryRecord {
+    name: String,
+    kind: String,
+    query: String,
+    color: Option<String>,
+}
+
+impl WatchEntryRecord {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_model(self) -> Result<WatchEntry> {
+        let kind = WatchKind::parse(&self.kind)
+            .map_err(|err| ServiceError::new(RepositoryError::InvalidRecord, err.into_inner()))?;
+        Ok(WatchEntry {
+            name: self.name,
+            kind,
+            query: self.query,
+            color: self.color,
+            builtin: false,
+        })
+    }
+
+    pub fn from_model(entry: WatchEntry) -> Self {
+        Self {
+            name: entry.name,
+            kind: entry.kind.as_str().to_string(),
+            query: entry.query,
+            color: entry.color,
+        }
+    }
+}
+
+pub struct WatchRepository {
+    db: DBConnector,
+}
+
+impl WatchRepository {
+    pub fn new(db: DBConnector) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl IWatchRepository for WatchRepository {
+    async fn list(&self) -> Result<Vec<WatchEntry>> {
+        let records = self
+            .db
+            .load::<WatchEntryRecord, _>(watch_entries::table.order(watch_entries::name.asc()))
+            .await?;
+        records
+            .into_iter()
+            .map(|record| record.to_model())
+            .collect::<Result<Vec<WatchEntry>>>()
+    }
+
+    async fn save(&self, entry: WatchEntry) -> Result<()> {
+        self.db
+            .execute(
+                replace_into(watch_entries::table)
+                    .values::<WatchEntryRecord>(WatchEntryRecord::from_model(entry)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<bool> {
+        let deleted = self
+            .db
+            .execute(delete(watch_entries::table).filter(watch_entries::name.eq(name.to_string())))
+            .await?;
+        Ok(deleted > 0)
+    }
+}
@@ -1,5 +1,71 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    actions (id) {
+        id -> Text,
+        kind -> Text,
+        tweet_id -> Text,
+        performed_at -> Text,
+        succeeded -> Bool,
+        error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    fixtures (id) {
+        id -> Text,
+        stage -> Text,
+        home_code -> Text,
+        home_name -> Text,
+        home_hashtags -> Text,
+        away_code -> Text,
+        away_name -> Text,
+        away_hashtags -> Text,
+        kickoff -> Text,
+        venue -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    match_events (id) {
+        id -> Text,
+        kind -> Text,
+        detected_at -> Text,
+        query -> Text,
+        tweets_per_minute -> Double,
+        baseline -> Double,
+        score -> Double,
+        keyword_hits -> Integer,
+        sample_tweet_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    media_records (media_key) {
+        media_key -> Text,
+        tweet_id -> Text,
+        position -> Integer,
+        media_type -> Text,
+        url -> Nullable<Text>,
+        preview_image_url -> Nullable<Text>,
+        duration_ms -> Nullable<BigInt>,
+        width -> Nullable<Integer>,
+        height -> Nullable<Integer>,
+        alt_text -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tweet_metric_snapshots (tweet_id, observed_at) {
+        tweet_id -> Text,
+        observed_at -> Text,
+        like_count -> BigInt,
+        retweet_count -> BigInt,
+        reply_count -> BigInt,
+        quote_count -> BigInt,
+    }
+}
+
 diesel::table! {
     tweet_records (id) {
         id -> Text,
@@ -15,5 +81,43 @@ diesel::table! {
         source -> Text,
         withheld -> Nullable<Text>,
         bigquery -> Bool,
+        conversation_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    user_records (id) {
+        id -> Text,
+        username -> Text,
+        name -> Text,
+        created_at -> Nullable<Text>,
+        description -> Nullable<Text>,
+        location -> Nullable<Text>,
+        profile_image_url -> Nullable<Text>,
+        url -> Nullable<Text>,
+        protected -> Nullable<Bool>,
+        verified -> Nullable<Bool>,
+        public_metrics -> Nullable<Text>,
+        fetched_at -> Text,
+    }
+}
+
+diesel::table! {
+    watch_entries (name) {
+        name -> Text,
+        kind -> Text,
+        query -> Text,
+        color -> Nullable<Text>,
     }
 }
+
+diesel::allow_tables_to_appear_in_same_query!(
+    actions,
+    fixtures,
+    match_events,
+    media_records,
+    tweet_metric_snapshots,
+    tweet_records,
+    user_records,
+    watch_entries,
+);
@@ -0,0 +1,13 @@
+mod disguise;
+pub use disguise::*;
+
+mod preview;
+pub use preview::*;
+
+mod renderer;
+pub use renderer::*;
+
+mod theme;
+pub use theme::*;
+
+pub mod tui;
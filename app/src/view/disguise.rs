@@ -0,0 +1,465 @@
+use crate::domain::model::{MatchEvent, Tweet};
+use crate::domain::service::{describe_error, PollCommand, PollEvent, PollerHandle};
+use crate::error::*;
+use crate::view::{Renderer, ViewError};
+use chrono::{DateTime, Local, SecondsFormat};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+/// このキーで偽ビルドとツイートを切り替える
+pub const PANIC_KEY: char = 'b';
+const FAKE_BUILD_TICK: Duration = Duration::from_millis(250);
+
+/// ファイル名やモジュール名に使う
+const MODULES: &[&str] = &[
+    "scheduler",
+    "matcher",
+    "ingest",
+    "codec",
+    "pool",
+    "resolver",
+    "lexer",
+    "planner",
+    "storage",
+    "handshake",
+];
+
+/// cargo の偽ビルドに出てくるクレート
+const CRATES: &[(&str, &str)] = &[
+    ("proc-macro2", "1.0.47"),
+    ("unicode-ident", "1.0.5"),
+    ("libc", "0.2.138"),
+    ("syn", "1.0.105"),
+    ("serde_derive", "1.0.149"),
+    ("tokio-macros", "1.8.2"),
+    ("hyper", "0.14.23"),
+    ("h2", "0.3.15"),
+    ("diesel_derives", "2.0.1"),
+    ("reqwest", "0.11.13"),
+    ("tower", "0.4.13"),
+    ("regex-syntax", "0.6.28"),
+    ("openssl-sys", "0.9.79"),
+    ("libsqlite3-sys", "0.25.2"),
+    ("clap_builder", "4.0.29"),
+];
+
+/// 仕事中に見ていてもばれない見た目
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disguise {
+    Cargo,
+    Pytest,
+    Kubectl,
+    Tail,
+}
+
+impl Disguise {
+    pub const NAMES: [&'static str; 4] = ["cargo", "pytest", "kubectl", "tail"];
+
+    pub fn parse(name: &str) -> Result<Disguise> {
+        use Disguise::*;
+
+        match name {
+            "cargo" => Ok(Cargo),
+            "pytest" => Ok(Pytest),
+            "kubectl" => Ok(Kubectl),
+            "tail" => Ok(Tail),
+            _ => Err(ServiceError::new(
+                ViewError::UnknownFormat,
+                anyhow::anyhow!("unknown disguise `{}`", name),
+            )),
+        }
+    }
+
+    /// ツイート 1 件分の行
+    pub fn lines(&self, tweet: &Tweet) -> Vec<String> {
+        use Disguise::*;
+
+        let text = tweet.text.replace(['\n', '\r'], " ");
+        let author = stable_hash(&tweet.author_id);
+        let id = stable_hash(&tweet.id);
+        let module = MODULES[(author % MODULES.len() as u64) as usize];
+        let line = 10 + id % 490;
+        let time = tweet.created_at_local().unwrap_or_else(Local::now);
+
+        match self {
+            Cargo => vec![
+                format!("warning: {}", text),
+                format!("  --> src/{}.rs:{}:{}", module, line, 5 + author % 40),
+            ],
+            Pytest => vec![format!(
+                "{} INFO     {}:{}.py:{} {}",
+                time.format("%H:%M:%S"),
+                module,
+                module,
+                line,
+                text
+            )],
+            Kubectl => vec![format!(
+                "[pod/{}-{}/app] {}",
+                module,
+                pod_suffix(author),
+                serde_json::json!({
+                    "level": "info",
+                    "ts": time.to_rfc3339_opts(SecondsFormat::Millis, false),
+                    "caller": format!("{}/handler.go:{}", module, line),
+                    "msg": text,
+                })
+            )],
+            Tail => vec![format!(
+                "{} build-01 {}[{}]: {}",
+                time.format("%b %e %H:%M:%S"),
+                module,
+                1000 + author % 30000,
+                text
+            )],
+        }
+    }
+}
+
+impl Disguise {
+    /// `real` で見つけたイベントの行。ツイートより目立つが、それらしく見えるようにする
+    pub fn event_line(&self, event: &MatchEvent) -> String {
+        use Disguise::*;
+
+        let kind = event.kind.as_str();
+        let time = event.detected_at.with_timezone(&Local);
+        match self {
+            Cargo => format!(
+                "error[E0{}]: `{}` exceeded its budget: {:.0} calls/min (expected {:.0}, z={:.1})",
+                600 + stable_hash(kind) % 100,
+                kind,
+                event.tweets_per_minute,
+                event.baseline,
+                event.score
+            ),
+            Pytest => format!(
+                "FAILED tests/test_{}.py::test_rate - assert {:.0} <= {:.0} (z={:.1})",
+                kind, event.tweets_per_minute, event.baseline, event.score
+            ),
+            Kubectl => format!(
+                "[pod/{}-monitor/alertmanager] ALERT firing rate={:.0}/min baseline={:.0} z={:.1}",
+                kind, event.tweets_per_minute, event.baseline, event.score
+            ),
+            Tail => format!(
+                "{} build-01 kernel: [{}] rate limit exceeded: {:.0}/min (z={:.1})",
+                time.format("%b %e %H:%M:%S"),
+                kind,
+                event.tweets_per_minute,
+                event.score
+            ),
+        }
+    }
+}
+
+/// 実行ごとに変わらないハッシュ (FNV-1a)
+pub fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `7d9f8c6b5-xk2lq` のような pod 名の後ろ
+fn pod_suffix(seed: u64) -> String {
+    const CHARS: &[u8] = b"bcdfghjklmnpqrstvwxz2456789";
+    let pick = |n: u64| CHARS[(n % CHARS.len() as u64) as usize] as char;
+    let replica_set = format!("{:x}", seed >> 28)
+        .chars()
+        .take(9)
+        .collect::<String>();
+    let pod = (0..5).map(|i| pick(seed >> (i * 5))).collect::<String>();
+    format!("{}-{}", replica_set, pod)
+}
+
+struct DisguiseRenderer {
+    out: Box<dyn Write + Send>,
+    disguise: Disguise,
+}
+
+impl Renderer for DisguiseRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        for line in self.disguise.lines(tweet) {
+            writeln!(self.out, "{}", line).map_err(ViewError::write_error)?;
+        }
+        self.out.flush().map_err(ViewError::write_error)?;
+        Ok(())
+    }
+}
+
+pub fn disguise_renderer(
+    disguise: Disguise,
+    out: Box<dyn Write + Send>,
+) -> Box<dyn Renderer + Send> {
+    Box::new(DisguiseRenderer { out, disguise })
+}
+
+/// パニックキーを押したときに流す偽のビルド
+pub struct FakeBuild {
+    disguise: Disguise,
+    step: usize,
+    started_at: DateTime<Local>,
+}
+
+impl FakeBuild {
+    pub fn new(disguise: Disguise) -> Self {
+        Self {
+            disguise,
+            step: 0,
+            started_at: Local::now(),
+        }
+    }
+
+    /// 最初に出す行
+    pub fn header(&self) -> Vec<String> {
+        use Disguise::*;
+
+        match self.disguise {
+            Cargo => vec!["    Updating crates.io index".to_string()],
+            Pytest => vec![
+                "============================= test session starts ==============================".to_string(),
+                "platform linux -- Python 3.10.6, pytest-7.2.0, pluggy-1.0.0".to_string(),
+                "collected 1284 items".to_string(),
+                String::new(),
+            ],
+            Kubectl => vec![format!(
+                "[pod/build-runner-{}/builder] #0 building with \"default\" instance using docker driver",
+                pod_suffix(self.started_at.timestamp() as u64)
+            )],
+            Tail => vec![format!(
+                "{} build-01 systemd[1]: Starting Nightly build...",
+                self.started_at.format("%b %e %H:%M:%S")
+            )],
+        }
+    }
+
+    pub fn next_line(&mut self) -> String {
+        use Disguise::*;
+
+        self.step += 1;
+        let step = self.step;
+        let module = MODULES[step % MODULES.len()];
+        match self.disguise {
+            Cargo => {
+                let (name, version) = CRATES[step % CRATES.len()];
+                if step.is_multiple_of(8) {
+                    let total = 311;
+                    let done = (step * 3) % total;
+                    let bar = 27 * done / total;
+                    format!(
+                        "    Building [{}>{}] {}/{}: {}",
+                        "=".repeat(bar),
+                        " ".repeat(27 - bar),
+                        done,
+                        total,
+                        name
+                    )
+                } else {
+                    format!("   Compiling {} v{}", name, version)
+                }
+            }
+            Pytest => format!(
+                "tests/test_{}.py::test_{}_{} PASSED{:>width$}",
+                module,
+                module,
+                step,
+                format!("[{:>3}%]", step % 100),
+                width = 30
+            ),
+            Kubectl => format!(
+                "[pod/build-runner-{}/builder] #{} [builder {}/12] RUN make -C {} -j8",
+                pod_suffix(self.started_at.timestamp() as u64),
+                step + 4,
+                step % 12 + 1,
+                module
+            ),
+            Tail => format!(
+                "{} build-01 make[{}]: Entering directory '/srv/build/{}'",
+                Local::now().format("%b %e %H:%M:%S"),
+                2 + step % 4,
+                module
+            ),
+        }
+    }
+}
+
+/// `real --disguise`。端末ではパニックキーで偽ビルドに切り替えられる
+pub async fn run_disguised(poller: &mut PollerHandle, disguise: Disguise) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        let mut renderer = disguise_renderer(disguise, Box::new(std::io::stdout()));
+        while let Some(event) = poller.events.recv().await {
+            match event {
+                PollEvent::Tweets { tweets, .. } => {
+                    for tweet in tweets {
+                        renderer.render(&tweet)?;
+                    }
+                }
+                PollEvent::Error {
+                    message,
+                    at,
+                    retry_in,
+                } => {
+                    eprintln!(
+                        "[{}] {}",
+                        at.format("%H:%M:%S"),
+                        describe_error(&message, retry_in)
+                    );
+                }
+                PollEvent::Match(event) => println!("{}", disguise.event_line(&event)),
+            }
+        }
+        return Ok(());
+    }
+
+    crossterm::terminal::enable_raw_mode().map_err(ViewError::terminal_error)?;
+    let result = panic_key_loop(poller, disguise).await;
+    crossterm::terminal::disable_raw_mode().map_err(ViewError::terminal_error)?;
+    result
+}
+
+fn clear_screen() -> Result<()> {
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+        crossterm::cursor::MoveTo(0, 0)
+    )
+    .map_err(ViewError::terminal_error)?;
+    Ok(())
+}
+
+async fn panic_key_loop(poller: &mut PollerHandle, disguise: Disguise) -> Result<()> {
+    let mut renderer = disguise_renderer(disguise, Box::new(CrLf(std::io::stdout())));
+    let mut out = CrLf(std::io::stdout());
+    let mut keys = EventStream::new();
+    let mut tick = tokio::time::interval(FAKE_BUILD_TICK);
+    // Some の間は偽ビルドを表示していて、ポーリングも止めている
+    let mut fake: Option<FakeBuild> = None;
+
+    loop {
+        tokio::select! {
+            event = poller.events.recv() => match event {
+                Some(PollEvent::Tweets { tweets, .. }) if fake.is_none() => {
+                    for tweet in tweets {
+                        renderer.render(&tweet)?;
+                    }
+                }
+                Some(PollEvent::Error { message, at, retry_in }) if fake.is_none() => {
+                    writeln!(
+                        CrLf(std::io::stderr()),
+                        "[{}] {}",
+                        at.format("%H:%M:%S"),
+                        describe_error(&message, retry_in)
+                    ).map_err(ViewError::write_error)?;
+                }
+                Some(PollEvent::Match(event)) if fake.is_none() => {
+                    writeln!(out, "{}", disguise.event_line(&event)).map_err(ViewError::write_error)?;
+                }
+                Some(_) => {}
+                None => return Ok(()),
+            },
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char(PANIC_KEY) => {
+                        clear_screen()?;
+                        fake = match fake {
+                            Some(_) => {
+                                poller.send(PollCommand::Resume);
+                                None
+                            }
+                            None => {
+                                poller.send(PollCommand::Pause);
+                                let build = FakeBuild::new(disguise);
+                                for line in build.header() {
+                                    writeln!(out, "{}", line).map_err(ViewError::write_error)?;
+                                }
+                                Some(build)
+                            }
+                        };
+                        out.flush().map_err(ViewError::write_error)?;
+                    }
+                    _ => {}
+                },
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(ViewError::terminal_error(err)),
+                None => return Ok(()),
+            },
+            _ = tick.tick(), if fake.is_some() => {
+                if let Some(build) = &mut fake {
+                    writeln!(out, "{}", build.next_line()).map_err(ViewError::write_error)?;
+                    out.flush().map_err(ViewError::write_error)?;
+                }
+            }
+        }
+    }
+}
+
+/// raw モードの端末では `\n` だけだと行頭に戻らないので `\r\n` にする
+pub struct CrLf<W: Write>(pub W);
+
+impl<W: Write> Write for CrLf<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for (i, chunk) in buf.split(|it| *it == b'\n').enumerate() {
+            if i > 0 {
+                self.0.write_all(b"\r\n")?;
+            }
+            self.0.write_all(chunk)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(text: &str) -> Tweet {
+        Tweet::new(
+            "1599817325123457024".to_string(),
+            text.to_string(),
+            "42".to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn it_should_tuck_text_into_message() {
+        let tweet = tweet("同点ゴール!\n\"PK\" 戦へ");
+        for name in Disguise::NAMES {
+            let lines = Disguise::parse(name).unwrap().lines(&tweet);
+            assert!(lines.iter().all(|line| !line.contains('\n')));
+            assert_eq!(lines, Disguise::parse(name).unwrap().lines(&tweet));
+        }
+
+        let cargo = Disguise::Cargo.lines(&tweet);
+        assert_eq!(cargo[0], "warning: 同点ゴール! \"PK\" 戦へ");
+        assert!(cargo[1].starts_with("  --> src/"));
+
+        let kubectl = Disguise::Kubectl.lines(&tweet).remove(0);
+        let json = kubectl.split_once("] ").unwrap().1;
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["msg"], "同点ゴール! \"PK\" 戦へ");
+    }
+
+    #[test]
+    fn it_should_convert_newlines_for_raw_mode() {
+        let mut out = CrLf(Vec::new());
+        write!(out, "a\nb\n").unwrap();
+        assert_eq!(out.0, b"a\r\nb\r\n");
+    }
+}
@@ -0,0 +1,222 @@
+use crate::error::*;
+use crate::view::ViewError;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbImage};
+use owo_colors::OwoColorize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// sixel のときに 1 セルを何ピクセルとみなすか
+const CELL_WIDTH_PX: u32 = 8;
+const CELL_HEIGHT_PX: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewProtocol {
+    /// `▀` の前景色と背景色で 1 セルに 2 ピクセル
+    HalfBlock,
+    Sixel,
+}
+
+impl PreviewProtocol {
+    pub const NAMES: [&'static str; 2] = ["halfblock", "sixel"];
+
+    pub fn parse(name: &str) -> Result<PreviewProtocol> {
+        match name {
+            "halfblock" => Ok(PreviewProtocol::HalfBlock),
+            "sixel" => Ok(PreviewProtocol::Sixel),
+            _ => Err(ServiceError::new(
+                ViewError::UnknownPreview,
+                anyhow::anyhow!("unknown preview `{}`", name),
+            )),
+        }
+    }
+}
+
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage> {
+    image::load_from_memory(bytes).map_err(|err| ServiceError::new(ViewError::InvalidImage, err))
+}
+
+/// `max_width` x `max_height` ピクセルに収まるように縦横比を保って縮める
+fn fit(image: &DynamicImage, max_width: u32, max_height: u32) -> RgbImage {
+    let (width, height) = (image.width().max(1), image.height().max(1));
+    let scale = (max_width as f64 / width as f64)
+        .min(max_height as f64 / height as f64)
+        .min(1.0);
+    let width = ((width as f64 * scale).round() as u32).max(1);
+    let height = ((height as f64 * scale).round() as u32).max(1);
+    image
+        .resize_exact(width, height, FilterType::Triangle)
+        .to_rgb8()
+}
+
+/// 1 セル分の (上, 下) の色。行ごと
+pub fn half_blocks(
+    image: &DynamicImage,
+    max_cols: u32,
+    max_rows: u32,
+) -> Vec<Vec<([u8; 3], [u8; 3])>> {
+    let image = fit(image, max_cols, max_rows * 2);
+    (0..image.height())
+        .step_by(2)
+        .map(|y| {
+            (0..image.width())
+                .map(|x| {
+                    let upper = image.get_pixel(x, y).0;
+                    // 高さが奇数なら最後の行の下は上と同じ色にする
+                    let lower = image.get_pixel(x, (y + 1).min(image.height() - 1)).0;
+                    (upper, lower)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// sixel のデータ。色は 6x6x6 に減らす
+pub fn sixel(image: &DynamicImage, max_cols: u32, max_rows: u32) -> String {
+    let image = fit(image, max_cols * CELL_WIDTH_PX, max_rows * CELL_HEIGHT_PX);
+    let level = |value: u8| value as usize * 6 / 256;
+    let index = |x: u32, y: u32| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        level(r) * 36 + level(g) * 6 + level(b)
+    };
+    let (width, height) = image.dimensions();
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    let used = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| index(x, y))
+        .collect::<BTreeSet<_>>();
+    for color in &used {
+        let percent = |level: usize| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            color,
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        );
+    }
+
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let colors = rows
+            .clone()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| index(x, y))
+            .collect::<BTreeSet<_>>();
+        for (i, color) in colors.into_iter().enumerate() {
+            if i > 0 {
+                // 同じ 6 行の頭に戻って次の色を重ねる
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+            let sixels = (0..width).map(|x| {
+                let bits = rows
+                    .clone()
+                    .filter(|y| index(x, *y) == color)
+                    .fold(0, |bits, y| bits | 1 << (y - top));
+                char::from(63 + bits as u8)
+            });
+            push_runs(&mut out, sixels);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// 同じ文字が 4 つ以上続いたら `!<count><char>` にまとめる
+fn push_runs(out: &mut String, chars: impl Iterator<Item = char>) {
+    fn flush(out: &mut String, run: Option<(char, usize)>) {
+        match run {
+            Some((c, count)) if count > 3 => {
+                let _ = write!(out, "!{}{}", count, c);
+            }
+            Some((c, count)) => out.extend(std::iter::repeat_n(c, count)),
+            None => {}
+        }
+    }
+
+    let mut run: Option<(char, usize)> = None;
+    for c in chars {
+        run = match run {
+            Some((prev, count)) if prev == c => Some((c, count + 1)),
+            _ => {
+                flush(out, run);
+                Some((c, 1))
+            }
+        };
+    }
+    flush(out, run);
+}
+
+/// 端末にそのまま出力できる行
+pub fn preview_lines(
+    bytes: &[u8],
+    protocol: PreviewProtocol,
+    max_cols: u32,
+    max_rows: u32,
+) -> Result<Vec<String>> {
+    let image = decode_image(bytes)?;
+    Ok(match protocol {
+        PreviewProtocol::HalfBlock => half_blocks(&image, max_cols, max_rows)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|([r, g, b], [lr, lg, lb])| {
+                        "▀".truecolor(r, g, b).on_truecolor(lr, lg, lb).to_string()
+                    })
+                    .collect()
+            })
+            .collect(),
+        PreviewProtocol::Sixel => vec![sixel(&image, max_cols, max_rows)],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb(color(x, y))))
+    }
+
+    #[test]
+    fn it_should_pack_two_rows_per_cell() {
+        let flag = image(
+            4,
+            4,
+            |_, y| if y < 2 { [255, 255, 255] } else { [188, 0, 45] },
+        );
+        let cells = half_blocks(&flag, 40, 20);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0], vec![([255, 255, 255], [255, 255, 255]); 4]);
+        assert_eq!(cells[1][0], ([188, 0, 45], [188, 0, 45]));
+
+        // 縦横比を保って収める
+        let wide = image(100, 10, |_, _| [0, 0, 0]);
+        let cells = half_blocks(&wide, 20, 20);
+        assert_eq!((cells[0].len(), cells.len()), (20, 1));
+    }
+
+    #[test]
+    fn it_should_encode_sixel() {
+        let red = image(1, 1, |_, _| [255, 0, 0]);
+        assert_eq!(
+            sixel(&red, 1, 1),
+            "\x1bPq\"1;1;1;1#180;2;100;0;0#180@-\x1b\\"
+        );
+
+        let stripe = image(
+            5,
+            2,
+            |_, y| if y == 0 { [0, 0, 0] } else { [255, 255, 255] },
+        );
+        assert_eq!(
+            sixel(&stripe, 1, 1),
+            "\x1bPq\"1;1;5;2#0;2;0;0;0#215;2;100;100;100#0!5@$#215!5A-\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+use crate::domain::model::Tweet;
+use crate::error::*;
+use crate::view::Theme;
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use std::io::Write;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// `Tweet` を serialize したときのキー。`author` は `@username (name)`
+pub const TWEET_FIELDS: &[&str] = &[
+    "id",
+    "text",
+    "author_id",
+    "created_at",
+    "conversation_id",
+    "entities",
+    "geo",
+    "in_reply_to_user_id",
+    "lang",
+    "possibly_sensitive",
+    "referenced_tweets",
+    "source",
+    "withheld",
+    "author",
+    "media",
+    "public_metrics",
+];
+
+/// csv / table で `--fields` を省略したときの列
+pub const DEFAULT_FIELDS: &[&str] = &["id", "author_id", "created_at", "text"];
+
+/// table の 1 セルの最大幅
+const MAX_CELL_WIDTH: usize = 60;
+
+#[derive(Debug)]
+pub enum ViewError {
+    UnknownFormat,
+    UnknownField,
+    /// `--fields` を選べない出力形式 (pretty)
+    FieldsNotSupported,
+    UnknownTheme,
+    UnknownPreview,
+    InvalidImage,
+    WriteError,
+    /// raw モードや全画面の切り替え、キー入力の読み取り
+    TerminalError,
+}
+
+impl ViewError {
+    /// 出力を書いているところの io エラーだけに使う
+    pub fn write_error(err: std::io::Error) -> ServiceError {
+        ServiceError::new(ViewError::WriteError, err)
+    }
+
+    pub fn terminal_error(err: std::io::Error) -> ServiceError {
+        ServiceError::new(ViewError::TerminalError, err)
+    }
+}
+
+impl IServiceError for ViewError {
+    fn error_type(&self) -> String {
+        use ViewError::*;
+
+        match self {
+            UnknownFormat => "unknown_output_format",
+            UnknownField => "unknown_field",
+            FieldsNotSupported => "fields_not_supported",
+            UnknownTheme => "unknown_theme",
+            UnknownPreview => "unknown_preview",
+            InvalidImage => "invalid_image",
+            WriteError => "write_error",
+            TerminalError => "terminal_error",
+        }
+        .to_string()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        use ViewError::*;
+
+        match self {
+            UnknownFormat | UnknownField | FieldsNotSupported | UnknownTheme | UnknownPreview => {
+                http::StatusCode::BAD_REQUEST
+            }
+            InvalidImage => http::StatusCode::UNPROCESSABLE_ENTITY,
+            WriteError | TerminalError => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<csv::Error> for ServiceError {
+    fn from(err: csv::Error) -> ServiceError {
+        ServiceError::new(ViewError::WriteError, err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Ndjson,
+    Csv,
+    Table,
+    Pretty,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 5] = ["json", "ndjson", "csv", "table", "pretty"];
+
+    pub fn parse(name: &str) -> Result<OutputFormat> {
+        use OutputFormat::*;
+
+        match name {
+            "json" => Ok(Json),
+            "ndjson" => Ok(Ndjson),
+            "csv" => Ok(Csv),
+            "table" => Ok(Table),
+            "pretty" => Ok(Pretty),
+            _ => Err(ServiceError::new(
+                ViewError::UnknownFormat,
+                anyhow::anyhow!("unknown output format `{}`", name),
+            )),
+        }
+    }
+}
+
+/// `--fields id,author_id,text` をパースする。空なら `None`
+pub fn parse_fields(fields: &str) -> Result<Option<Vec<String>>> {
+    let fields = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+        .map(|field| {
+            if TWEET_FIELDS.contains(&field) {
+                Ok(field.to_string())
+            } else {
+                Err(ServiceError::new(
+                    ViewError::UnknownField,
+                    anyhow::anyhow!(
+                        "unknown field `{}` (available: {})",
+                        field,
+                        TWEET_FIELDS.join(",")
+                    ),
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(if fields.is_empty() {
+        None
+    } else {
+        Some(fields)
+    })
+}
+
+pub trait Renderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()>;
+    /// バッファしている出力を書き出す
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct RendererOptions {
+    pub format: OutputFormat,
+    pub fields: Option<Vec<String>>,
+    pub theme: Option<Theme>,
+    pub indent: String,
+}
+
+pub fn renderer(options: RendererOptions, out: Box<dyn Write + Send>) -> Box<dyn Renderer + Send> {
+    use OutputFormat::*;
+
+    let all_fields = || TWEET_FIELDS.iter().map(|it| it.to_string()).collect();
+    let default_fields = || DEFAULT_FIELDS.iter().map(|it| it.to_string()).collect();
+
+    match options.format {
+        Json => Box::new(JsonRenderer {
+            out,
+            fields: options.fields.unwrap_or_else(all_fields),
+            rows: Vec::new(),
+        }),
+        Ndjson => Box::new(NdjsonRenderer {
+            out,
+            fields: options.fields.unwrap_or_else(all_fields),
+        }),
+        Csv => Box::new(CsvRenderer {
+            writer: csv::Writer::from_writer(out),
+            fields: options.fields.unwrap_or_else(default_fields),
+            header_written: false,
+        }),
+        Table => Box::new(TableRenderer {
+            out,
+            fields: options.fields.unwrap_or_else(default_fields),
+            rows: Vec::new(),
+        }),
+        Pretty => Box::new(PrettyRenderer {
+            out,
+            theme: options.theme,
+            indent: options.indent,
+        }),
+    }
+}
+
+/// 指定されたキーだけを指定された順に残す
+fn select(tweet: &Tweet, fields: &[String]) -> Result<IndexMap<String, serde_json::Value>> {
+    let value = serde_json::to_value(tweet).map_err(GeneralError::serialization_error)?;
+    let mut object = match value {
+        serde_json::Value::Object(object) => object,
+        _ => serde_json::Map::new(),
+    };
+    Ok(fields
+        .iter()
+        .map(|field| {
+            let value = match field.as_str() {
+                // どの形式でも `@username (name)` で出す
+                "author" => serde_json::Value::String(tweet.author_label()),
+                _ => object.remove(field).unwrap_or(serde_json::Value::Null),
+            };
+            (field.clone(), value)
+        })
+        .collect())
+}
+
+/// csv / table 用に 1 セルの文字列にする
+fn cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct JsonRenderer {
+    out: Box<dyn Write + Send>,
+    fields: Vec<String>,
+    rows: Vec<IndexMap<String, serde_json::Value>>,
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        self.rows.push(select(tweet, &self.fields)?);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let rows = std::mem::take(&mut self.rows);
+        serde_json::to_writer_pretty(&mut self.out, &rows)
+            .map_err(GeneralError::serialization_error)?;
+        writeln!(self.out).map_err(ViewError::write_error)?;
+        Ok(())
+    }
+}
+
+struct NdjsonRenderer {
+    out: Box<dyn Write + Send>,
+    fields: Vec<String>,
+}
+
+impl Renderer for NdjsonRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        serde_json::to_writer(&mut self.out, &select(tweet, &self.fields)?)
+            .map_err(GeneralError::serialization_error)?;
+        writeln!(self.out).map_err(ViewError::write_error)?;
+        self.out.flush().map_err(ViewError::write_error)?;
+        Ok(())
+    }
+}
+
+struct CsvRenderer {
+    writer: csv::Writer<Box<dyn Write + Send>>,
+    fields: Vec<String>,
+    header_written: bool,
+}
+
+impl Renderer for CsvRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        if !self.header_written {
+            self.writer.write_record(&self.fields)?;
+            self.header_written = true;
+        }
+        let row = select(tweet, &self.fields)?;
+        self.writer.write_record(row.values().map(cell))?;
+        self.writer.flush().map_err(ViewError::write_error)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if !self.header_written {
+            self.writer.write_record(&self.fields)?;
+            self.header_written = true;
+        }
+        self.writer.flush().map_err(ViewError::write_error)?;
+        Ok(())
+    }
+}
+
+struct TableRenderer {
+    out: Box<dyn Write + Send>,
+    fields: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// 改行を潰して `width` (表示幅) に収まるように切り詰める
+fn truncate(s: &str, width: usize) -> String {
+    let s = s.replace(['\n', '\r', '\t'], " ");
+    if s.width() <= width {
+        return s;
+    }
+    let mut result = String::new();
+    let mut current = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if current + w + 1 > width {
+            break;
+        }
+        result.push(c);
+        current += w;
+    }
+    result.push('…');
+    result
+}
+
+fn pad(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(s.width())))
+}
+
+impl Renderer for TableRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        let row = select(tweet, &self.fields)?;
+        self.rows.push(
+            row.values()
+                .map(|value| truncate(&cell(value), MAX_CELL_WIDTH))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let widths = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].width())
+                    .chain(std::iter::once(field.width()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| pad(cell, *width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        writeln!(self.out, "{}", line(&self.fields)).map_err(ViewError::write_error)?;
+        let separator = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+        writeln!(self.out, "{}", line(&separator)).map_err(ViewError::write_error)?;
+        for row in std::mem::take(&mut self.rows) {
+            writeln!(self.out, "{}", line(&row)).map_err(ViewError::write_error)?;
+        }
+        Ok(())
+    }
+}
+
+struct PrettyRenderer {
+    out: Box<dyn Write + Send>,
+    theme: Option<Theme>,
+    indent: String,
+}
+
+impl Renderer for PrettyRenderer {
+    fn render(&mut self, tweet: &Tweet) -> Result<()> {
+        match &self.theme {
+            Some(theme) => {
+                let author = tweet
+                    .author_label()
+                    .color(theme.author_color(&tweet.author_id))
+                    .bold()
+                    .to_string();
+                let rules = tweet.rule_labels().yellow().to_string();
+                match theme.text_color() {
+                    Some(text) => writeln!(
+                        self.out,
+                        "{}{}{} {}",
+                        self.indent,
+                        rules,
+                        author,
+                        tweet.text.color(text)
+                    )
+                    .map_err(ViewError::write_error)?,
+                    None => writeln!(
+                        self.out,
+                        "{}{}{} {}",
+                        self.indent, rules, author, tweet.text
+                    )
+                    .map_err(ViewError::write_error)?,
+                }
+            }
+            None => writeln!(
+                self.out,
+                "{}{}{} {}",
+                self.indent,
+                tweet.rule_labels(),
+                tweet.author_label(),
+                tweet.text
+            )
+            .map_err(ViewError::write_error)?,
+        }
+        for media in &tweet.media {
+            match &self.theme {
+                Some(_) => writeln!(self.out, "{}  {}", self.indent, media.summary().dimmed())
+                    .map_err(ViewError::write_error)?,
+                None => writeln!(self.out, "{}  {}", self.indent, media.summary())
+                    .map_err(ViewError::write_error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn tweet(id: &str, text: &str) -> Tweet {
+        Tweet::new(
+            id.to_string(),
+            text.to_string(),
+            "42".to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn render(format: &str, fields: &str, tweets: &[Tweet]) -> String {
+        let buffer = Buffer::default();
+        let mut renderer = renderer(
+            RendererOptions {
+                format: OutputFormat::parse(format).unwrap(),
+                fields: parse_fields(fields).unwrap(),
+                theme: None,
+                indent: String::new(),
+            },
+            Box::new(buffer.clone()),
+        );
+        for tweet in tweets {
+            renderer.render(tweet).unwrap();
+        }
+        renderer.finish().unwrap();
+        buffer.contents()
+    }
+
+    #[test]
+    fn it_should_render_ndjson_with_selected_fields() {
+        let out = render(
+            "ndjson",
+            "id,text",
+            &[tweet("1", "ゴール!"), tweet("2", "PK")],
+        );
+        assert_eq!(
+            out,
+            "{\"id\":\"1\",\"text\":\"ゴール!\"}\n{\"id\":\"2\",\"text\":\"PK\"}\n"
+        );
+    }
+
+    #[test]
+    fn it_should_render_json_array() {
+        let out = render("json", "id", &[tweet("1", "a")]);
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value, serde_json::json!([{ "id": "1" }]));
+    }
+
+    #[test]
+    fn it_should_render_csv_with_quotes() {
+        let out = render("csv", "", &[tweet("1", "日本, 勝った\n!")]);
+        assert_eq!(
+            out,
+            "id,author_id,created_at,text\n1,42,2022-12-05T15:00:00.000Z,\"日本, 勝った\n!\"\n"
+        );
+    }
+
+    #[test]
+    fn it_should_align_table_by_display_width() {
+        let out = render("table", "id,text", &[tweet("1", "日本"), tweet("22", "ab")]);
+        assert_eq!(out, "id  text\n--  ----\n1   日本\n22  ab\n");
+    }
+
+    #[test]
+    fn it_should_reject_unknown_field() {
+        let err = parse_fields("id,likes").unwrap_err();
+        assert!(err.is_error_of(ViewError::UnknownField));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::*;
+use crate::view::{stable_hash, ViewError};
+use clap::ColorChoice;
+use palette::color_difference::Wcag21RelativeContrast;
+use palette::{Clamp, FromColor, Oklch, Srgb};
+use std::io::IsTerminal;
+
+/// `plain` は色なし
+pub const THEME_NAMES: [&str; 4] = ["samurai-blue", "monochrome", "high-contrast", "plain"];
+
+/// 端末の背景。わからないときは暗い方にしておく
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// `COLORFGBG=15;0` のような値 (rxvt 系や一部の端末が設定する) から判定する
+    pub fn detect() -> Background {
+        Self::from_colorfgbg(std::env::var("COLORFGBG").ok().as_deref())
+    }
+
+    fn from_colorfgbg(value: Option<&str>) -> Background {
+        let bg = value
+            .and_then(|it| it.rsplit(';').next())
+            .and_then(|it| it.parse::<u8>().ok());
+        match bg {
+            Some(7) | Some(9..=15) => Background::Light,
+            _ => Background::Dark,
+        }
+    }
+
+    fn color(&self) -> Srgb<f32> {
+        match self {
+            Background::Dark => Srgb::new(0x1eu8, 0x1e, 0x1e).into_format(),
+            Background::Light => Srgb::new(0xfau8, 0xfa, 0xfa).into_format(),
+        }
+    }
+}
+
+/// 著者ごとの色は `author_id` のハッシュから決めるので、起動し直しても変わらない
+#[derive(Clone, Debug)]
+pub struct Theme {
+    background: Background,
+    /// 著者の色相の範囲 (度)
+    hues: (f32, f32),
+    chroma: f32,
+    lightness: f32,
+    /// 著者ごとに明るさをずらす幅
+    lightness_spread: f32,
+    /// 本文の色。`None` は端末の文字色
+    text: Option<(Srgb<u8>, Srgb<u8>)>,
+    /// 背景とのコントラスト比 (WCAG 2.1) の下限
+    min_contrast: f32,
+}
+
+impl Theme {
+    /// `plain` なら `None`
+    pub fn named(name: &str, background: Background) -> Result<Option<Theme>> {
+        let theme = match name {
+            "samurai-blue" => Theme {
+                background,
+                hues: (200.0, 275.0),
+                chroma: 0.13,
+                lightness: 0.72,
+                lightness_spread: 0.08,
+                text: Some((Srgb::new(0xe6, 0xec, 0xf5), Srgb::new(0x10, 0x1e, 0x3c))),
+                min_contrast: 4.5,
+            },
+            "monochrome" => Theme {
+                background,
+                hues: (0.0, 0.0),
+                chroma: 0.0,
+                lightness: 0.78,
+                lightness_spread: 0.15,
+                text: None,
+                min_contrast: 4.5,
+            },
+            "high-contrast" => Theme {
+                background,
+                hues: (0.0, 360.0),
+                chroma: 0.17,
+                lightness: 0.85,
+                lightness_spread: 0.0,
+                text: Some((Srgb::new(0xff, 0xff, 0xff), Srgb::new(0x00, 0x00, 0x00))),
+                min_contrast: 7.0,
+            },
+            "plain" => return Ok(None),
+            // 以前の設定ファイルにはランダムな配色の `random` が書かれている
+            "random" => {
+                return Err(ServiceError::new(
+                    ViewError::UnknownTheme,
+                    anyhow::anyhow!(
+                        "theme `random` was removed; use `samurai-blue` instead (available: {})",
+                        THEME_NAMES.join(", ")
+                    ),
+                ))
+            }
+            _ => {
+                return Err(ServiceError::new(
+                    ViewError::UnknownTheme,
+                    anyhow::anyhow!(
+                        "unknown theme `{}` (available: {})",
+                        name,
+                        THEME_NAMES.join(", ")
+                    ),
+                ))
+            }
+        };
+        Ok(Some(theme))
+    }
+
+    pub fn author_color(&self, author_id: &str) -> owo_colors::Rgb {
+        let hash = stable_hash(author_id);
+        let (from, to) = self.hues;
+        let hue = from + (to - from) * (hash % 1024) as f32 / 1024.0;
+        let offset = ((hash >> 10) % 1024) as f32 / 1023.0 * 2.0 - 1.0;
+        let mut lightness = self.lightness + self.lightness_spread * offset;
+
+        // 背景と見分けがつくまで明るさを背景から遠ざける
+        let step = match self.background {
+            Background::Dark => 0.03,
+            Background::Light => -0.03,
+        };
+        if self.background == Background::Light {
+            lightness = 1.0 - lightness;
+        }
+        let mut color = self.to_srgb(lightness, hue);
+        while color.relative_contrast(self.background.color()) < self.min_contrast
+            && (0.0..=1.0).contains(&(lightness + step))
+        {
+            lightness += step;
+            color = self.to_srgb(lightness, hue);
+        }
+        to_owo(color.into_format())
+    }
+
+    pub fn text_color(&self) -> Option<owo_colors::Rgb> {
+        self.text.map(|(dark, light)| match self.background {
+            Background::Dark => to_owo(dark),
+            Background::Light => to_owo(light),
+        })
+    }
+
+    fn to_srgb(&self, lightness: f32, hue: f32) -> Srgb<f32> {
+        Srgb::from_color(Oklch::new(lightness, self.chroma, hue)).clamp()
+    }
+}
+
+fn to_owo(color: Srgb<u8>) -> owo_colors::Rgb {
+    owo_colors::Rgb(color.red, color.green, color.blue)
+}
+
+/// `--color auto` のときは `NO_COLOR` が空でなく設定されているか、端末でなければ色なし
+pub fn color_enabled(choice: ColorChoice, no_color: Option<&str>, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => no_color.is_none_or(str::is_empty) && is_terminal,
+    }
+}
+
+/// 設定のテーマ名と `--color` から、使うテーマを決める。色なしなら `None`
+pub fn theme(name: &str, choice: ColorChoice) -> Result<Option<Theme>> {
+    // 色を使わないときもテーマ名の間違いは教える
+    let theme = Theme::named(name, Background::detect())?;
+    let no_color = std::env::var("NO_COLOR").ok();
+    if color_enabled(choice, no_color.as_deref(), std::io::stdout().is_terminal()) {
+        Ok(theme)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contrast(color: owo_colors::Rgb, background: Background) -> f32 {
+        let owo_colors::Rgb(r, g, b) = color;
+        Srgb::new(r, g, b)
+            .into_format::<f32>()
+            .relative_contrast(background.color())
+    }
+
+    #[test]
+    fn it_should_pick_stable_readable_author_colors() {
+        for name in ["samurai-blue", "monochrome", "high-contrast"] {
+            for background in [Background::Dark, Background::Light] {
+                let theme = Theme::named(name, background).unwrap().unwrap();
+                for author in 0..200 {
+                    let author = (author * 7919).to_string();
+                    let color = theme.author_color(&author);
+                    assert_eq!(color, theme.author_color(&author));
+                    assert!(
+                        contrast(color, background) >= theme.min_contrast,
+                        "{} on {:?}: {:?}",
+                        name,
+                        background,
+                        color
+                    );
+                }
+            }
+        }
+
+        let theme = Theme::named("samurai-blue", Background::Dark)
+            .unwrap()
+            .unwrap();
+        assert_ne!(theme.author_color("1"), theme.author_color("2"));
+    }
+
+    #[test]
+    fn it_should_handle_theme_names() {
+        assert!(Theme::named("plain", Background::Dark).unwrap().is_none());
+        let err = Theme::named("rainbow", Background::Dark).unwrap_err();
+        assert!(err.is_error_of(ViewError::UnknownTheme));
+        let err = Theme::named("random", Background::Dark).unwrap_err();
+        assert!(err.is_error_of(ViewError::UnknownTheme));
+        assert!(err.to_string().contains("use `samurai-blue` instead"));
+    }
+
+    #[test]
+    fn it_should_respect_no_color_and_color_choice() {
+        assert!(color_enabled(ColorChoice::Auto, None, true));
+        assert!(color_enabled(ColorChoice::Auto, Some(""), true));
+        assert!(!color_enabled(ColorChoice::Auto, Some("1"), true));
+        assert!(!color_enabled(ColorChoice::Auto, None, false));
+        assert!(color_enabled(ColorChoice::Always, Some("1"), false));
+        assert!(!color_enabled(ColorChoice::Never, None, true));
+    }
+
+    #[test]
+    fn it_should_detect_background() {
+        assert_eq!(Background::from_colorfgbg(None), Background::Dark);
+        assert_eq!(Background::from_colorfgbg(Some("15;0")), Background::Dark);
+        assert_eq!(Background::from_colorfgbg(Some("0;15")), Background::Light);
+        assert_eq!(
+            Background::from_colorfgbg(Some("0;default;15")),
+            Background::Light
+        );
+    }
+}
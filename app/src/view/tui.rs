@@ -0,0 +1,82 @@
+mod state;
+pub use state::*;
+
+mod ui;
+
+use crate::domain::service::{MediaService, PollCommand, PollerHandle};
+use crate::error::*;
+use crate::view::{decode_image, Theme, ViewError};
+use crossterm::event::{Event, EventStream, KeyEventKind};
+use futures::StreamExt;
+use ratatui::DefaultTerminal;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 全画面表示。`q` で終わるまで戻らない
+pub async fn run(
+    poller: &mut PollerHandle,
+    query: String,
+    theme: Option<Theme>,
+    media: MediaService,
+) -> Result<()> {
+    // panic したときも端末を元に戻すフックが入る
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, poller, TuiState::new(query), theme, media).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    poller: &mut PollerHandle,
+    mut state: TuiState,
+    theme: Option<Theme>,
+    media: MediaService,
+) -> Result<()> {
+    let mut keys = EventStream::new();
+    let (preview_tx, mut preview_rx) = mpsc::unbounded_channel();
+    // ステータスバーの tweets/min を更新するため
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        terminal
+            .draw(|frame| ui::draw(frame, &mut state, theme.as_ref()))
+            .map_err(ViewError::terminal_error)?;
+
+        // 選んだツイートの画像は裏で取ってくる
+        for pending in state.take_pending_media() {
+            let media = media.clone();
+            let preview_tx = preview_tx.clone();
+            tokio::spawn(async move {
+                let image = media
+                    .preview_image(&pending)
+                    .await
+                    .and_then(|bytes| decode_image(&bytes.unwrap_or_default()));
+                let _ = preview_tx.send((pending.media_key, image));
+            });
+        }
+
+        tokio::select! {
+            event = poller.events.recv() => match event {
+                Some(event) => state.on_poll(event),
+                None => return Ok(()),
+            },
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    match state.on_key(key) {
+                        Action::None => {}
+                        Action::Quit => return Ok(()),
+                        Action::Pause => poller.send(PollCommand::Pause),
+                        Action::Resume => poller.send(PollCommand::Resume),
+                        Action::SetQuery(query) => poller.send(PollCommand::SetQuery(query)),
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(ViewError::terminal_error(err)),
+                None => return Ok(()),
+            },
+            Some((media_key, image)) = preview_rx.recv() => state.on_preview(media_key, image),
+            _ = tick.tick() => {}
+        }
+    }
+}
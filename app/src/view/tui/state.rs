@@ -0,0 +1,350 @@
+use crate::domain::model::{MatchEvent, Media, RateLimit, SearchQuery, Tweet};
+use crate::domain::service::{describe_error, PollEvent};
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::widgets::ListState;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// 古いものから捨てる
+const MAX_TWEETS: usize = 1000;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const PAGE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    EditingQuery(String),
+}
+
+/// 添付のプレビュー画像
+pub enum Preview {
+    Loading,
+    Ready(image::DynamicImage),
+    Failed(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    Pause,
+    Resume,
+    SetQuery(SearchQuery),
+}
+
+pub struct TuiState {
+    /// 新しい順
+    pub tweets: Vec<Tweet>,
+    pub list: ListState,
+    pub query: String,
+    pub paused: bool,
+    pub mode: Mode,
+    pub last_poll: Option<DateTime<Local>>,
+    pub rate_limit: Option<RateLimit>,
+    pub last_error: Option<(DateTime<Local>, String)>,
+    pub error_count: usize,
+    /// 見つけたイベント (新しい順)
+    pub events: Vec<MatchEvent>,
+    /// `media_key` ごと
+    pub previews: HashMap<String, Preview>,
+    arrivals: VecDeque<(Instant, usize)>,
+}
+
+impl TuiState {
+    pub fn new(query: String) -> Self {
+        Self {
+            tweets: Vec::new(),
+            list: ListState::default(),
+            query,
+            paused: false,
+            mode: Mode::Normal,
+            last_poll: None,
+            rate_limit: None,
+            last_error: None,
+            error_count: 0,
+            events: Vec::new(),
+            previews: HashMap::new(),
+            arrivals: VecDeque::new(),
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Tweet> {
+        self.list.selected().and_then(|i| self.tweets.get(i))
+    }
+
+    pub fn tweets_per_minute(&self) -> usize {
+        let now = Instant::now();
+        self.arrivals
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= RATE_WINDOW)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn on_poll(&mut self, event: PollEvent) {
+        match event {
+            PollEvent::Tweets {
+                tweets,
+                polled_at,
+                rate_limit,
+            } => {
+                self.last_poll = Some(polled_at);
+                self.rate_limit = rate_limit.or(self.rate_limit);
+                self.push_tweets(tweets);
+            }
+            PollEvent::Error {
+                message,
+                at,
+                retry_in,
+            } => {
+                self.error_count += 1;
+                self.last_error = Some((at, describe_error(&message, retry_in)));
+            }
+            PollEvent::Match(event) => self.events.insert(0, event),
+        }
+    }
+
+    /// 選んでいるツイートの添付で、まだ取りに行っていないもの。取りに行ったことにする
+    pub fn take_pending_media(&mut self) -> Vec<Media> {
+        let Some(tweet) = self.list.selected().and_then(|i| self.tweets.get(i)) else {
+            return Vec::new();
+        };
+        let pending = tweet
+            .media
+            .iter()
+            .filter(|media| media.image_url().is_some())
+            .filter(|media| !self.previews.contains_key(&media.media_key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for media in &pending {
+            self.previews
+                .insert(media.media_key.clone(), Preview::Loading);
+        }
+        pending
+    }
+
+    pub fn on_preview(
+        &mut self,
+        media_key: String,
+        image: crate::error::Result<image::DynamicImage>,
+    ) {
+        let preview = match image {
+            Ok(image) => Preview::Ready(image),
+            Err(err) => Preview::Failed(err.to_string()),
+        };
+        self.previews.insert(media_key, preview);
+    }
+
+    /// イベントのきっかけになったツイートなら、そのイベント
+    pub fn event_of(&self, tweet: &Tweet) -> Option<&MatchEvent> {
+        self.events
+            .iter()
+            .find(|event| event.sample_tweet_id.as_deref() == Some(tweet.id.as_str()))
+    }
+
+    fn push_tweets(&mut self, tweets: Vec<Tweet>) {
+        let count = tweets.len();
+        let now = Instant::now();
+        self.arrivals.push_back((now, count));
+        while let Some((at, _)) = self.arrivals.front() {
+            if now.duration_since(*at) > RATE_WINDOW {
+                self.arrivals.pop_front();
+            } else {
+                break;
+            }
+        }
+        if count == 0 {
+            return;
+        }
+
+        self.tweets.splice(0..0, tweets);
+        self.tweets.truncate(MAX_TWEETS);
+
+        // 先頭を見ているときは最新を追いかけ、スクロール中は同じツイートを選んだままにする
+        let selected = match self.list.selected() {
+            None | Some(0) => 0,
+            Some(i) => (i + count).min(self.tweets.len() - 1),
+        };
+        self.list.select(Some(selected));
+    }
+
+    fn scroll(&mut self, delta: isize) {
+        if self.tweets.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.tweets.len() as isize - 1);
+        self.list.select(Some(next as usize));
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match &mut self.mode {
+            Mode::EditingQuery(input) => match key.code {
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    Action::None
+                }
+                KeyCode::Enter => {
+                    let input = input.trim().to_string();
+                    self.mode = Mode::Normal;
+                    // 入力はそのままクエリとして使う (-is:retweet も自分で書く)
+                    let query = SearchQuery::new().raw(&input).include_retweets(true);
+                    match query.build() {
+                        Ok(_) => {
+                            self.query = input;
+                            self.tweets.clear();
+                            self.events.clear();
+                            self.list.select(None);
+                            Action::SetQuery(query)
+                        }
+                        Err(err) => {
+                            self.error_count += 1;
+                            self.last_error = Some((Local::now(), err.to_string()));
+                            Action::None
+                        }
+                    }
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                    Action::None
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    Action::None
+                }
+                _ => Action::None,
+            },
+            Mode::Normal => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+                KeyCode::Char('j') | KeyCode::Down => {
+                    self.scroll(1);
+                    Action::None
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.scroll(-1);
+                    Action::None
+                }
+                KeyCode::PageDown => {
+                    self.scroll(PAGE as isize);
+                    Action::None
+                }
+                KeyCode::PageUp => {
+                    self.scroll(-(PAGE as isize));
+                    Action::None
+                }
+                KeyCode::Char('g') | KeyCode::Home => {
+                    if !self.tweets.is_empty() {
+                        self.list.select(Some(0));
+                    }
+                    Action::None
+                }
+                KeyCode::Char('G') | KeyCode::End => {
+                    if !self.tweets.is_empty() {
+                        self.list.select(Some(self.tweets.len() - 1));
+                    }
+                    Action::None
+                }
+                KeyCode::Char('p') | KeyCode::Char(' ') => {
+                    self.paused = !self.paused;
+                    if self.paused {
+                        Action::Pause
+                    } else {
+                        Action::Resume
+                    }
+                }
+                KeyCode::Char('/') => {
+                    self.mode = Mode::EditingQuery(self.query.clone());
+                    Action::None
+                }
+                _ => Action::None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: &str) -> Tweet {
+        Tweet::new(
+            id.to_string(),
+            format!("tweet {}", id),
+            "42".to_string(),
+            "2022-12-05T15:00:00.000Z".to_string(),
+            None,
+            None,
+            None,
+            Some("ja".to_string()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn polled(ids: &[&str]) -> PollEvent {
+        PollEvent::Tweets {
+            tweets: ids.iter().map(|id| tweet(id)).collect(),
+            polled_at: Local::now(),
+            rate_limit: None,
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn it_should_keep_selection_while_scrolled() {
+        let mut state = TuiState::new("q".to_string());
+        state.on_poll(polled(&["3", "2", "1"]));
+        assert_eq!(state.selected().unwrap().id, "3");
+
+        state.on_key(key(KeyCode::Down));
+        assert_eq!(state.selected().unwrap().id, "2");
+
+        state.on_poll(polled(&["5", "4"]));
+        assert_eq!(state.selected().unwrap().id, "2");
+        assert_eq!(state.tweets_per_minute(), 5);
+
+        state.on_key(key(KeyCode::Char('g')));
+        assert_eq!(state.selected().unwrap().id, "5");
+    }
+
+    #[test]
+    fn it_should_change_query() {
+        let mut state = TuiState::new("ワールドカップ".to_string());
+        state.on_key(key(KeyCode::Char('/')));
+        for _ in 0.."ワールドカップ".chars().count() {
+            state.on_key(key(KeyCode::Backspace));
+        }
+        for c in "#SAMURAIBLUE".chars() {
+            state.on_key(key(KeyCode::Char(c)));
+        }
+
+        let action = state.on_key(key(KeyCode::Enter));
+        assert_eq!(
+            action,
+            Action::SetQuery(
+                SearchQuery::new()
+                    .raw("#SAMURAIBLUE")
+                    .include_retweets(true)
+            )
+        );
+        assert_eq!(state.query, "#SAMURAIBLUE");
+        assert_eq!(state.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn it_should_toggle_pause() {
+        let mut state = TuiState::new("q".to_string());
+        assert_eq!(state.on_key(key(KeyCode::Char('p'))), Action::Pause);
+        assert_eq!(state.on_key(key(KeyCode::Char('p'))), Action::Resume);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::domain::model::Tweet;
+use crate::view::tui::{Mode, Preview, TuiState};
+use crate::view::{half_blocks, Theme};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
+use ratatui::Frame;
+
+const EVENT_STYLE: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::Yellow)
+    .add_modifier(Modifier::BOLD);
+/// 詳細欄のプレビューの最大の高さ (セル)
+const PREVIEW_ROWS: u32 = 12;
+const HELP: &str = "q:quit j/k:scroll g:newest p:pause /:query";
+
+pub fn draw(frame: &mut Frame, state: &mut TuiState, theme: Option<&Theme>) {
+    let editing = matches!(state.mode, Mode::EditingQuery(_));
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(if editing { 1 } else { 0 }),
+        ])
+        .split(frame.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(rows[0]);
+
+    draw_list(frame, state, theme, columns[0]);
+    draw_detail(frame, state, columns[1]);
+    frame.render_widget(status_bar(state), rows[1]);
+
+    if let Mode::EditingQuery(input) = &state.mode {
+        let prompt = format!("query> {}", input);
+        let width = unicode_width::UnicodeWidthStr::width(prompt.as_str()) as u16;
+        frame.render_widget(Paragraph::new(prompt), rows[2]);
+        frame.set_cursor_position((rows[2].x + width, rows[2].y));
+    }
+}
+
+fn to_color(color: owo_colors::Rgb) -> Color {
+    let owo_colors::Rgb(r, g, b) = color;
+    Color::Rgb(r, g, b)
+}
+
+fn draw_list(
+    frame: &mut Frame,
+    state: &mut TuiState,
+    theme: Option<&Theme>,
+    area: ratatui::layout::Rect,
+) {
+    let items = state
+        .tweets
+        .iter()
+        .map(|tweet| {
+            let time = tweet
+                .created_at_local()
+                .map(|it| it.format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            let text = tweet.text.replace('\n', " ");
+            let mut author = Style::default().add_modifier(Modifier::BOLD);
+            let mut body = Style::default();
+            if let Some(theme) = theme {
+                author = author.fg(to_color(theme.author_color(&tweet.author_id)));
+                if let Some(text) = theme.text_color() {
+                    body = body.fg(to_color(text));
+                }
+            }
+            let mut spans = vec![
+                Span::styled(time, Style::default().fg(Color::DarkGray)),
+                Span::raw(" "),
+            ];
+            if let Some(event) = state.event_of(tweet) {
+                spans.push(Span::styled(
+                    format!("⚽ {} ", event.kind.label()),
+                    EVENT_STYLE,
+                ));
+            }
+            if !tweet.matching_rules.is_empty() {
+                spans.push(Span::styled(
+                    tweet.rule_labels(),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            spans.extend([
+                Span::styled(tweet.author_label(), author),
+                Span::raw(" "),
+                Span::styled(text, body),
+            ]);
+            ListItem::new(Line::from(spans))
+        })
+        .collect::<Vec<_>>();
+
+    let title = format!(" tweets ({}) ", state.tweets.len());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut state.list);
+}
+
+fn detail_lines(tweet: &Tweet) -> Vec<Line<'static>> {
+    let label =
+        |name: &str| Span::styled(format!("{:<10}", name), Style::default().fg(Color::Yellow));
+    let field = |name: &str, value: String| Line::from(vec![label(name), Span::raw(value)]);
+
+    let mut lines = tweet
+        .text
+        .lines()
+        .map(|line| Line::from(line.to_string()))
+        .collect::<Vec<_>>();
+    lines.push(Line::from(""));
+    lines.push(field("id", tweet.id.clone()));
+    lines.push(field("author", tweet.author_label()));
+    if let Some(user) = &tweet.author {
+        lines.push(field("author id", user.id.clone()));
+        if let Some(location) = &user.location {
+            lines.push(field("location", location.clone()));
+        }
+    }
+    lines.push(field(
+        "created",
+        tweet
+            .created_at_local()
+            .map(|it| it.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| tweet.created_at.clone()),
+    ));
+    lines.push(field("lang", tweet.lang.clone().unwrap_or_default()));
+    lines.push(field("source", tweet.source.clone().unwrap_or_default()));
+    if let Some(user) = &tweet.in_reply_to_user_id {
+        lines.push(field("reply to", user.clone()));
+    }
+
+    let hashtags = tweet.hashtags();
+    if !hashtags.is_empty() {
+        lines.push(field(
+            "hashtags",
+            hashtags
+                .iter()
+                .map(|it| format!("#{}", it))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ));
+    }
+    let mentions = tweet.mentions();
+    if !mentions.is_empty() {
+        lines.push(field(
+            "mentions",
+            mentions
+                .iter()
+                .map(|it| format!("@{}", it))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ));
+    }
+    for url in tweet.urls() {
+        lines.push(field("url", url));
+    }
+    for (kind, id) in tweet.references() {
+        lines.push(field(&kind, id));
+    }
+    lines
+}
+
+/// 添付ごとに概要と、取れていればプレビュー
+fn media_lines(tweet: &Tweet, state: &TuiState, width: u16) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for media in &tweet.media {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            media.summary(),
+            Style::default().fg(Color::Yellow),
+        ));
+        match state.previews.get(&media.media_key) {
+            Some(Preview::Loading) => lines.push(Line::styled(
+                "loading...",
+                Style::default().fg(Color::DarkGray),
+            )),
+            Some(Preview::Ready(image)) => {
+                for row in half_blocks(image, width as u32, PREVIEW_ROWS) {
+                    lines.push(Line::from(
+                        row.into_iter()
+                            .map(|([r, g, b], [lr, lg, lb])| {
+                                Span::styled(
+                                    "▀",
+                                    Style::default()
+                                        .fg(Color::Rgb(r, g, b))
+                                        .bg(Color::Rgb(lr, lg, lb)),
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                    ));
+                }
+            }
+            Some(Preview::Failed(message)) => lines.push(Line::styled(
+                message.clone(),
+                Style::default().fg(Color::Red),
+            )),
+            None => {}
+        }
+    }
+    lines
+}
+
+fn draw_detail(frame: &mut Frame, state: &TuiState, area: ratatui::layout::Rect) {
+    let lines = state
+        .selected()
+        .map(|tweet| {
+            let mut lines = detail_lines(tweet);
+            // 枠の分を除く
+            lines.extend(media_lines(tweet, state, area.width.saturating_sub(2)));
+            lines
+        })
+        .unwrap_or_default();
+    let detail = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(" detail "))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(detail, area);
+}
+
+fn status_bar(state: &TuiState) -> Paragraph<'static> {
+    let (mode, mode_style) = if state.paused {
+        (
+            " PAUSED ",
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        )
+    } else {
+        (" LIVE ", Style::default().fg(Color::Black).bg(Color::Green))
+    };
+    let last_poll = state
+        .last_poll
+        .map(|it| it.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    let mut spans = vec![
+        Span::styled(mode, mode_style),
+        Span::raw(format!(
+            " {} | last poll {} | {} tweets/min ",
+            state.query,
+            last_poll,
+            state.tweets_per_minute()
+        )),
+    ];
+    if let Some(limit) = &state.rate_limit {
+        // 残り 1 割を切ったら目立たせる
+        let style = if limit.remaining * 10 < limit.limit {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(
+            format!(
+                "quota {}/{} (reset {}) ",
+                limit.remaining,
+                limit.limit,
+                limit.reset.with_timezone(&chrono::Local).format("%H:%M")
+            ),
+            style,
+        ));
+    }
+    if let Some(event) = state.events.first() {
+        spans.push(Span::styled(
+            format!(
+                " ⚽ {} {} ",
+                event
+                    .detected_at
+                    .with_timezone(&chrono::Local)
+                    .format("%H:%M"),
+                event.summary()
+            ),
+            EVENT_STYLE,
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if let Some((at, message)) = &state.last_error {
+        spans.push(Span::styled(
+            format!(
+                "| errors {} ({} {}) ",
+                state.error_count,
+                at.format("%H:%M:%S"),
+                message.replace('\n', " ")
+            ),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    spans.push(Span::styled(HELP, Style::default().fg(Color::DarkGray)));
+    Paragraph::new(Line::from(spans))
+}
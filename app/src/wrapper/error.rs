@@ -98,6 +98,12 @@ impl ServiceError {
     }
 }
 
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:#}", self.error_type, self.inner)
+    }
+}
+
 // failure::Error can be treated as ServiceError
 impl IServiceError for Error {}
 